@@ -0,0 +1,15 @@
+-- Capacity per gameday
+ALTER TABLE Gameday ADD COLUMN IF NOT EXISTS max_skaters INT DEFAULT 20 NOT NULL;
+ALTER TABLE Gameday ADD COLUMN IF NOT EXISTS max_goalkeepers INT DEFAULT 2 NOT NULL;
+
+-- Waitlist for full gamedays, ordered by sign-up time
+CREATE TABLE IF NOT EXISTS Waitlist_Gameday (
+    player_id INT NOT NULL,
+    gameday_id INT NOT NULL,
+    joined_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (player_id, gameday_id),
+    FOREIGN KEY (player_id) REFERENCES Player(player_id) ON DELETE CASCADE,
+    FOREIGN KEY (gameday_id) REFERENCES Gameday(gameday_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_gameday_joined_at ON Waitlist_Gameday (gameday_id, joined_at);
@@ -0,0 +1,15 @@
+-- Earlier versions stored the admin's Europe/Stockholm wall-clock time as if it was UTC.
+-- Reinterpret those timestamps as local club time.
+--
+-- The zone is deliberately hardcoded instead of read from CLUB_TIMEZONE: every database
+-- created before this migration was entered in Stockholm time, whatever the club is set
+-- to now. Databases created from scratch have no rows here, so nothing is changed for
+-- clubs in other zones. A club that ran an earlier version somewhere else has to change
+-- the zone below before migrating.
+UPDATE Gameday
+SET start_date = (start_date AT TIME ZONE 'UTC') AT TIME ZONE 'Europe/Stockholm',
+    end_date = (end_date AT TIME ZONE 'UTC') AT TIME ZONE 'Europe/Stockholm';
+
+UPDATE Cup
+SET start_date = (start_date AT TIME ZONE 'UTC') AT TIME ZONE 'Europe/Stockholm',
+    end_date = (end_date AT TIME ZONE 'UTC') AT TIME ZONE 'Europe/Stockholm';
@@ -0,0 +1,9 @@
+-- Players that haven't seen that the time of a gameday they signed up for was changed
+CREATE TABLE IF NOT EXISTS Time_Change_Notice (
+    player_id INT NOT NULL,
+    gameday_id INT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (player_id, gameday_id),
+    FOREIGN KEY (player_id) REFERENCES Player(player_id) ON DELETE CASCADE,
+    FOREIGN KEY (gameday_id) REFERENCES Gameday(gameday_id) ON DELETE CASCADE
+);
@@ -0,0 +1,18 @@
+-- Recurring gamedays, times are wall-clock time in the club's timezone
+CREATE TABLE IF NOT EXISTS Gameday_Series (
+    series_id SERIAL PRIMARY KEY,
+    weekday SMALLINT NOT NULL CHECK (weekday BETWEEN 1 AND 7), -- ISO weekday, 1 = Monday
+    start_time TIME NOT NULL,
+    end_time TIME NOT NULL,
+    season_start DATE NOT NULL,
+    season_end DATE NOT NULL,
+    exceptions DATE[] DEFAULT '{}' NOT NULL,                   -- Cancelled occurrences
+    max_skaters INT DEFAULT 20 NOT NULL,
+    max_goalkeepers INT DEFAULT 2 NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+-- Past gamedays are kept when a series is deleted
+ALTER TABLE Gameday ADD COLUMN IF NOT EXISTS series_id INT REFERENCES Gameday_Series(series_id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_gameday_series_id ON Gameday (series_id);
@@ -0,0 +1,4 @@
+-- Secret token for the personal calendar feed
+ALTER TABLE Player ADD COLUMN IF NOT EXISTS calendar_token UUID DEFAULT gen_random_uuid() NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_player_calendar_token ON Player (calendar_token);
@@ -0,0 +1,4 @@
+-- Players can opt out of each category of email notifications
+ALTER TABLE Player ADD COLUMN IF NOT EXISTS notify_registration BOOLEAN DEFAULT TRUE NOT NULL;
+ALTER TABLE Player ADD COLUMN IF NOT EXISTS notify_waitlist BOOLEAN DEFAULT TRUE NOT NULL;
+ALTER TABLE Player ADD COLUMN IF NOT EXISTS notify_cancellation BOOLEAN DEFAULT TRUE NOT NULL;
@@ -0,0 +1,5 @@
+-- Reminders are sent once per gameday, moving the gameday sends a new one
+ALTER TABLE Gameday ADD COLUMN IF NOT EXISTS reminder_sent BOOLEAN DEFAULT FALSE NOT NULL;
+
+ALTER TABLE Player ADD COLUMN IF NOT EXISTS notify_reminder BOOLEAN DEFAULT TRUE NOT NULL;
+ALTER TABLE Player ADD COLUMN IF NOT EXISTS notify_goalkeeper_shortage BOOLEAN DEFAULT TRUE NOT NULL;
@@ -0,0 +1,2 @@
+-- Nonce sent in the authorization request, checked against the ID token
+ALTER TABLE pkce_store ADD COLUMN IF NOT EXISTS nonce TEXT DEFAULT '' NOT NULL;
@@ -0,0 +1,5 @@
+-- Roles replace the free text access group
+CREATE TYPE player_role AS ENUM ('player', 'organizer', 'cup_manager', 'admin');
+ALTER TABLE Player ADD COLUMN role player_role DEFAULT 'player' NOT NULL;
+UPDATE Player SET role = 'admin' WHERE access_group IN ('admin', 'super-admin');
+ALTER TABLE Player DROP COLUMN access_group;
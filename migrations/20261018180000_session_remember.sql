@@ -0,0 +1,3 @@
+-- "Remember me" sessions live longer, the choice is kept through the login redirect
+ALTER TABLE session ADD COLUMN IF NOT EXISTS remember BOOLEAN DEFAULT FALSE NOT NULL;
+ALTER TABLE pkce_store ADD COLUMN IF NOT EXISTS remember BOOLEAN DEFAULT FALSE NOT NULL;
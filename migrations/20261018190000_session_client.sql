@@ -0,0 +1,3 @@
+-- Shown in the list of active sessions so players can tell their devices apart
+ALTER TABLE session ADD COLUMN IF NOT EXISTS user_agent TEXT;
+ALTER TABLE session ADD COLUMN IF NOT EXISTS ip TEXT;
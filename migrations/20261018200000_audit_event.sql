@@ -0,0 +1,14 @@
+-- Who changed what, with the state before and after the change
+CREATE TABLE IF NOT EXISTS Audit_Event (
+    audit_event_id SERIAL PRIMARY KEY,
+    actor_id INT,                                 -- Player that made the change
+    action TEXT NOT NULL,                         -- E.g. 'roster.add'
+    target TEXT NOT NULL,                         -- E.g. 'gameday:12/player:5'
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    FOREIGN KEY (actor_id) REFERENCES Player(player_id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_event_created_at ON Audit_Event (created_at);
@@ -0,0 +1,6 @@
+-- Organizers can let a player play another position than usual on a single gameday
+ALTER TABLE Player_Gameday ADD COLUMN is_goalkeeper BOOLEAN;
+UPDATE Player_Gameday pg SET is_goalkeeper = p.is_goalkeeper
+FROM Player p
+WHERE pg.player_id = p.player_id;
+ALTER TABLE Player_Gameday ALTER COLUMN is_goalkeeper SET NOT NULL;
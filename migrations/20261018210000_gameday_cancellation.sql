@@ -0,0 +1,17 @@
+-- Marked on the day, NULL until the player has been checked
+CREATE TYPE attendance_status AS ENUM ('attended', 'no_show');
+ALTER TABLE Player_Gameday ADD COLUMN attendance attendance_status;
+
+-- Players giving up a confirmed spot, kept for the attendance statistics
+CREATE TABLE IF NOT EXISTS Gameday_Cancellation (
+    cancellation_id SERIAL PRIMARY KEY,
+    player_id INT NOT NULL,
+    gameday_id INT NOT NULL,
+    cancelled_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    late BOOLEAN NOT NULL,                        -- Closer to the start than the late cancellation window
+
+    FOREIGN KEY (player_id) REFERENCES Player(player_id) ON DELETE CASCADE,
+    FOREIGN KEY (gameday_id) REFERENCES Gameday(gameday_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_gameday_cancellation_player ON Gameday_Cancellation (player_id);
@@ -0,0 +1,7 @@
+-- Set by admins, used to balance the generated teams
+ALTER TABLE Player ADD COLUMN skill SMALLINT DEFAULT 3 NOT NULL CHECK (skill BETWEEN 1 AND 5);
+
+-- Generated lineups, only shown to the players once the organizer has locked them
+CREATE TYPE team_color AS ENUM ('light', 'dark');
+ALTER TABLE Player_Gameday ADD COLUMN team team_color;
+ALTER TABLE Gameday ADD COLUMN teams_locked BOOLEAN DEFAULT FALSE NOT NULL;
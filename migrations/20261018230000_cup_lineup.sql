@@ -0,0 +1,27 @@
+-- Teams and lines cup managers build from the players registered for a cup
+CREATE TABLE IF NOT EXISTS Cup_Team (
+    cup_team_id SERIAL PRIMARY KEY,
+    cup_id INT NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    UNIQUE (cup_team_id, cup_id),
+    FOREIGN KEY (cup_id) REFERENCES Cup(cup_id) ON DELETE CASCADE
+);
+
+CREATE TYPE lineup_position AS ENUM (
+    'left_wing', 'center', 'right_wing', 'left_defense', 'right_defense', 'goalkeeper'
+);
+
+-- A player leaving the cup is taken out of the lineup
+CREATE TABLE IF NOT EXISTS Cup_Lineup (
+    cup_team_id INT NOT NULL,
+    cup_id INT NOT NULL,
+    position lineup_position NOT NULL,
+    line SMALLINT NOT NULL CHECK (line > 0),
+    player_id INT NOT NULL,
+    PRIMARY KEY (cup_team_id, position, line),
+    UNIQUE (cup_id, player_id),
+    FOREIGN KEY (cup_team_id, cup_id) REFERENCES Cup_Team(cup_team_id, cup_id) ON DELETE CASCADE,
+    FOREIGN KEY (player_id, cup_id) REFERENCES Player_Cup(player_id, cup_id) ON DELETE CASCADE
+);
+
+ALTER TABLE Cup ADD COLUMN lineup_published BOOLEAN DEFAULT FALSE NOT NULL;
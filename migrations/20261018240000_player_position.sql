@@ -0,0 +1,43 @@
+-- Goalkeeper, defender or forward, used for cup registrations and the player's preference
+CREATE TYPE player_position AS ENUM ('goalkeeper', 'defender', 'forward');
+
+-- The cup form always sent the English names, other spellings were entered by hand
+UPDATE Player_Cup SET position = CASE lower(trim(position))
+    WHEN 'goalkeeper' THEN 'goalkeeper'
+    WHEN 'goalie' THEN 'goalkeeper'
+    WHEN 'målvakt' THEN 'goalkeeper'
+    WHEN 'mv' THEN 'goalkeeper'
+    WHEN 'defender' THEN 'defender'
+    WHEN 'defence' THEN 'defender'
+    WHEN 'defense' THEN 'defender'
+    WHEN 'back' THEN 'defender'
+    WHEN 'forward' THEN 'forward'
+    WHEN 'center' THEN 'forward'
+    WHEN 'centre' THEN 'forward'
+    WHEN 'wing' THEN 'forward'
+    WHEN 'anfallare' THEN 'forward'
+    ELSE position
+END;
+
+-- Anything else has to be fixed by hand, guessing would change someone's registration
+DO $$
+DECLARE
+    unknown TEXT;
+BEGIN
+    SELECT string_agg(DISTINCT quote_literal(position), ', ') INTO unknown
+    FROM Player_Cup
+    WHERE position NOT IN ('goalkeeper', 'defender', 'forward');
+
+    IF unknown IS NOT NULL THEN
+        RAISE EXCEPTION 'Unknown cup positions: %, update them in player_cup before migrating', unknown;
+    END IF;
+END $$;
+
+ALTER TABLE Player_Cup ALTER COLUMN position TYPE player_position USING position::player_position;
+
+-- The preferred position replaces the goalkeeper flag, which stays for the gameday queries
+ALTER TABLE Player ADD COLUMN position player_position DEFAULT 'forward' NOT NULL;
+UPDATE Player SET position = 'goalkeeper' WHERE is_goalkeeper;
+ALTER TABLE Player DROP COLUMN is_goalkeeper;
+ALTER TABLE Player ADD COLUMN is_goalkeeper BOOLEAN NOT NULL
+    GENERATED ALWAYS AS (position = 'goalkeeper') STORED;
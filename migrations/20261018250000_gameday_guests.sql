@@ -0,0 +1,14 @@
+-- Friends without a login that a registered player brings to a gameday
+CREATE TABLE IF NOT EXISTS Guest_Gameday (
+    guest_id SERIAL PRIMARY KEY,
+    gameday_id INT NOT NULL,
+    host_id INT NOT NULL,                         -- Registered player bringing the guest
+    name TEXT NOT NULL,
+    is_goalkeeper BOOLEAN DEFAULT FALSE NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    -- Guests leave together with their host
+    FOREIGN KEY (host_id, gameday_id) REFERENCES Player_Gameday(player_id, gameday_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_guest_gameday_gameday ON Guest_Gameday (gameday_id);
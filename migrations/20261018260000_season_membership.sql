@@ -0,0 +1,28 @@
+-- A season of play, members pay the fee once and everyone else per session.
+-- Amounts are whole kronor.
+CREATE TABLE IF NOT EXISTS Season (
+    season_id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    start_date DATE NOT NULL,                     -- Club's local dates, inclusive
+    end_date DATE NOT NULL,
+    fee INT DEFAULT 0 NOT NULL CHECK (fee >= 0),
+    drop_in_fee INT DEFAULT 0 NOT NULL CHECK (drop_in_fee >= 0),
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    CHECK (start_date <= end_date)
+);
+
+-- Set from the local date of the gameday, whenever seasons or gamedays change
+ALTER TABLE Gameday ADD COLUMN IF NOT EXISTS season_id INT REFERENCES Season(season_id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_gameday_season_id ON Gameday (season_id);
+
+CREATE TABLE IF NOT EXISTS Membership (
+    season_id INT NOT NULL,
+    player_id INT NOT NULL,
+    amount INT NOT NULL CHECK (amount >= 0),      -- What the player owes for the season
+    paid_at TIMESTAMPTZ,                          -- NULL until paid
+    PRIMARY KEY (season_id, player_id),
+    FOREIGN KEY (season_id) REFERENCES Season(season_id) ON DELETE CASCADE,
+    FOREIGN KEY (player_id) REFERENCES Player(player_id) ON DELETE CASCADE
+);
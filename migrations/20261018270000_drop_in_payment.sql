@@ -0,0 +1,10 @@
+-- Drop-in fees paid by players without a membership of the gameday's season
+CREATE TABLE IF NOT EXISTS Drop_In_Payment (
+    gameday_id INT NOT NULL,
+    player_id INT NOT NULL,
+    amount INT NOT NULL CHECK (amount >= 0),
+    paid_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (gameday_id, player_id),
+    FOREIGN KEY (gameday_id) REFERENCES Gameday(gameday_id) ON DELETE CASCADE,
+    FOREIGN KEY (player_id) REFERENCES Player(player_id) ON DELETE CASCADE
+);
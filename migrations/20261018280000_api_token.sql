@@ -0,0 +1,12 @@
+-- Personal tokens for the JSON API, only a hash of the token is stored
+CREATE TABLE IF NOT EXISTS Api_Token (
+    api_token_id SERIAL PRIMARY KEY,
+    player_id INT NOT NULL,
+    name TEXT NOT NULL,                           -- What the token is used for
+    token_hash TEXT NOT NULL UNIQUE,              -- Hex encoded SHA-256 of the token
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    last_used_at TIMESTAMPTZ,
+    FOREIGN KEY (player_id) REFERENCES Player(player_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_token_player ON Api_Token (player_id);
@@ -0,0 +1,3 @@
+-- Guests hold a spot like players, so they are put in a team and checked in too
+ALTER TABLE Guest_Gameday ADD COLUMN team team_color;
+ALTER TABLE Guest_Gameday ADD COLUMN attendance attendance_status;
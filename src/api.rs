@@ -0,0 +1,660 @@
+#![cfg(feature = "ssr")]
+//! Versioned JSON API for scripts and bots, served under `/api/v1`. Callers authenticate
+//! with a personal API token from the profile page as `Authorization: Bearer <token>`,
+//! or with the session cookie of a logged in browser. The OpenAPI schema is served at
+//! `/api/v1/openapi.json`.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tower_cookies::Cookies;
+use tracing::{error, info};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+
+use crate::{
+    auth::{get_player_by_api_token, get_player_by_session, read_session_cookie},
+    components::{
+        drop_in::drop_in_payment,
+        join_button::{
+            gamedays_by_player, notify_joined, register_player, RegistrationError, SignUp,
+        },
+        join_cup_form::register_cup_player,
+        leave_button::{notify_left, notify_promoted, unregister_player, LeaveError},
+        leave_cup_button::unregister_cup_player,
+    },
+    database::get_db,
+    models::{
+        Attendance, Cup, CupPlayer, DropInPayment, Gameday, Guest, Player, Position, WaitlistPlayer,
+    },
+    pages::cup_page::cups_by_player,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Gubbhockey API", version = "1"),
+    servers((url = "/api/v1")),
+    paths(
+        get_me,
+        get_my_registrations,
+        list_gamedays,
+        get_gameday,
+        join_gameday,
+        leave_gameday,
+        list_cups,
+        get_cup,
+        join_cup,
+        leave_cup,
+    ),
+    modifiers(&SecurityAddon),
+    security(("api_token" = []), ("session_cookie" = [])),
+    tags(
+        (name = "players", description = "The calling player"),
+        (name = "gamedays", description = "Gamedays and registrations for them"),
+        (name = "cups", description = "Cups and registrations for them"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("Personal API token from the profile page"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "session_cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("session_id"))),
+        );
+    }
+}
+
+/// The API routes, nested under `/api/v1` in the server. Changes go through PUT and
+/// DELETE, which browsers don't send cross-site without a preflight, so the session
+/// cookie can't be used for cross-site requests.
+pub fn router<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/me", get(get_me))
+        .route("/me/registrations", get(get_my_registrations))
+        .route("/gamedays", get(list_gamedays))
+        .route("/gamedays/:gameday_id", get(get_gameday))
+        .route(
+            "/gamedays/:gameday_id/registration",
+            put(join_gameday).delete(leave_gameday),
+        )
+        .route("/cups", get(list_cups))
+        .route("/cups/:cup_id", get(get_cup))
+        .route(
+            "/cups/:cup_id/registration",
+            put(join_cup).delete(leave_cup),
+        )
+        .fallback(|| async { ApiError::NotFound("No such endpoint.") })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("No valid session or API token.")]
+    Unauthorized,
+    #[error("{0}")]
+    NotFound(&'static str),
+    #[error("{0}")]
+    Conflict(&'static str),
+    #[error("Internal server error.")]
+    Internal,
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        error!("Database error: {:?}", e);
+        ApiError::Internal
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = ErrorResponse {
+            error: self.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// The calling player, from the API token or else the session cookie.
+pub struct ApiPlayer(pub Player);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ApiPlayer {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let bearer = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        let player = match bearer {
+            Some(token) => get_player_by_api_token(token.trim()).await?,
+            None => {
+                let cookies = Cookies::from_request_parts(parts, state)
+                    .await
+                    .map_err(|_| ApiError::Unauthorized)?;
+                match read_session_cookie(&cookies) {
+                    Some(cookie) => get_player_by_session(cookie.session_id)
+                        .await
+                        .map_err(|_| ApiError::Internal)?,
+                    None => None,
+                }
+            }
+        };
+        player.map(ApiPlayer).ok_or(ApiError::Unauthorized)
+    }
+}
+
+/// A player with a confirmed spot on a gameday.
+#[derive(Serialize, ToSchema)]
+pub struct RegisteredPlayer {
+    pub player_id: i32,
+    pub name: String,
+    pub is_goalkeeper: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GamedayDetails {
+    pub gameday: Gameday,
+    pub players: Vec<RegisteredPlayer>,
+    pub guests: Vec<Guest>,
+    pub waitlist: Vec<WaitlistPlayer>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CupDetails {
+    pub cup: Cup,
+    pub players: Vec<CupPlayer>,
+}
+
+/// Gamedays and cups the player is registered for, newest first.
+#[derive(Serialize, ToSchema)]
+pub struct Registrations {
+    pub gamedays: Vec<Gameday>,
+    pub cups: Vec<Cup>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GamedayRegistration {
+    /// False when the gameday is full and the player is on the waitlist
+    pub confirmed: bool,
+    /// The Swish payment request when the player owes a drop-in fee for the gameday
+    pub drop_in_payment: Option<DropInPayment>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CupRegistration {
+    pub position: Position,
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// The calling player.
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "players",
+    responses(
+        (status = 200, body = Player),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+    )
+)]
+async fn get_me(ApiPlayer(player): ApiPlayer) -> Json<Player> {
+    Json(player)
+}
+
+/// Gamedays and cups the calling player is registered for.
+#[utoipa::path(
+    get,
+    path = "/me/registrations",
+    tag = "players",
+    responses(
+        (status = 200, body = Registrations),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+    )
+)]
+async fn get_my_registrations(
+    ApiPlayer(player): ApiPlayer,
+) -> Result<Json<Registrations>, ApiError> {
+    let (gamedays, cups) = tokio::try_join!(
+        gamedays_by_player(player.player_id),
+        cups_by_player(player.player_id)
+    )?;
+    Ok(Json(Registrations { gamedays, cups }))
+}
+
+/// Upcoming gamedays, the next first.
+#[utoipa::path(
+    get,
+    path = "/gamedays",
+    tag = "gamedays",
+    responses(
+        (status = 200, body = Vec<Gameday>),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+    )
+)]
+async fn list_gamedays(_: ApiPlayer) -> Result<Json<Vec<Gameday>>, ApiError> {
+    let gamedays = sqlx::query_as!(
+        Gameday,
+        r#"
+        SELECT
+            g.gameday_id,
+            g.start_date,
+            g.end_date,
+            (
+                SELECT COUNT(*) FROM player_gameday pg
+                WHERE pg.gameday_id = g.gameday_id
+            ) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id
+            ) as player_count,
+            g.max_skaters,
+            g.max_goalkeepers,
+            NULL::bigint as queue_position,
+            FALSE as "time_changed!",
+            FALSE as "late_cancellation!"
+        FROM gameday g
+        WHERE g.start_date >= NOW()
+        ORDER BY g.start_date ASC
+        "#
+    )
+    .fetch_all(get_db())
+    .await?;
+    Ok(Json(gamedays))
+}
+
+/// A gameday with its players, guests and waitlist.
+#[utoipa::path(
+    get,
+    path = "/gamedays/{gameday_id}",
+    tag = "gamedays",
+    params(("gameday_id" = i32, Path)),
+    responses(
+        (status = 200, body = GamedayDetails),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+        (status = 404, description = "No such gameday", body = ErrorResponse),
+    )
+)]
+async fn get_gameday(
+    _: ApiPlayer,
+    Path(gameday_id): Path<i32>,
+) -> Result<Json<GamedayDetails>, ApiError> {
+    let pool = get_db();
+    let gameday = sqlx::query_as!(
+        Gameday,
+        r#"
+        SELECT
+            g.gameday_id,
+            g.start_date,
+            g.end_date,
+            (
+                SELECT COUNT(*) FROM player_gameday pg
+                WHERE pg.gameday_id = g.gameday_id
+            ) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id
+            ) as player_count,
+            g.max_skaters,
+            g.max_goalkeepers,
+            NULL::bigint as queue_position,
+            FALSE as "time_changed!",
+            FALSE as "late_cancellation!"
+        FROM gameday g
+        WHERE g.gameday_id = $1
+        "#,
+        gameday_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    let Some(gameday) = gameday else {
+        return Err(ApiError::NotFound("No such gameday."));
+    };
+
+    let players = sqlx::query_as!(
+        RegisteredPlayer,
+        r#"
+        SELECT p.player_id, p.name, pg.is_goalkeeper
+        FROM player_gameday pg
+        JOIN player p ON pg.player_id = p.player_id
+        WHERE pg.gameday_id = $1
+        ORDER BY p.name
+        "#,
+        gameday_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let guests = sqlx::query_as!(
+        Guest,
+        r#"
+        SELECT
+            gg.guest_id,
+            gg.name,
+            gg.is_goalkeeper,
+            gg.host_id,
+            p.name as host_name,
+            NULL::attendance_status as "attendance: Attendance"
+        FROM guest_gameday gg
+        JOIN player p ON gg.host_id = p.player_id
+        WHERE gg.gameday_id = $1
+        ORDER BY gg.created_at
+        "#,
+        gameday_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let waitlist = sqlx::query_as!(
+        WaitlistPlayer,
+        r#"
+        SELECT
+            p.player_id,
+            p.name,
+            p.is_goalkeeper,
+            ROW_NUMBER() OVER (
+                PARTITION BY p.is_goalkeeper
+                ORDER BY wg.joined_at
+            ) AS queue_position
+        FROM waitlist_gameday wg
+        JOIN player p ON wg.player_id = p.player_id
+        WHERE wg.gameday_id = $1
+        ORDER BY wg.joined_at ASC
+        "#,
+        gameday_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Json(GamedayDetails {
+        gameday,
+        players,
+        guests,
+        waitlist,
+    }))
+}
+
+/// Registers the calling player in the preferred position, on the waitlist when the
+/// gameday is full.
+#[utoipa::path(
+    put,
+    path = "/gamedays/{gameday_id}/registration",
+    tag = "gamedays",
+    params(("gameday_id" = i32, Path)),
+    responses(
+        (status = 200, body = GamedayRegistration),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+        (status = 404, description = "No such gameday", body = ErrorResponse),
+        (status = 409, description = "Already registered or the gameday has started", body = ErrorResponse),
+    )
+)]
+async fn join_gameday(
+    ApiPlayer(player): ApiPlayer,
+    Path(gameday_id): Path<i32>,
+) -> Result<Json<GamedayRegistration>, ApiError> {
+    let SignUp {
+        confirmed,
+        promoted,
+    } = match register_player(player.player_id, player.is_goalkeeper(), gameday_id).await {
+        Ok(sign_up) => sign_up,
+        Err(RegistrationError::Started) => {
+            return Err(ApiError::Conflict("The gameday has already started."))
+        }
+        Err(RegistrationError::AlreadyRegistered) => {
+            return Err(ApiError::Conflict("Already registered."))
+        }
+        Err(RegistrationError::Database(sqlx::Error::RowNotFound)) => {
+            return Err(ApiError::NotFound("No such gameday."))
+        }
+        Err(RegistrationError::Database(e)) => return Err(e.into()),
+    };
+
+    info!(
+        "Player: {:?} joined {:?} through the API, confirmed: {:?}",
+        player.player_id, gameday_id, confirmed
+    );
+    notify_joined(player.player_id, gameday_id, confirmed);
+    notify_promoted(gameday_id, promoted);
+
+    // The spot is taken either way, so a failed payment request isn't an error
+    let payment = if confirmed {
+        drop_in_payment(player.player_id, gameday_id)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Failed to create payment request: {:?}", e);
+                None
+            })
+    } else {
+        None
+    };
+    Ok(Json(GamedayRegistration {
+        confirmed,
+        drop_in_payment: payment,
+    }))
+}
+
+/// Unregisters the calling player from the gameday or its waitlist.
+#[utoipa::path(
+    delete,
+    path = "/gamedays/{gameday_id}/registration",
+    tag = "gamedays",
+    params(("gameday_id" = i32, Path)),
+    responses(
+        (status = 204, description = "Unregistered"),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+        (status = 404, description = "Not registered for the gameday", body = ErrorResponse),
+        (status = 409, description = "Too late to leave, ask an organizer", body = ErrorResponse),
+    )
+)]
+async fn leave_gameday(
+    ApiPlayer(player): ApiPlayer,
+    Path(gameday_id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let promoted = match unregister_player(player.player_id, gameday_id).await {
+        Ok(promoted) => promoted,
+        Err(LeaveError::NotRegistered) => {
+            return Err(ApiError::NotFound("Not registered for the gameday."))
+        }
+        Err(LeaveError::Closed) => {
+            return Err(ApiError::Conflict(
+                "It is too late to leave the gameday, ask an organizer.",
+            ))
+        }
+        Err(LeaveError::Database(e)) => return Err(e.into()),
+    };
+
+    info!(
+        "Player: {:?} left gameday: {:?} through the API",
+        player.player_id, gameday_id
+    );
+    notify_left(player.player_id, gameday_id, promoted);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Upcoming cups, the next first.
+#[utoipa::path(
+    get,
+    path = "/cups",
+    tag = "cups",
+    responses(
+        (status = 200, body = Vec<Cup>),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+    )
+)]
+async fn list_cups(_: ApiPlayer) -> Result<Json<Vec<Cup>>, ApiError> {
+    let cups = sqlx::query_as!(
+        Cup,
+        r#"
+        SELECT
+            c.cup_id,
+            c.start_date,
+            c.end_date,
+            c.title,
+            c.info,
+            (
+                SELECT COUNT(*) FROM player_cup pc
+                WHERE pc.cup_id = c.cup_id
+            ) as player_count
+        FROM cup c
+        WHERE c.start_date >= NOW()
+        ORDER BY c.start_date ASC
+        "#
+    )
+    .fetch_all(get_db())
+    .await?;
+    Ok(Json(cups))
+}
+
+/// A cup with its players.
+#[utoipa::path(
+    get,
+    path = "/cups/{cup_id}",
+    tag = "cups",
+    params(("cup_id" = i32, Path)),
+    responses(
+        (status = 200, body = CupDetails),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+        (status = 404, description = "No such cup", body = ErrorResponse),
+    )
+)]
+async fn get_cup(_: ApiPlayer, Path(cup_id): Path<i32>) -> Result<Json<CupDetails>, ApiError> {
+    let pool = get_db();
+    let cup = sqlx::query_as!(
+        Cup,
+        r#"
+        SELECT
+            c.cup_id,
+            c.start_date,
+            c.end_date,
+            c.title,
+            c.info,
+            (
+                SELECT COUNT(*) FROM player_cup pc
+                WHERE pc.cup_id = c.cup_id
+            ) as player_count
+        FROM cup c
+        WHERE c.cup_id = $1
+        "#,
+        cup_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    let Some(cup) = cup else {
+        return Err(ApiError::NotFound("No such cup."));
+    };
+
+    let players = sqlx::query_as!(
+        CupPlayer,
+        r#"
+        SELECT p.name, pc.position as "position: Position"
+        FROM player_cup pc
+        JOIN player p ON pc.player_id = p.player_id
+        WHERE pc.cup_id = $1
+        ORDER BY p.name
+        "#,
+        cup_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Json(CupDetails { cup, players }))
+}
+
+/// Registers the calling player on the cup.
+#[utoipa::path(
+    put,
+    path = "/cups/{cup_id}/registration",
+    tag = "cups",
+    params(("cup_id" = i32, Path)),
+    request_body = CupRegistration,
+    responses(
+        (status = 204, description = "Registered"),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+        (status = 404, description = "No such cup", body = ErrorResponse),
+        (status = 409, description = "Already registered", body = ErrorResponse),
+    )
+)]
+async fn join_cup(
+    ApiPlayer(player): ApiPlayer,
+    Path(cup_id): Path<i32>,
+    Json(registration): Json<CupRegistration>,
+) -> Result<StatusCode, ApiError> {
+    match register_cup_player(player.player_id, cup_id, registration.position).await {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+            return Err(ApiError::NotFound("No such cup."))
+        }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(ApiError::Conflict("Already registered."))
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    info!(
+        "Player: {:?} joined cup: {:?} through the API",
+        player.player_id, cup_id
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Unregisters the calling player from the cup.
+#[utoipa::path(
+    delete,
+    path = "/cups/{cup_id}/registration",
+    tag = "cups",
+    params(("cup_id" = i32, Path)),
+    responses(
+        (status = 204, description = "Unregistered"),
+        (status = 401, description = "No valid session or API token", body = ErrorResponse),
+        (status = 404, description = "Not registered for the cup", body = ErrorResponse),
+    )
+)]
+async fn leave_cup(
+    ApiPlayer(player): ApiPlayer,
+    Path(cup_id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    if !unregister_cup_player(player.player_id, cup_id).await? {
+        return Err(ApiError::NotFound("Not registered for the cup."));
+    }
+
+    info!(
+        "Player: {:?} left cup: {:?} through the API",
+        player.player_id, cup_id
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,4 +1,4 @@
-use leptos::{prelude::*, task::spawn_local};
+use leptos::prelude::*;
 use leptos_meta::{provide_meta_context, Link, MetaTags, Stylesheet, Title};
 use leptos_router::{
     components::{Route, Router, Routes},
@@ -7,14 +7,18 @@ use leptos_router::{
 use reactive_stores::Store;
 
 use crate::{
-    auth::{user_from_session, validate_admin},
-    components::{footer::Footer, header::Header, not_found::NotFound},
-    models::{GlobalState, GlobalStateStoreFields},
+    auth::user_from_session,
+    components::{
+        footer::Footer, header::Header, leave_button::get_cancellation_policy, not_found::NotFound,
+    },
+    models::{CancellationPolicy, GlobalState, GlobalStateStoreFields, Role},
     pages::{
-        admin_page::AdminPage, auth_page::AuthPage, create_page::CreatePage, cup_page::CupPage,
-        day_page::DayPage, edit_cup_page::EditCupPage, homepage::HomePage,
-        profile_page::ProfilePage, terms_page::TermsPage,
+        admin_page::AdminPage, audit_page::AuditPage, auth_page::AuthPage,
+        check_in_page::CheckInPage, create_page::CreatePage, cup_page::CupPage, day_page::DayPage,
+        edit_cup_page::EditCupPage, edit_day_page::EditDayPage, homepage::HomePage,
+        profile_page::ProfilePage, season_page::SeasonPage, terms_page::TermsPage,
     },
+    timezone::club_timezone,
 };
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
@@ -24,6 +28,7 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
             <head>
                 <meta charset="utf-8" />
                 <meta name="viewport" content="width=device-width, initial-scale=1" />
+                <meta name="club-timezone" content=club_timezone().name() />
                 <AutoReload options=options.clone() />
                 <HydrationScripts options />
                 <MetaTags />
@@ -39,7 +44,8 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
 pub fn App() -> impl IntoView {
     let store = Store::new(GlobalState {
         logged_in: false,
-        is_admin: false,
+        role: Role::Player,
+        cancellation_policy: CancellationPolicy::default(),
     });
     provide_context(store);
 
@@ -52,19 +58,18 @@ pub fn App() -> impl IntoView {
         if let Some(Ok(player_data)) = player.get() {
             // set_loggedin.set(true);
             store.logged_in().set(true);
-            if let Some(access_group) = player_data.access_group {
-                if access_group == *"admin" {
-                    spawn_local(async move {
-                        if let Ok(admin) = validate_admin().await {
-                            store.is_admin().set(admin);
-                        }
-                    });
-                }
-            }
+            store.role().set(player_data.role);
         }
     });
     provide_context(player);
 
+    let cancellation_policy = Resource::new(|| (), |_| get_cancellation_policy());
+    Effect::new(move |_| {
+        if let Some(Ok(policy)) = cancellation_policy.get() {
+            store.cancellation_policy().set(policy);
+        }
+    });
+
     let website = view! {
         // injects a stylesheet into the document <head>
         // id=leptos means cargo-leptos will hot-reload this stylesheet
@@ -86,7 +91,11 @@ pub fn App() -> impl IntoView {
                             <Route path=path!("/auth") view=AuthPage />
                             <Route path=path!("/create") view=CreatePage />
                             <Route path=path!("/admin") view=AdminPage />
+                            <Route path=path!("/admin/audit") view=AuditPage />
+                            <Route path=path!("/admin/seasons") view=SeasonPage />
                             <Route path=path!("/day/:id") view=DayPage />
+                            <Route path=path!("/day/edit/:id") view=EditDayPage />
+                            <Route path=path!("/day/checkin/:id") view=CheckInPage />
                             <Route path=path!("/cup/:id") view=CupPage />
                             <Route path=path!("/cup/edit/:id") view=EditCupPage />
                             <Route path=path!("/terms") view=TermsPage />
@@ -0,0 +1,36 @@
+#![cfg(feature = "ssr")]
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgExecutor;
+
+/// Records a change in the audit log. `before` and `after` hold the state of the target,
+/// `None` when it didn't exist before or doesn't exist after the change.
+pub async fn record_event<'e>(
+    executor: impl PgExecutor<'e>,
+    actor_id: i32,
+    action: &str,
+    target: &str,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_event (actor_id, action, target, before, after)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        actor_id,
+        action,
+        target,
+        before,
+        after
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// The state of an audited row, `None` if it can't be serialized.
+pub fn snapshot<T: Serialize>(value: &T) -> Option<Value> {
+    serde_json::to_value(value).ok()
+}
@@ -1,33 +1,272 @@
-use crate::models::{PkceStore, Player};
+#[cfg(feature = "ssr")]
+use crate::models::PkceStore;
+use crate::models::Player;
 use leptos::prelude::*;
 use leptos::{prelude::ServerFnError, server};
-use std::env;
+/// Login settings, checked once at startup.
+#[cfg(feature = "ssr")]
+#[derive(Clone, Debug)]
+pub struct AuthConfig {
+    pub issuer_url: openidconnect::IssuerUrl,
+    pub client_id: openidconnect::ClientId,
+    pub client_secret: openidconnect::ClientSecret,
+    pub redirect_url: openidconnect::RedirectUrl,
+    pub logout_url: openidconnect::url::Url,
+    pub userinfo_url: Option<openidconnect::UserInfoUrl>,
+}
 
-#[server]
-pub async fn get_auth_url() -> Result<(), ServerFnError> {
-    use oauth2::{
-        basic::BasicClient, AuthUrl, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge,
-        RedirectUrl, Scope, TokenUrl,
+#[cfg(feature = "ssr")]
+#[derive(thiserror::Error, Debug)]
+pub enum AuthConfigError {
+    #[error("{0} must be set")]
+    Missing(&'static str),
+    #[error("{0} is not a valid URL: {1}")]
+    InvalidUrl(&'static str, openidconnect::url::ParseError),
+    #[error("OIDC discovery failed: {0}")]
+    Discovery(String),
+}
+
+#[cfg(feature = "ssr")]
+impl AuthConfig {
+    pub fn new(settings: &crate::config::AuthSettings) -> Result<Self, AuthConfigError> {
+        use openidconnect::{
+            url::{ParseError, Url},
+            ClientId, ClientSecret, IssuerUrl, RedirectUrl, UserInfoUrl,
+        };
+
+        fn required(name: &'static str, value: &str) -> Result<String, AuthConfigError> {
+            Some(value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .ok_or(AuthConfigError::Missing(name))
+        }
+        // Keeps the value as written, the issuer has to match the discovery document exactly
+        fn url<T>(
+            name: &'static str,
+            value: &str,
+            new: fn(String) -> Result<T, ParseError>,
+        ) -> Result<T, AuthConfigError> {
+            new(required(name, value)?).map_err(|e| AuthConfigError::InvalidUrl(name, e))
+        }
+
+        Ok(AuthConfig {
+            issuer_url: url(
+                "auth.issuer_url (OIDC_ISSUER_URL)",
+                &settings.issuer_url,
+                IssuerUrl::new,
+            )?,
+            client_id: ClientId::new(required(
+                "auth.client_id (OAUTH_CLIENT_ID)",
+                &settings.client_id,
+            )?),
+            client_secret: ClientSecret::new(required(
+                "auth.client_secret (OAUTH_CLIENT_SECRET)",
+                settings.client_secret.expose(),
+            )?),
+            redirect_url: url(
+                "auth.redirect_url (OAUTH_REDIRECT_URL)",
+                &settings.redirect_url,
+                RedirectUrl::new,
+            )?,
+            logout_url: url(
+                "auth.logout_url (OAUTH_LOGOUT_URL)",
+                &settings.logout_url,
+                |value| Url::parse(&value),
+            )?,
+            userinfo_url: match &settings.userinfo_url {
+                Some(userinfo_url) => Some(url(
+                    "auth.userinfo_url (OIDC_USERINFO_URL)",
+                    userinfo_url,
+                    UserInfoUrl::new,
+                )?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// How long the discovered endpoints and signing keys are used before they are fetched
+/// again, so keys the provider adds or retires are picked up without a restart.
+#[cfg(feature = "ssr")]
+const DISCOVERY_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// The OIDC client shared by all requests, provided to server functions as context.
+#[cfg(feature = "ssr")]
+#[derive(Clone)]
+pub struct AuthClient {
+    pub config: AuthConfig,
+    discovered: std::sync::Arc<std::sync::RwLock<Discovered>>,
+}
+
+#[cfg(feature = "ssr")]
+struct Discovered {
+    client: openidconnect::core::CoreClient,
+    at: std::time::Instant,
+}
+
+#[cfg(feature = "ssr")]
+impl AuthClient {
+    /// Discovers the endpoints and signing keys from the issuer's
+    /// `.well-known/openid-configuration`, so any compliant provider works.
+    pub async fn discover(config: AuthConfig) -> Result<Self, AuthConfigError> {
+        use std::sync::{Arc, RwLock};
+        use std::time::Instant;
+
+        let client = discover_client(&config).await?;
+        Ok(AuthClient {
+            config,
+            discovered: Arc::new(RwLock::new(Discovered {
+                client,
+                at: Instant::now(),
+            })),
+        })
+    }
+
+    /// The client of the latest discovery, discovered again when it is older than
+    /// `DISCOVERY_TTL`. If the provider can't be reached the previous one is kept.
+    pub async fn client(&self) -> openidconnect::core::CoreClient {
+        use tracing::error;
+
+        let (client, expired) = {
+            let discovered = self.discovered.read().unwrap_or_else(|e| e.into_inner());
+            (
+                discovered.client.clone(),
+                discovered.at.elapsed() > DISCOVERY_TTL,
+            )
+        };
+        if !expired {
+            return client;
+        }
+        match self.refresh().await {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Keeping the previous OIDC configuration: {}", e);
+                client
+            }
+        }
+    }
+
+    /// Fetches the discovery document and the signing keys again.
+    pub async fn refresh(&self) -> Result<openidconnect::core::CoreClient, AuthConfigError> {
+        use std::time::Instant;
+
+        let client = discover_client(&self.config).await?;
+        *self.discovered.write().unwrap_or_else(|e| e.into_inner()) = Discovered {
+            client: client.clone(),
+            at: Instant::now(),
+        };
+        Ok(client)
+    }
+
+    /// Checks the ID token of a token response: the signature against the provider's
+    /// JWKS, the issuer, audience, expiry, that the token was issued for this login attempt
+    /// and, when the provider includes it, the hash of the access token. A token signed
+    /// with a key that isn't known yet fetches the keys again, the provider may have
+    /// rotated them.
+    pub async fn verify_id_token(
+        &self,
+        token_response: &openidconnect::core::CoreTokenResponse,
+        nonce: &openidconnect::Nonce,
+    ) -> Result<openidconnect::core::CoreIdTokenClaims, IdTokenError> {
+        use openidconnect::{ClaimsVerificationError, SignatureVerificationError};
+        use tracing::info;
+
+        match verify_id_token(&self.client().await, token_response, nonce) {
+            Err(IdTokenError::Claims(ClaimsVerificationError::SignatureVerification(
+                SignatureVerificationError::NoMatchingKey,
+            ))) => {
+                info!("ID token signed with an unknown key, fetching the signing keys again");
+                let client = self.refresh().await?;
+                verify_id_token(&client, token_response, nonce)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn discover_client(
+    config: &AuthConfig,
+) -> Result<openidconnect::core::CoreClient, AuthConfigError> {
+    use openidconnect::{
+        core::{CoreClient, CoreProviderMetadata},
+        reqwest::async_http_client,
     };
     use tracing::info;
 
-    // Create an OAuth2 client by specifying the client ID, client secret, authorization URL and
-    // token URL.
-    let client = BasicClient::new(
-        ClientId::new(env::var("OAUTH_CLIENT_ID")?),
-        Some(ClientSecret::new(env::var("OAUTH_CLIENT_SECRET")?)),
-        AuthUrl::new(env::var("OAUTH_AUTH_URL")?)?,
-        Some(TokenUrl::new(env::var("OAUTH_TOKEN_URL")?)?),
+    info!("Discovering OIDC provider {}", config.issuer_url.as_str());
+    let mut provider_metadata =
+        CoreProviderMetadata::discover_async(config.issuer_url.clone(), async_http_client)
+            .await
+            .map_err(|e| AuthConfigError::Discovery(e.to_string()))?;
+    if let Some(userinfo_url) = &config.userinfo_url {
+        provider_metadata = provider_metadata.set_userinfo_endpoint(Some(userinfo_url.clone()));
+    }
+
+    Ok(CoreClient::from_provider_metadata(
+        provider_metadata,
+        config.client_id.clone(),
+        Some(config.client_secret.clone()),
     )
     // Set the URL the user will be redirected to after the authorization process.
-    .set_redirect_uri(RedirectUrl::new(env::var("OAUTH_REDIRECT_URL")?)?);
+    .set_redirect_uri(config.redirect_url.clone()))
+}
+
+#[cfg(feature = "ssr")]
+fn verify_id_token(
+    client: &openidconnect::core::CoreClient,
+    token_response: &openidconnect::core::CoreTokenResponse,
+    nonce: &openidconnect::Nonce,
+) -> Result<openidconnect::core::CoreIdTokenClaims, IdTokenError> {
+    use openidconnect::{AccessTokenHash, OAuth2TokenResponse, TokenResponse};
+
+    let id_token = token_response.id_token().ok_or(IdTokenError::Missing)?;
+    let claims = id_token.claims(&client.id_token_verifier(), nonce)?;
+
+    if let Some(expected_hash) = claims.access_token_hash() {
+        let actual_hash =
+            AccessTokenHash::from_token(token_response.access_token(), &id_token.signing_alg()?)?;
+        if actual_hash != *expected_hash {
+            return Err(IdTokenError::AccessTokenHash);
+        }
+    }
+    Ok(claims.clone())
+}
+
+/// Why the ID token of a login was rejected.
+#[cfg(feature = "ssr")]
+#[derive(thiserror::Error, Debug)]
+pub enum IdTokenError {
+    #[error("No ID token in token response")]
+    Missing,
+    #[error("Invalid ID token: {0}")]
+    Claims(#[from] openidconnect::ClaimsVerificationError),
+    #[error("Unsupported ID token signature: {0}")]
+    Signing(#[from] openidconnect::SignatureVerificationError),
+    #[error("Could not hash the access token: {0}")]
+    Hash(#[from] openidconnect::SigningError),
+    #[error("Access token hash does not match the ID token")]
+    AccessTokenHash,
+    #[error(transparent)]
+    Discovery(#[from] AuthConfigError),
+}
+
+#[server]
+pub async fn get_auth_url(remember: bool) -> Result<(), ServerFnError> {
+    use openidconnect::{core::CoreAuthenticationFlow, CsrfToken, Nonce, PkceCodeChallenge, Scope};
+    use tracing::info;
+
+    let client = expect_context::<AuthClient>().client().await;
 
     // Generate a PKCE challenge.
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
     // Generate the full authorization URL.
-    let (auth_url, csrf_token) = client
-        .authorize_url(CsrfToken::new_random)
+    let (auth_url, csrf_token, nonce) = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
         // Set the desired scopes.
         .add_scope(Scope::new("openid".to_string()))
         .add_scope(Scope::new("profile".to_string()))
@@ -39,6 +278,8 @@ pub async fn get_auth_url() -> Result<(), ServerFnError> {
     store_pkce_verifier(
         csrf_token.secret().to_string(),
         pkce_verifier.secret().to_string(),
+        nonce.secret().to_string(),
+        remember,
     )
     .await?;
 
@@ -47,117 +288,300 @@ pub async fn get_auth_url() -> Result<(), ServerFnError> {
     Ok(())
 }
 
+/// The player of the current session, used by the UI to show who is logged in.
+/// Anonymous visitors get an error but the response status is left alone.
 #[server]
 pub async fn user_from_session() -> Result<Player, ServerFnError> {
-    use http::StatusCode;
+    session_player()
+        .await?
+        .ok_or_else(|| ServerFnError::ServerError("No user found".to_string()))
+}
+
+#[cfg(feature = "ssr")]
+async fn session_player() -> Result<Option<Player>, ServerFnError> {
     use leptos_axum::extract;
     use tower_cookies::Cookies;
     use tracing::{error, info};
-    use uuid::Uuid;
 
     info!("Getting user from session");
-    if let Some(cookies) = extract::<Cookies>().await.ok() {
-        if let Some(session_id) = cookies.get("session_id") {
-            match Uuid::parse_str(session_id.value()) {
-                Ok(uuid) => match get_player_by_session(uuid).await? {
-                    Some(player) => return Ok(player),
-                    None => {
-                        error!("No player with session {:?} found", uuid);
-
-                        let opts = expect_context::<leptos_axum::ResponseOptions>();
-                        opts.set_status(StatusCode::UNAUTHORIZED);
-                        return Err(ServerFnError::ServerError("Unauthorized".to_string()));
-                    }
-                },
-                Err(_) => {
-                    error!("Invalid session_id: {:?}", session_id.value());
-                    let opts = expect_context::<leptos_axum::ResponseOptions>();
-                    opts.set_status(StatusCode::UNAUTHORIZED);
-                    return Err(ServerFnError::ServerError("Unauthorized".to_string()));
-                }
-            };
+    let Ok(cookies) = extract::<Cookies>().await else {
+        error!("Cookies are unavailable");
+        return Ok(None);
+    };
+    let Some(cookie) = read_session_cookie(&cookies) else {
+        info!("No valid session cookie found");
+        return Ok(None);
+    };
+
+    let player = get_player_by_session(cookie.session_id).await?;
+    if player.is_none() {
+        error!("No player with session {:?} found", cookie.session_id);
+    }
+    Ok(player)
+}
+
+/// Rejects the request with the status. The status is only set for server function
+/// calls, pages rendered for a visitor who lacks access still respond normally.
+#[cfg(feature = "ssr")]
+fn deny(status: http::StatusCode, message: &str) -> ServerFnError {
+    use leptos_router::location::RequestUrl;
+
+    if use_context::<RequestUrl>().is_none() {
+        if let Some(opts) = use_context::<leptos_axum::ResponseOptions>() {
+            opts.set_status(status);
         }
     }
+    ServerFnError::ServerError(message.to_string())
+}
+
+/// Guard for server functions that need a logged in player.
+/// Responds with 401 without a valid session.
+#[cfg(feature = "ssr")]
+pub async fn require_player() -> Result<Player, ServerFnError> {
+    use http::StatusCode;
 
-    error!("No session cookie found");
-    return Err(ServerFnError::ServerError("No user found".to_string()));
+    session_player()
+        .await?
+        .ok_or_else(|| deny(StatusCode::UNAUTHORIZED, "Unauthorized"))
 }
 
-#[server]
-pub async fn validate_admin() -> Result<bool, ServerFnError> {
+/// Guard for server functions that need a permission of the player's role.
+/// Responds with 401 without a valid session and 403 when the role lacks the permission.
+#[cfg(feature = "ssr")]
+pub async fn require_permission(
+    permission: crate::models::Permission,
+) -> Result<Player, ServerFnError> {
     use http::StatusCode;
-    use leptos_axum::extract;
-    use tower_cookies::Cookies;
-    use tracing::{error, info};
-    use uuid::Uuid;
-
-    info!("Validate admin session");
-    if let Some(cookies) = extract::<Cookies>().await.ok() {
-        if let Some(session_id) = cookies.get("session_id") {
-            match Uuid::parse_str(session_id.value()) {
-                Ok(uuid) => match get_player_by_session(uuid).await? {
-                    Some(player) => {
-                        if let Some(access_group) = player.access_group {
-                            if access_group == "admin" {
-                                return Ok(true);
-                            }
-                        }
-                    }
-                    None => {
-                        error!("No player with session {:?} found", uuid);
-
-                        let opts = expect_context::<leptos_axum::ResponseOptions>();
-                        opts.set_status(StatusCode::UNAUTHORIZED);
-                        leptos_axum::redirect("/");
-                        return Err(ServerFnError::ServerError("Unauthorized".to_string()));
-                    }
-                },
-                Err(_) => {
-                    error!("Invalid session_id: {:?}", session_id.value());
-                    let opts = expect_context::<leptos_axum::ResponseOptions>();
-                    opts.set_status(StatusCode::UNAUTHORIZED);
-                    leptos_axum::redirect("/");
-                    return Err(ServerFnError::ServerError("Unauthorized".to_string()));
-                }
-            };
-        }
+    use tracing::warn;
+
+    let player = require_player().await?;
+    if !player.role.has_permission(permission) {
+        warn!(
+            "Player {} with role {:?} lacks permission {:?}",
+            player.player_id, player.role, permission
+        );
+        return Err(deny(StatusCode::FORBIDDEN, "Forbidden"));
     }
+    Ok(player)
+}
 
-    error!("No session cookie found");
-    let opts = expect_context::<leptos_axum::ResponseOptions>();
-    opts.set_status(StatusCode::UNAUTHORIZED);
-    return Err(ServerFnError::ServerError("Unauthorized".to_string()));
+/// Guard for server functions only admins may call, whatever the permissions of other roles.
+/// Responds with 401 without a valid session and 403 for other roles.
+#[cfg(feature = "ssr")]
+pub async fn require_admin() -> Result<Player, ServerFnError> {
+    use crate::models::Role;
+    use http::StatusCode;
+    use tracing::warn;
+
+    let player = require_player().await?;
+    if player.role != Role::Admin {
+        warn!("Player {} is not an admin", player.player_id);
+        return Err(deny(StatusCode::FORBIDDEN, "Forbidden"));
+    }
+    Ok(player)
 }
 
 #[server]
 pub async fn logout() -> Result<(), ServerFnError> {
     use leptos_axum::extract;
     use tower_cookies::Cookies;
-    use tracing::error;
-    use uuid::Uuid;
-
-    if let Some(cookies) = extract::<Cookies>().await.ok() {
-        if let Some(session_id) = cookies.get("session_id") {
-            match Uuid::parse_str(session_id.value()) {
-                Ok(uuid) => {
-                    let _ = delete_session(uuid).await;
-                }
-                Err(err) => {
-                    error!("{}", err);
-                    return Err(ServerFnError::ServerError("No session_id".to_string()));
-                }
-            };
+
+    if let Ok(cookies) = extract::<Cookies>().await {
+        if let Some(cookie) = read_session_cookie(&cookies) {
+            let _ = delete_session(cookie.session_id).await;
         }
+        remove_session_cookie(&cookies);
     }
-    let logout_url = env::var("OAUTH_LOGOUT_URL")?;
-    leptos_axum::redirect(&logout_url);
+    let logout_url = expect_context::<AuthClient>().config.logout_url;
+    leptos_axum::redirect(logout_url.as_str());
     Ok(())
 }
 
+/// Ends every session of the player, on all devices, and removes the player's API tokens.
 #[server]
+pub async fn logout_everywhere() -> Result<(), ServerFnError> {
+    use crate::database::get_db;
+    use leptos_axum::extract;
+    use tower_cookies::Cookies;
+    use tracing::{error, info};
+
+    let player = require_player().await?;
+    match delete_player_sessions(get_db(), player.player_id).await {
+        Ok((sessions, api_tokens)) => info!(
+            "{} sessions and {} API tokens of player {:?} deleted.",
+            sessions, api_tokens, player.player_id
+        ),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            return Err(ServerFnError::ServerError(
+                "Failed to delete sessions.".to_string(),
+            ));
+        }
+    }
+    if let Ok(cookies) = extract::<Cookies>().await {
+        remove_session_cookie(&cookies);
+    }
+    let logout_url = expect_context::<AuthClient>().config.logout_url;
+    leptos_axum::redirect(logout_url.as_str());
+    Ok(())
+}
+
+/// Name of the signed cookie holding the session id.
+#[cfg(feature = "ssr")]
+const SESSION_COOKIE: &str = "session_id";
+
+#[cfg(feature = "ssr")]
+pub struct SessionCookie {
+    pub session_id: uuid::Uuid,
+    /// Signed with an older key and should be signed again with the current one
+    pub outdated_key: bool,
+}
+
+/// Keys derived from `session.keys`, the first one signs new cookies.
+#[cfg(feature = "ssr")]
+fn session_keys() -> &'static [tower_cookies::Key] {
+    use crate::config::get_config;
+    use std::sync::OnceLock;
+    use tower_cookies::Key;
+
+    static KEYS: OnceLock<Vec<Key>> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        get_config()
+            .session
+            .keys
+            .iter()
+            .map(|key| Key::derive_from(key.expose().as_bytes()))
+            .collect()
+    })
+}
+
+/// How long a session lives without activity.
+#[cfg(feature = "ssr")]
+pub fn session_ttl(remember: bool) -> chrono::Duration {
+    use crate::config::get_config;
+
+    let config = &get_config().session;
+    if remember {
+        chrono::Duration::days(config.remember_days)
+    } else {
+        chrono::Duration::hours(config.ttl_hours)
+    }
+}
+
+/// The session id from the signed cookie, trying every configured key.
+/// Tampered cookies and cookies signed with a removed key are ignored.
+#[cfg(feature = "ssr")]
+pub fn read_session_cookie(cookies: &tower_cookies::Cookies) -> Option<SessionCookie> {
+    session_keys().iter().enumerate().find_map(|(index, key)| {
+        let cookie = cookies.signed(key).get(SESSION_COOKIE)?;
+        let session_id = uuid::Uuid::parse_str(cookie.value()).ok()?;
+        Some(SessionCookie {
+            session_id,
+            outdated_key: index > 0,
+        })
+    })
+}
+
+#[cfg(feature = "ssr")]
+pub fn set_session_cookie(
+    cookies: &tower_cookies::Cookies,
+    session_id: uuid::Uuid,
+    remember: bool,
+) {
+    use crate::config::{get_config, SameSite as SameSiteConfig};
+    use tower_cookies::cookie::{time::Duration, Cookie, SameSite};
+
+    let config = get_config();
+    let same_site = match config.cookie.same_site {
+        SameSiteConfig::Strict => SameSite::Strict,
+        SameSiteConfig::Lax => SameSite::Lax,
+        SameSiteConfig::None => SameSite::None,
+    };
+    let mut cookie: Cookie = Cookie::build((SESSION_COOKIE, session_id.to_string()))
+        .path("/")
+        .secure(config.cookie.secure)
+        .http_only(true)
+        .max_age(Duration::seconds(session_ttl(remember).num_seconds()))
+        .same_site(same_site)
+        .build();
+    if let Some(domain) = &config.cookie.domain {
+        cookie.set_domain(domain.clone());
+    }
+
+    if let Some(key) = session_keys().first() {
+        cookies.signed(key).add(cookie);
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub fn remove_session_cookie(cookies: &tower_cookies::Cookies) {
+    use crate::config::get_config;
+    use tower_cookies::Cookie;
+
+    let mut cookie = Cookie::build((SESSION_COOKIE, "")).path("/").build();
+    if let Some(domain) = &get_config().cookie.domain {
+        cookie.set_domain(domain.clone());
+    }
+    cookies.remove(cookie);
+}
+
+/// Middleware keeping active sessions alive. Once half of the lifetime has passed the
+/// expiry is pushed forward, cookies signed with an old key are signed again.
+#[cfg(feature = "ssr")]
+pub async fn refresh_session(
+    cookies: tower_cookies::Cookies,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use tracing::error;
+
+    if let Some(cookie) = read_session_cookie(&cookies) {
+        match extend_session(cookie.session_id, cookie.outdated_key).await {
+            Ok(Some(remember)) => set_session_cookie(&cookies, cookie.session_id, remember),
+            Ok(None) => {}
+            Err(e) => error!("Failed to refresh session: {:?}", e),
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Extends the session if it is past half of its lifetime, or always with `force`.
+/// Returns whether it is a "remember me" session when it was extended.
+#[cfg(feature = "ssr")]
+async fn extend_session(session_id: uuid::Uuid, force: bool) -> Result<Option<bool>, sqlx::Error> {
+    use crate::database::get_db;
+    use chrono::Utc;
+
+    let now = Utc::now();
+    let (ttl, remember_ttl) = (session_ttl(false), session_ttl(true));
+
+    sqlx::query_scalar!(
+        r#"
+        UPDATE session
+        SET expires_at = CASE WHEN remember THEN $2::timestamptz ELSE $3::timestamptz END
+        WHERE session_id = $1
+          AND expires_at > NOW()
+          AND ($6 OR expires_at < CASE WHEN remember THEN $4::timestamptz ELSE $5::timestamptz END)
+        RETURNING remember
+        "#,
+        session_id,
+        now + remember_ttl,
+        now + ttl,
+        now + remember_ttl / 2,
+        now + ttl / 2,
+        force
+    )
+    .fetch_optional(get_db())
+    .await
+}
+
+#[cfg(feature = "ssr")]
 async fn store_pkce_verifier(
     csrf_token: String,
     pkce_verifier: String,
+    nonce: String,
+    remember: bool,
 ) -> Result<(), ServerFnError> {
     use crate::database::get_db;
     use chrono::Utc;
@@ -169,11 +593,13 @@ async fn store_pkce_verifier(
     let expires_at = Utc::now() + chrono::Duration::minutes(15);
 
     match sqlx::query!(
-        "INSERT INTO pkce_store (csrf_token, pkce_verifier, created_at, expires_at)
-         VALUES ($1, $2, NOW(), $3)
+        "INSERT INTO pkce_store (csrf_token, pkce_verifier, nonce, remember, created_at, expires_at)
+         VALUES ($1, $2, $3, $4, NOW(), $5)
          ON CONFLICT (csrf_token) DO NOTHING",
         csrf_token,
         pkce_verifier,
+        nonce,
+        remember,
         expires_at
     )
     .execute(pool)
@@ -193,7 +619,7 @@ async fn store_pkce_verifier(
     }
 }
 
-#[server]
+#[cfg(feature = "ssr")]
 pub async fn get_pkce_verifier(csrf_token: String) -> Result<Option<PkceStore>, ServerFnError> {
     use crate::database::get_db;
     use tracing::{error, info};
@@ -220,8 +646,56 @@ pub async fn get_pkce_verifier(csrf_token: String) -> Result<Option<PkceStore>,
     }
 }
 
-#[server]
-async fn delete_session(session_id: uuid::Uuid) -> Result<(), ServerFnError> {
+/// The browser and address a login came from, stored with the session.
+#[cfg(feature = "ssr")]
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[cfg(feature = "ssr")]
+pub async fn session_client() -> SessionClient {
+    use crate::config::get_config;
+    use axum::extract::ConnectInfo;
+    use http::{header, HeaderMap};
+    use leptos_axum::extract;
+    use std::net::SocketAddr;
+
+    let headers = extract::<HeaderMap>().await.unwrap_or_default();
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    // Behind the reverse proxies the peer is a proxy, only the addresses they added can be
+    // trusted, anything further left was sent by the client
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| forwarded_client(value, get_config().session.trusted_proxies));
+    let ip = match forwarded {
+        Some(ip) => Some(ip),
+        None => extract::<ConnectInfo<SocketAddr>>()
+            .await
+            .ok()
+            .map(|ConnectInfo(addr)| addr.ip().to_string()),
+    };
+
+    SessionClient { user_agent, ip }
+}
+
+/// The address the outermost of `trusted_proxies` proxies appended to `X-Forwarded-For`,
+/// `None` when the header has fewer addresses than there are proxies.
+#[cfg(feature = "ssr")]
+fn forwarded_client(header: &str, trusted_proxies: usize) -> Option<String> {
+    let ip = header
+        .rsplit(',')
+        .nth(trusted_proxies.checked_sub(1)?)?
+        .trim();
+    Some(ip.to_string()).filter(|ip| !ip.is_empty())
+}
+
+#[cfg(feature = "ssr")]
+pub async fn delete_session(session_id: uuid::Uuid) -> Result<(), ServerFnError> {
     use crate::database::get_db;
     use tracing::{error, info};
 
@@ -250,8 +724,40 @@ async fn delete_session(session_id: uuid::Uuid) -> Result<(), ServerFnError> {
     }
 }
 
-#[server]
-async fn get_player_by_session(session_id: uuid::Uuid) -> Result<Option<Player>, ServerFnError> {
+/// Deletes all sessions and API tokens of a player, so nothing is left signed in as the
+/// player. Returns the number of sessions and API tokens removed.
+#[cfg(feature = "ssr")]
+pub async fn delete_player_sessions(
+    executor: impl sqlx::PgExecutor<'_>,
+    player_id: i32,
+) -> Result<(i64, i64), sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        WITH sessions AS (
+            DELETE FROM session
+            WHERE player_id = $1
+            RETURNING session_id
+        ),
+        api_tokens AS (
+            DELETE FROM api_token
+            WHERE player_id = $1
+            RETURNING api_token_id
+        )
+        SELECT
+            (SELECT COUNT(*) FROM sessions) as "sessions!",
+            (SELECT COUNT(*) FROM api_tokens) as "api_tokens!"
+        "#,
+        player_id
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok((deleted.sessions, deleted.api_tokens))
+}
+
+#[cfg(feature = "ssr")]
+pub async fn get_player_by_session(
+    session_id: uuid::Uuid,
+) -> Result<Option<Player>, ServerFnError> {
     use crate::database::get_db;
     use tracing::error;
 
@@ -260,7 +766,7 @@ async fn get_player_by_session(session_id: uuid::Uuid) -> Result<Option<Player>,
     match sqlx::query_as!(
         Player,
         r#"
-        SELECT p.player_id, p.name, p.given_name, p.family_name, p.email, p.access_group, is_goalkeeper
+        SELECT p.player_id, p.name, p.given_name, p.family_name, p.email, p.role as "role: _", p.position as "position: _"
         FROM session s
         JOIN player p ON s.player_id = p.player_id
         WHERE s.session_id = $1
@@ -280,3 +786,77 @@ async fn get_player_by_session(session_id: uuid::Uuid) -> Result<Option<Player>,
         }
     }
 }
+
+/// Prefix of personal API tokens, makes a leaked token easy to recognize.
+#[cfg(feature = "ssr")]
+const API_TOKEN_PREFIX: &str = "gubb_";
+
+/// A new random API token. Only its hash is stored so the player sees it once.
+#[cfg(feature = "ssr")]
+pub fn generate_api_token() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}{}", API_TOKEN_PREFIX, token)
+}
+
+/// What is stored of an API token, the tokens are random enough that a plain hash will do.
+#[cfg(feature = "ssr")]
+pub fn hash_api_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The player owning an API token, the token is marked as used.
+#[cfg(feature = "ssr")]
+pub async fn get_player_by_api_token(token: &str) -> Result<Option<Player>, sqlx::Error> {
+    use crate::database::get_db;
+
+    if !token.starts_with(API_TOKEN_PREFIX) {
+        return Ok(None);
+    }
+
+    sqlx::query_as!(
+        Player,
+        r#"
+        WITH used AS (
+            UPDATE api_token
+            SET last_used_at = NOW()
+            WHERE token_hash = $1
+            RETURNING player_id
+        )
+        SELECT p.player_id, p.name, p.given_name, p.family_name, p.email, p.role as "role: _", p.position as "position: _"
+        FROM used u
+        JOIN player p ON u.player_id = p.player_id
+        "#,
+        hash_api_token(token)
+    )
+    .fetch_optional(get_db())
+    .await
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::forwarded_client;
+
+    #[test]
+    fn forwarded_client_is_added_by_the_outermost_proxy() {
+        let header = "203.0.113.9, 198.51.100.7, 10.0.0.2";
+        assert_eq!(forwarded_client(header, 1).as_deref(), Some("10.0.0.2"));
+        assert_eq!(forwarded_client(header, 2).as_deref(), Some("198.51.100.7"));
+        assert_eq!(forwarded_client(header, 3).as_deref(), Some("203.0.113.9"));
+    }
+
+    #[test]
+    fn forwarded_client_ignores_the_header_without_enough_proxies() {
+        assert_eq!(forwarded_client("203.0.113.9", 0), None);
+        assert_eq!(forwarded_client("203.0.113.9", 2), None);
+        assert_eq!(forwarded_client(" , ", 1), None);
+    }
+}
@@ -0,0 +1,378 @@
+#![cfg(feature = "ssr")]
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    components::join_button::gamedays_by_player,
+    database::get_db,
+    models::{Cup, Gameday},
+    pages::cup_page::cups_by_player,
+    timezone::club_timezone,
+};
+
+// UIDs only depend on the id so calendar apps update events in place when they are edited
+const UID_DOMAIN: &str = "gubbhockey.com";
+
+struct Event {
+    uid: String,
+    summary: String,
+    description: Option<String>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+impl From<&Gameday> for Event {
+    fn from(gameday: &Gameday) -> Self {
+        let summary = match gameday.queue_position {
+            Some(position) => format!("Gubbhockey (reserv #{})", position),
+            None => "Gubbhockey".to_string(),
+        };
+        Event {
+            uid: format!("gameday-{}@{}", gameday.gameday_id, UID_DOMAIN),
+            summary,
+            description: None,
+            start: gameday.start_date,
+            end: gameday.end_date,
+        }
+    }
+}
+
+impl From<&Cup> for Event {
+    fn from(cup: &Cup) -> Self {
+        Event {
+            uid: format!("cup-{}@{}", cup.cup_id, UID_DOMAIN),
+            summary: cup.title.clone().unwrap_or("Cup".to_string()),
+            description: cup.info.clone(),
+            start: cup.start_date,
+            end: cup.end_date,
+        }
+    }
+}
+
+/// Public feed with every upcoming gameday.
+pub async fn public_calendar() -> Response {
+    match sqlx::query!(
+        r#"
+        SELECT gameday_id, start_date, end_date
+        FROM gameday
+        WHERE start_date >= NOW()
+        ORDER BY start_date ASC
+        "#
+    )
+    .fetch_all(get_db())
+    .await
+    {
+        Ok(gamedays) => {
+            let events = gamedays
+                .into_iter()
+                .map(|gameday| Event {
+                    uid: format!("gameday-{}@{}", gameday.gameday_id, UID_DOMAIN),
+                    summary: "Gubbhockey".to_string(),
+                    description: None,
+                    start: gameday.start_date,
+                    end: gameday.end_date,
+                })
+                .collect::<Vec<_>>();
+            calendar_response(render_calendar("Gubbhockey", &events))
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Personal feed with the gamedays and cups a player is registered for.
+/// The token is the secret `calendar_token` of the player, optionally ending with `.ics`.
+pub async fn player_calendar(Path(token): Path<String>) -> Response {
+    let Ok(token) = Uuid::parse_str(token.trim_end_matches(".ics")) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let player_id = match sqlx::query_scalar!(
+        r#"
+        SELECT player_id
+        FROM player
+        WHERE calendar_token = $1
+        "#,
+        token
+    )
+    .fetch_optional(get_db())
+    .await
+    {
+        Ok(Some(player_id)) => player_id,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match tokio::try_join!(gamedays_by_player(player_id), cups_by_player(player_id)) {
+        Ok((gamedays, cups)) => {
+            info!("Serving calendar for player {}", player_id);
+            let events = gamedays
+                .iter()
+                .map(Event::from)
+                .chain(cups.iter().map(Event::from))
+                .collect::<Vec<_>>();
+            calendar_response(render_calendar("Gubbhockey", &events))
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn calendar_response(body: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+fn render_calendar(name: &str, events: &[Event]) -> String {
+    let tz = club_timezone();
+    let now = Utc::now();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Falkenbergs Gubbhockey//gubbhockey.com//SV".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+        format!("X-WR-TIMEZONE:{}", tz.name()),
+    ];
+
+    let years = events.iter().map(|event| event.start.year());
+    let from_year = years.clone().min().unwrap_or(now.year()) - 1;
+    let to_year = years.max().unwrap_or(now.year()) + 1;
+    lines.extend(render_timezone(tz, from_year, to_year));
+
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", event.uid));
+        lines.push(format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")));
+        lines.push(format!(
+            "DTSTART;TZID={}:{}",
+            tz.name(),
+            event.start.with_timezone(&tz).format("%Y%m%dT%H%M%S")
+        ));
+        lines.push(format!(
+            "DTEND;TZID={}:{}",
+            tz.name(),
+            event.end.with_timezone(&tz).format("%Y%m%dT%H%M%S")
+        ));
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+/// Describes every offset change of the timezone between the given years.
+fn render_timezone(tz: Tz, from_year: i32, to_year: i32) -> Vec<String> {
+    let mut lines = vec!["BEGIN:VTIMEZONE".to_string(), format!("TZID:{}", tz.name())];
+
+    let start = Utc
+        .with_ymd_and_hms(from_year, 1, 1, 0, 0, 0)
+        .single()
+        .unwrap_or_default();
+    let end = Utc
+        .with_ymd_and_hms(to_year + 1, 1, 1, 0, 0, 0)
+        .single()
+        .unwrap_or_default();
+
+    let first_offset = tz.offset_from_utc_datetime(&start.naive_utc());
+    let mut previous = first_offset;
+    let mut day = start;
+    let mut has_transitions = false;
+    while day < end {
+        let next_day = day + Duration::days(1);
+        let offset = tz.offset_from_utc_datetime(&next_day.naive_utc());
+        if offset.fix() != previous.fix() {
+            // Narrow the transition down to the second
+            let (mut before, mut after) = (day, next_day);
+            while after - before > Duration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if tz.offset_from_utc_datetime(&middle.naive_utc()).fix() == previous.fix() {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+
+            let component = if offset.dst_offset().is_zero() {
+                "STANDARD"
+            } else {
+                "DAYLIGHT"
+            };
+            lines.push(format!("BEGIN:{}", component));
+            lines.push(format!(
+                "DTSTART:{}",
+                after.with_timezone(&previous.fix()).format("%Y%m%dT%H%M%S")
+            ));
+            lines.push(format!("TZOFFSETFROM:{}", format_offset(previous.fix())));
+            lines.push(format!("TZOFFSETTO:{}", format_offset(offset.fix())));
+            if let Some(name) = offset.abbreviation() {
+                lines.push(format!("TZNAME:{}", name));
+            }
+            lines.push(format!("END:{}", component));
+
+            has_transitions = true;
+            previous = offset;
+        }
+        day = next_day;
+    }
+
+    if !has_transitions {
+        lines.push("BEGIN:STANDARD".to_string());
+        lines.push("DTSTART:19700101T000000".to_string());
+        lines.push(format!(
+            "TZOFFSETFROM:{}",
+            format_offset(first_offset.fix())
+        ));
+        lines.push(format!("TZOFFSETTO:{}", format_offset(first_offset.fix())));
+        if let Some(name) = first_offset.abbreviation() {
+            lines.push(format!("TZNAME:{}", name));
+        }
+        lines.push("END:STANDARD".to_string());
+    }
+
+    lines.push("END:VTIMEZONE".to_string());
+    lines
+}
+
+fn format_offset(offset: FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    format!("{}{:02}{:02}", sign, seconds / 3600, seconds % 3600 / 60)
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\r', "")
+        .replace('\n', "\\n")
+}
+
+/// Folds content lines longer than 75 octets without splitting UTF-8 characters.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_line_keeps_characters_whole() {
+        // å is two octets and would end at octet 76
+        let line = format!("{}å", "a".repeat(74));
+        assert_eq!(fold_line(&line), format!("{}\r\n å", "a".repeat(74)));
+
+        let line = format!("SUMMARY:{}", "Gubbhockey på Åby, för äldre öppet ".repeat(5));
+        let folded = fold_line(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+
+        assert_eq!(fold_line("SUMMARY:Gubbhockey"), "SUMMARY:Gubbhockey");
+    }
+
+    #[test]
+    fn escape_text_escapes_special_characters() {
+        assert_eq!(
+            escape_text("Ta med: klubba, skridskor; hjälm\\visir\r\nVälkomna!"),
+            r"Ta med: klubba\, skridskor\; hjälm\\visir\nVälkomna!"
+        );
+    }
+
+    #[test]
+    fn format_offset_pads_hours_and_minutes() {
+        let hour = 3600;
+        assert_eq!(format_offset(FixedOffset::east_opt(hour).unwrap()), "+0100");
+        assert_eq!(format_offset(FixedOffset::east_opt(0).unwrap()), "+0000");
+        assert_eq!(
+            format_offset(FixedOffset::east_opt(5 * hour + 45 * 60).unwrap()),
+            "+0545"
+        );
+        assert_eq!(
+            format_offset(FixedOffset::west_opt(3 * hour + 30 * 60).unwrap()),
+            "-0330"
+        );
+    }
+
+    #[test]
+    fn render_timezone_has_the_stockholm_transitions() {
+        assert_eq!(
+            render_timezone(chrono_tz::Europe::Stockholm, 2025, 2025),
+            [
+                "BEGIN:VTIMEZONE",
+                "TZID:Europe/Stockholm",
+                "BEGIN:DAYLIGHT",
+                "DTSTART:20250330T020000",
+                "TZOFFSETFROM:+0100",
+                "TZOFFSETTO:+0200",
+                "TZNAME:CEST",
+                "END:DAYLIGHT",
+                "BEGIN:STANDARD",
+                "DTSTART:20251026T030000",
+                "TZOFFSETFROM:+0200",
+                "TZOFFSETTO:+0100",
+                "TZNAME:CET",
+                "END:STANDARD",
+                "END:VTIMEZONE",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_timezone_without_transitions_has_one_standard_time() {
+        assert_eq!(
+            render_timezone(chrono_tz::UTC, 2025, 2026),
+            [
+                "BEGIN:VTIMEZONE",
+                "TZID:UTC",
+                "BEGIN:STANDARD",
+                "DTSTART:19700101T000000",
+                "TZOFFSETFROM:+0000",
+                "TZOFFSETTO:+0000",
+                "TZNAME:UTC",
+                "END:STANDARD",
+                "END:VTIMEZONE",
+            ]
+        );
+    }
+}
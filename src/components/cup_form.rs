@@ -1,3 +1,4 @@
+#[cfg(feature = "ssr")]
 use chrono::{DateTime, Utc};
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -88,9 +89,14 @@ struct InputCup {
 
 #[server]
 async fn add_cup(input_cup: InputCup) -> Result<(), ServerFnError> {
-    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+    use crate::auth::require_permission;
+    use crate::models::Permission;
+    use crate::timezone::from_club_time;
+    use chrono::{NaiveDate, NaiveTime};
     use tracing::info;
 
+    let manager = require_permission(Permission::EditCup).await?;
+
     info!("Date submit: {:?}", input_cup);
     info!("txt submit: {:?}", input_cup.info);
 
@@ -99,17 +105,24 @@ async fn add_cup(input_cup: InputCup) -> Result<(), ServerFnError> {
     let start_time = NaiveTime::parse_from_str(&input_cup.start, "%H:%M")?;
     let end_time = NaiveTime::parse_from_str(&input_cup.end, "%H:%M")?;
 
-    // Combine date and time into NaiveDateTime
-    let start_datetime = NaiveDateTime::new(date, start_time);
-    let end_datetime = NaiveDateTime::new(date, end_time);
+    // The input is wall-clock time in the club's timezone
+    let to_utc = |time: NaiveTime| -> Result<DateTime<Utc>, ServerFnError> {
+        from_club_time(date, time).ok_or_else(|| {
+            ServerFnError::ServerError(format!("{} {} is not a valid local time.", date, time))
+        })
+    };
+
+    let start_datetime = to_utc(start_time)?;
+    let end_datetime = to_utc(end_time)?;
     info!(
-        "Parsed NaiveStart: {:?}, NaiveEnd: {:?}",
+        "Parsed Start: {:?}, End: {:?}",
         start_datetime, end_datetime
     );
 
     insert_cup(
-        start_datetime.and_utc(),
-        end_datetime.and_utc(),
+        manager.player_id,
+        start_datetime,
+        end_datetime,
         input_cup.title,
         input_cup.info,
     )
@@ -118,41 +131,60 @@ async fn add_cup(input_cup: InputCup) -> Result<(), ServerFnError> {
     Ok(())
 }
 
-#[server]
+#[cfg(feature = "ssr")]
 async fn insert_cup(
+    actor_id: i32,
     start_date: DateTime<Utc>,
     end_date: DateTime<Utc>,
     title: String,
     info: String,
 ) -> Result<(), ServerFnError> {
-    use crate::auth::validate_admin;
+    use crate::audit::record_event;
     use crate::database::get_db;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
+    let created = async {
+        let mut tx = get_db().begin().await?;
+        let cup_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO cup (start_date, end_date, title, info)
+            VALUES ($1, $2, $3, $4)
+            RETURNING cup_id
+            "#,
+            start_date,
+            end_date,
+            title,
+            info
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        record_event(
+            &mut *tx,
+            actor_id,
+            "cup.create",
+            &format!("cup:{}", cup_id),
+            None,
+            Some(serde_json::json!({
+                "start_date": start_date,
+                "end_date": end_date,
+                "title": title,
+                "info": info,
+            })),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(cup_id)
     }
-    let pool = get_db();
-    match sqlx::query!(
-        r#"
-        INSERT INTO cup (start_date, end_date, title, info)
-        VALUES ($1, $2, $3, $4)
-        RETURNING cup_id
-        "#,
-        start_date,
-        end_date,
-        title,
-        info
-    )
-    .fetch_one(pool)
-    .await
-    {
-        Ok(cup) => {
+    .await;
+
+    match created {
+        Ok(cup_id) => {
             info!(
                 "Cup inserted successfully! Start:{:?}, End:{:?}",
                 start_date, end_date
             );
-            leptos_axum::redirect(format!("/cup/{}", cup.cup_id).as_str());
+            leptos_axum::redirect(format!("/cup/{}", cup_id).as_str());
             Ok(())
         }
         Err(e) => {
@@ -0,0 +1,765 @@
+use leptos::{ev::DragEvent, prelude::*};
+
+use crate::{
+    components::loading::Loading,
+    models::{CupLineup, CupLineupPlayer, CupTeam, LineupPosition, LineupSpot, Position},
+};
+
+fn spot_label(spot: LineupSpot) -> String {
+    match spot.position {
+        LineupPosition::Goalkeeper => format!("G{}", spot.line),
+        position => position.short_label().to_string(),
+    }
+}
+
+/// The player being dragged, set in `PlayerChip`.
+fn dragged_player(ev: &DragEvent) -> Option<i32> {
+    ev.data_transfer()?
+        .get_data("text/plain")
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Lets cup managers build teams and lines from the registered players and publish them
+/// on the cup page. Players are dragged to a spot, or tapped and then the spot is tapped.
+#[component]
+pub fn CupRosterBuilder(cup_id: i32) -> impl IntoView {
+    let add_team = ServerAction::<AddCupTeam>::new();
+    let remove_team = ServerAction::<RemoveCupTeam>::new();
+    let place = ServerAction::<PlaceCupPlayer>::new();
+    let unplace = ServerAction::<UnplaceCupPlayer>::new();
+    let publish = ServerAction::<PublishCupLineup>::new();
+    let lineup = Resource::new(
+        move || {
+            add_team.version().get()
+                + remove_team.version().get()
+                + place.version().get()
+                + unplace.version().get()
+                + publish.version().get()
+        },
+        move |_| async move { get_cup_lineup(cup_id).await },
+    );
+    // Player picked up by tapping, placed in the spot tapped next
+    let selected = RwSignal::new(None::<i32>);
+
+    let unplace_player = move |player_id: i32| {
+        selected.set(None);
+        unplace.dispatch(UnplaceCupPlayer { cup_id, player_id });
+    };
+
+    view! {
+        <div class="flex flex-col items-center w-full mt-6">
+            <h2 class="text-center text-2xl">"Laguppställning"</h2>
+            <Transition fallback=move || view! { <Loading /> }>
+                {move || Suspend::new(async move {
+                    let lineup = lineup.await.unwrap_or_default();
+                    let published = lineup.published;
+                    let unplaced = lineup
+                        .players
+                        .iter()
+                        .filter(|player| player.spot().is_none())
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    view! {
+                        <ShortageTable lineup=lineup.clone() />
+                        <p class="text-sm text-center m-2">
+                            "Dra en spelare till en plats, eller tryck på spelaren och sedan på platsen."
+                        </p>
+                        <div
+                            class="flex flex-wrap justify-center gap-1 w-11/12 min-h-12 border border-dashed rounded p-2"
+                            on:dragover=|ev: DragEvent| ev.prevent_default()
+                            on:drop=move |ev: DragEvent| {
+                                ev.prevent_default();
+                                if let Some(player_id) = dragged_player(&ev) {
+                                    unplace_player(player_id);
+                                }
+                            }
+                            on:click=move |_| {
+                                if let Some(player_id) = selected.get_untracked() {
+                                    unplace_player(player_id);
+                                }
+                            }
+                        >
+                            <span class="text-xs w-full text-center">"Ej placerade"</span>
+                            {unplaced
+                                .into_iter()
+                                .map(|player| view! { <PlayerChip player selected /> })
+                                .collect_view()}
+                        </div>
+                        <div class="flex flex-wrap justify-center gap-4 m-4">
+                            {lineup
+                                .teams
+                                .clone()
+                                .into_iter()
+                                .map(|team| {
+                                    view! {
+                                        <TeamBuilder
+                                            cup_id
+                                            team
+                                            lineup=lineup.clone()
+                                            selected
+                                            place
+                                            remove_team
+                                        />
+                                    }
+                                })
+                                .collect_view()}
+                        </div>
+                        <ActionForm action=publish>
+                            <input type="hidden" name="cup_id" value=cup_id />
+                            <input type="hidden" name="published" value=(!published).to_string() />
+                            <div class="flex flex-col items-center gap-1">
+                                <p class="text-sm">
+                                    {if published {
+                                        "Laguppställningen visas på cupsidan."
+                                    } else {
+                                        "Laguppställningen visas på cupsidan när den är publicerad."
+                                    }}
+                                </p>
+                                <button type="submit" class="btn btn-sm btn-warning">
+                                    {if published { "Avpublicera" } else { "Publicera" }}
+                                </button>
+                            </div>
+                        </ActionForm>
+                    }
+                })}
+            </Transition>
+            <ActionForm action=add_team>
+                <input type="hidden" name="cup_id" value=cup_id />
+                <div class="flex gap-2 m-4">
+                    <input
+                        type="text"
+                        name="name"
+                        required
+                        placeholder="Lagnamn"
+                        class="input input-bordered input-sm"
+                    />
+                    <button type="submit" class="btn btn-sm btn-success">
+                        "Lägg till lag"
+                    </button>
+                </div>
+            </ActionForm>
+        </div>
+    }
+}
+
+/// Spots in every team compared to the players registered for each position.
+#[component]
+fn ShortageTable(lineup: CupLineup) -> impl IntoView {
+    view! {
+        <table class="table table-sm w-auto">
+            <thead>
+                <tr>
+                    <th>"Position"</th>
+                    <th>"Platser"</th>
+                    <th>"Anmälda"</th>
+                    <th>"Placerade"</th>
+                    <th>"Saknas"</th>
+                </tr>
+            </thead>
+            <tbody>
+                {Position::ALL
+                    .into_iter()
+                    .map(|position| {
+                        let shortage = lineup.shortage(position);
+                        let missing = shortage.missing();
+                        view! {
+                            <tr>
+                                <td>{position.label()}</td>
+                                <td>{shortage.spots}</td>
+                                <td>{shortage.registered}</td>
+                                <td>{shortage.placed}</td>
+                                <td class=(missing > 0).then_some("text-error")>{missing}</td>
+                            </tr>
+                        }
+                    })
+                    .collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
+#[component]
+fn TeamBuilder(
+    cup_id: i32,
+    team: CupTeam,
+    lineup: CupLineup,
+    selected: RwSignal<Option<i32>>,
+    place: ServerAction<PlaceCupPlayer>,
+    remove_team: ServerAction<RemoveCupTeam>,
+) -> impl IntoView {
+    let group = |title: String, spots: Vec<LineupSpot>| {
+        view! {
+            <p class="text-xs mt-2">{title}</p>
+            <div class="flex gap-1">
+                {spots
+                    .into_iter()
+                    .map(|spot| {
+                        let player = lineup.player_at(spot).cloned();
+                        view! { <SpotBox cup_id spot player selected place /> }
+                    })
+                    .collect_view()}
+            </div>
+        }
+    };
+    let spot = |position, line| LineupSpot {
+        cup_team_id: team.cup_team_id,
+        position,
+        line,
+    };
+
+    view! {
+        <div class="flex flex-col items-center border rounded-box p-2">
+            <div class="flex items-center gap-2">
+                <h3 class="text-xl">{team.name.clone()}</h3>
+                <ActionForm action=remove_team>
+                    <input type="hidden" name="cup_team_id" value=team.cup_team_id />
+                    <button type="submit" class="btn btn-xs btn-error">
+                        "Ta bort"
+                    </button>
+                </ActionForm>
+            </div>
+            {(1..=CupLineup::FORWARD_LINES)
+                .map(|line| {
+                    group(
+                        format!("Kedja {}", line),
+                        LineupPosition::FORWARDS.map(|position| spot(position, line)).to_vec(),
+                    )
+                })
+                .collect_view()}
+            {(1..=CupLineup::DEFENSE_PAIRS)
+                .map(|line| {
+                    group(
+                        format!("Backpar {}", line),
+                        LineupPosition::DEFENDERS.map(|position| spot(position, line)).to_vec(),
+                    )
+                })
+                .collect_view()}
+            {group(
+                "Målvakter".to_string(),
+                (1..=CupLineup::GOALKEEPERS)
+                    .map(|line| spot(LineupPosition::Goalkeeper, line))
+                    .collect(),
+            )}
+        </div>
+    }
+}
+
+#[component]
+fn SpotBox(
+    cup_id: i32,
+    spot: LineupSpot,
+    player: Option<CupLineupPlayer>,
+    selected: RwSignal<Option<i32>>,
+    place: ServerAction<PlaceCupPlayer>,
+) -> impl IntoView {
+    let occupant = player.as_ref().map(|player| player.player_id);
+    let place_here = move |player_id: i32| {
+        selected.set(None);
+        if occupant != Some(player_id) {
+            place.dispatch(PlaceCupPlayer {
+                cup_id,
+                player_id,
+                spot,
+            });
+        }
+    };
+
+    view! {
+        <div
+            class="flex flex-col items-center border border-dashed rounded p-1 min-w-20 min-h-12"
+            on:dragover=|ev: DragEvent| ev.prevent_default()
+            on:drop=move |ev: DragEvent| {
+                ev.prevent_default();
+                ev.stop_propagation();
+                if let Some(player_id) = dragged_player(&ev) {
+                    place_here(player_id);
+                }
+            }
+            on:click=move |ev| {
+                ev.stop_propagation();
+                if let Some(player_id) = selected.get_untracked() {
+                    place_here(player_id);
+                }
+            }
+        >
+            <span class="text-xs">{spot_label(spot)}</span>
+            {player.map(|player| view! { <PlayerChip player selected /> })}
+        </div>
+    }
+}
+
+/// A player that can be dragged or tapped to pick up.
+#[component]
+fn PlayerChip(player: CupLineupPlayer, selected: RwSignal<Option<i32>>) -> impl IntoView {
+    let player_id = player.player_id;
+
+    view! {
+        <span
+            draggable="true"
+            class=move || {
+                if selected.get() == Some(player_id) {
+                    "badge badge-primary cursor-grab"
+                } else {
+                    "badge badge-outline cursor-grab"
+                }
+            }
+            on:dragstart=move |ev: DragEvent| {
+                if let Some(data) = ev.data_transfer() {
+                    let _ = data.set_data("text/plain", &player_id.to_string());
+                }
+            }
+            on:click=move |ev| {
+                ev.stop_propagation();
+                selected.update(|selected| {
+                    *selected = if *selected == Some(player_id) { None } else { Some(player_id) };
+                });
+            }
+        >
+            {player.name.clone()}
+            " ("
+            {player.position.label()}
+            ")"
+        </span>
+    }
+}
+
+/// The published lineup on the cup page.
+#[component]
+pub fn CupLineupView(cup_id: i32) -> impl IntoView {
+    let lineup = Resource::new(
+        move || cup_id,
+        |cup_id| async move { get_cup_lineup(cup_id).await },
+    );
+    let name = move |lineup: &CupLineup, spot: LineupSpot| {
+        lineup
+            .player_at(spot)
+            .map(|player| player.name.clone())
+            .unwrap_or_else(|| "–".to_string())
+    };
+
+    view! {
+        <Transition>
+            {move || Suspend::new(async move {
+                let lineup = lineup.await.unwrap_or_default();
+                if !lineup.published {
+                    return ().into_any();
+                }
+                view! {
+                    <h2 class="text-center text-bold text-2xl mt-6">"Laguppställning"</h2>
+                    <div class="flex flex-wrap justify-center gap-4 m-2">
+                        {lineup
+                            .teams
+                            .iter()
+                            .map(|team| {
+                                let spot = |position, line| LineupSpot {
+                                    cup_team_id: team.cup_team_id,
+                                    position,
+                                    line,
+                                };
+                                let row = |title: String, positions: &[LineupPosition], line| {
+                                    let names = positions
+                                        .iter()
+                                        .map(|position| name(&lineup, spot(*position, line)))
+                                        .collect::<Vec<_>>()
+                                        .join(", ");
+                                    view! {
+                                        <li>
+                                            <span class="text-bold">{title}": "</span>
+                                            {names}
+                                        </li>
+                                    }
+                                };
+                                view! {
+                                    <div class="flex flex-col border rounded-box p-2">
+                                        <h3 class="text-xl text-center">{team.name.clone()}</h3>
+                                        <ul>
+                                            {(1..=CupLineup::FORWARD_LINES)
+                                                .map(|line| {
+                                                    row(
+                                                        format!("Kedja {}", line),
+                                                        &LineupPosition::FORWARDS,
+                                                        line,
+                                                    )
+                                                })
+                                                .collect_view()}
+                                            {(1..=CupLineup::DEFENSE_PAIRS)
+                                                .map(|line| {
+                                                    row(
+                                                        format!("Backpar {}", line),
+                                                        &LineupPosition::DEFENDERS,
+                                                        line,
+                                                    )
+                                                })
+                                                .collect_view()}
+                                            <li>
+                                                <span class="text-bold">"Målvakter: "</span>
+                                                {(1..=CupLineup::GOALKEEPERS)
+                                                    .map(|line| name(&lineup, spot(LineupPosition::Goalkeeper, line)))
+                                                    .collect::<Vec<_>>()
+                                                    .join(", ")}
+                                            </li>
+                                        </ul>
+                                    </div>
+                                }
+                            })
+                            .collect_view()}
+                    </div>
+                }
+                    .into_any()
+            })}
+        </Transition>
+    }
+}
+
+/// The teams of a cup and every registered player with their spot.
+#[cfg(feature = "ssr")]
+async fn cup_lineup(cup_id: i32, include_drafts: bool) -> Result<CupLineup, sqlx::Error> {
+    use crate::database::get_db;
+
+    let pool = get_db();
+    let published = sqlx::query_scalar!(
+        r#"
+        SELECT lineup_published
+        FROM cup
+        WHERE cup_id = $1
+        "#,
+        cup_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(false);
+    if !published && !include_drafts {
+        return Ok(CupLineup::default());
+    }
+
+    let teams = sqlx::query_as!(
+        CupTeam,
+        r#"
+        SELECT cup_team_id, name
+        FROM cup_team
+        WHERE cup_id = $1
+        ORDER BY cup_team_id
+        "#,
+        cup_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let players = sqlx::query_as!(
+        CupLineupPlayer,
+        r#"
+        SELECT
+            p.player_id,
+            p.name,
+            pc.position as "position: Position",
+            cl.cup_team_id as "cup_team_id?",
+            cl.position as "lineup_position?: LineupPosition",
+            cl.line as "line?"
+        FROM player_cup pc
+        JOIN player p ON p.player_id = pc.player_id
+        LEFT JOIN cup_lineup cl ON cl.cup_id = pc.cup_id AND cl.player_id = pc.player_id
+        WHERE pc.cup_id = $1
+        ORDER BY p.name
+        "#,
+        cup_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(CupLineup {
+        published,
+        teams,
+        players,
+    })
+}
+
+/// The published lineup, cup managers also get the one they are working on.
+#[server]
+pub async fn get_cup_lineup(cup_id: i32) -> Result<CupLineup, ServerFnError> {
+    use crate::auth::user_from_session;
+    use crate::models::Permission;
+    use tracing::error;
+
+    let can_edit = user_from_session()
+        .await
+        .is_ok_and(|player| player.role.has_permission(Permission::EditCup));
+
+    match cup_lineup(cup_id, can_edit).await {
+        Ok(lineup) => Ok(lineup),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get the lineup.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+pub async fn add_cup_team(cup_id: i32, name: String) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let manager = require_permission(Permission::EditCup).await?;
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Err(ServerFnError::ServerError(
+            "The team name must be 1 to 100 characters.".to_string(),
+        ));
+    }
+
+    let mut tx = get_db().begin().await?;
+    let team = sqlx::query_as!(
+        CupTeam,
+        r#"
+        INSERT INTO cup_team (cup_id, name)
+        VALUES ($1, $2)
+        RETURNING cup_team_id, name
+        "#,
+        cup_id,
+        name
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_event(
+        &mut *tx,
+        manager.player_id,
+        "cup_lineup.add_team",
+        &format!("cup:{}/team:{}", cup_id, team.cup_team_id),
+        None,
+        snapshot(&team),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} added team {:?} to cup {:?}",
+        manager.player_id, team.cup_team_id, cup_id
+    );
+    Ok(())
+}
+
+/// Removes a team, its players go back to the unplaced.
+#[server]
+pub async fn remove_cup_team(cup_team_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let manager = require_permission(Permission::EditCup).await?;
+
+    let mut tx = get_db().begin().await?;
+    let Some(removed) = sqlx::query!(
+        r#"
+        DELETE FROM cup_team
+        WHERE cup_team_id = $1
+        RETURNING cup_id, name
+        "#,
+        cup_team_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    record_event(
+        &mut *tx,
+        manager.player_id,
+        "cup_lineup.remove_team",
+        &format!("cup:{}/team:{}", removed.cup_id, cup_team_id),
+        Some(serde_json::json!({ "cup_team_id": cup_team_id, "name": removed.name })),
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} removed team {:?} from cup {:?}",
+        manager.player_id, cup_team_id, removed.cup_id
+    );
+    Ok(())
+}
+
+/// Puts a registered player in a spot. A player already in the spot goes back to
+/// the unplaced.
+#[server]
+pub async fn place_cup_player(
+    cup_id: i32,
+    player_id: i32,
+    spot: LineupSpot,
+) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::{error, info};
+
+    let manager = require_permission(Permission::EditCup).await?;
+    if !CupLineup::spots(spot.cup_team_id).contains(&spot) {
+        return Err(ServerFnError::ServerError(
+            "No such spot in the lineup.".to_string(),
+        ));
+    }
+
+    let mut tx = get_db().begin().await?;
+    let before = sqlx::query_as!(
+        LineupSpot,
+        r#"
+        DELETE FROM cup_lineup
+        WHERE cup_id = $1 AND player_id = $2
+        RETURNING cup_team_id, position as "position: LineupPosition", line
+        "#,
+        cup_id,
+        player_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM cup_lineup
+        WHERE cup_team_id = $1 AND position = $2 AND line = $3
+        "#,
+        spot.cup_team_id,
+        spot.position as LineupPosition,
+        spot.line
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Fails unless both the player and the team belong to the cup
+    if let Err(e) = sqlx::query!(
+        r#"
+        INSERT INTO cup_lineup (cup_team_id, cup_id, position, line, player_id)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        spot.cup_team_id,
+        cup_id,
+        spot.position as LineupPosition,
+        spot.line,
+        player_id
+    )
+    .execute(&mut *tx)
+    .await
+    {
+        error!("Database error: {:?}", e);
+        return Err(ServerFnError::ServerError(
+            "Failed to place the player.".to_string(),
+        ));
+    }
+
+    record_event(
+        &mut *tx,
+        manager.player_id,
+        "cup_lineup.place",
+        &format!("cup:{}/player:{}", cup_id, player_id),
+        before.as_ref().and_then(snapshot),
+        snapshot(&spot),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} placed player {:?} in {:?} on cup {:?}",
+        manager.player_id, player_id, spot, cup_id
+    );
+    Ok(())
+}
+
+#[server]
+pub async fn unplace_cup_player(cup_id: i32, player_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let manager = require_permission(Permission::EditCup).await?;
+
+    let mut tx = get_db().begin().await?;
+    let Some(before) = sqlx::query_as!(
+        LineupSpot,
+        r#"
+        DELETE FROM cup_lineup
+        WHERE cup_id = $1 AND player_id = $2
+        RETURNING cup_team_id, position as "position: LineupPosition", line
+        "#,
+        cup_id,
+        player_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    record_event(
+        &mut *tx,
+        manager.player_id,
+        "cup_lineup.unplace",
+        &format!("cup:{}/player:{}", cup_id, player_id),
+        snapshot(&before),
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} took player {:?} out of the lineup on cup {:?}",
+        manager.player_id, player_id, cup_id
+    );
+    Ok(())
+}
+
+#[server]
+pub async fn publish_cup_lineup(cup_id: i32, published: bool) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let manager = require_permission(Permission::EditCup).await?;
+
+    let mut tx = get_db().begin().await?;
+    let updated = sqlx::query!(
+        r#"
+        UPDATE cup
+        SET lineup_published = $2
+        WHERE cup_id = $1
+        "#,
+        cup_id,
+        published
+    )
+    .execute(&mut *tx)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(ServerFnError::ServerError("No cup found.".to_string()));
+    }
+
+    record_event(
+        &mut *tx,
+        manager.player_id,
+        "cup_lineup.publish",
+        &format!("cup:{}", cup_id),
+        None,
+        Some(serde_json::json!({ "published": published })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} set lineup published {:?} on cup {:?}",
+        manager.player_id, published, cup_id
+    );
+    Ok(())
+}
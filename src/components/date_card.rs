@@ -1,8 +1,11 @@
 use chrono::{DateTime, Datelike, Utc};
 use leptos::prelude::*;
 
+use crate::timezone::to_club_time;
+
 #[component]
 pub fn DateCard(start: DateTime<Utc>, show_month: bool) -> impl IntoView {
+    let start = to_club_time(start);
     let date = start.date_naive().day();
     let month = match start.date_naive().month0() {
         0 => "Jan",
@@ -1,4 +1,5 @@
-use chrono::{DateTime, Utc};
+#[cfg(feature = "ssr")]
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -24,21 +25,43 @@ pub fn DatePicker(set_invalidate_gamedays: WriteSignal<bool>) -> impl IntoView {
                     Slut
                 </label>
                 <input type="time" required name="input_date[end]" class="input input-bordered" />
+                <div class="flex gap-2 mt-2">
+                    <div class="flex flex-col">
+                        <label for="input_date[max_skaters]">Max utespelare</label>
+                        <input
+                            type="number"
+                            required
+                            name="input_date[max_skaters]"
+                            class="input input-bordered max-w-32"
+                            min="0"
+                            value="20"
+                        />
+                    </div>
+                    <div class="flex flex-col">
+                        <label for="input_date[max_goalkeepers]">"Max målvakter"</label>
+                        <input
+                            type="number"
+                            required
+                            name="input_date[max_goalkeepers]"
+                            class="input input-bordered max-w-32"
+                            min="0"
+                            value="2"
+                        />
+                    </div>
+                </div>
                 <label class="label cursor-pointer mt-2">
                     <span class="label-text">Återkommande</span>
                     <input type="checkbox" class="toggle" bind:checked=show_repeat />
                 </label>
                 <Show when=move || { show_repeat.get() }>
-                    <label for="input_date[repeat]" class="max-w-40 mx-auto text-center">
-                        "varje vecka i (1-12) veckor"
+                    <label for="input_date[season_end]" class="max-w-40 mx-auto text-center">
+                        "varje vecka till och med"
                     </label>
                     <input
-                        type="number"
+                        type="date"
                         required
-                        name="input_date[repeat]"
+                        name="input_date[season_end]"
                         class="input input-bordered"
-                        min="1"
-                        max="12"
                     />
                 </Show>
                 <button class="btn btn-success mt-4" type="submit">
@@ -54,14 +77,21 @@ struct InputDate {
     date: String,
     start: String,
     end: String,
-    repeat: Option<i64>,
+    max_skaters: i32,
+    max_goalkeepers: i32,
+    season_end: Option<String>,
 }
 
 #[server]
 async fn add_date(input_date: InputDate) -> Result<(), ServerFnError> {
-    use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+    use crate::auth::require_permission;
+    use crate::models::Permission;
+    use crate::timezone::from_club_time;
+    use chrono::{NaiveDate, NaiveTime};
     use tracing::info;
 
+    let organizer = require_permission(Permission::CreateGameday).await?;
+
     info!("Date submit: {:?}", input_date);
 
     // Parse date and time
@@ -69,54 +99,98 @@ async fn add_date(input_date: InputDate) -> Result<(), ServerFnError> {
     let start_time = NaiveTime::parse_from_str(&input_date.start, "%H:%M")?;
     let end_time = NaiveTime::parse_from_str(&input_date.end, "%H:%M")?;
 
-    // Combine date and time into NaiveDateTime
-    let start_datetime = NaiveDateTime::new(date, start_time);
-    let end_datetime = NaiveDateTime::new(date, end_time);
+    // The input is wall-clock time in the club's timezone
+    let to_utc = |date: NaiveDate, time: NaiveTime| -> Result<DateTime<Utc>, ServerFnError> {
+        from_club_time(date, time).ok_or_else(|| {
+            ServerFnError::ServerError(format!("{} {} is not a valid local time.", date, time))
+        })
+    };
+
+    let start_datetime = to_utc(date, start_time)?;
+    let end_datetime = to_utc(date, end_time)?;
     info!(
-        "Parsed NaiveStart: {:?}, NaiveEnd: {:?}",
+        "Parsed Start: {:?}, End: {:?}",
         start_datetime, end_datetime
     );
 
-    insert_gameday(start_datetime.and_utc(), end_datetime.and_utc()).await?;
-
-    if let Some(repeat) = input_date.repeat {
-        info!("Adding repeating date for {} weeks", repeat);
+    if let Some(season_end) = input_date.season_end {
+        let season_end = NaiveDate::parse_from_str(&season_end, "%Y-%m-%d")?;
+        info!("Adding weekly series until {}", season_end);
 
-        for n in 1..=repeat {
-            let new_start_date = start_datetime + Duration::weeks(n);
-            let new_end_date = end_datetime + Duration::weeks(n);
-
-            insert_gameday(new_start_date.and_utc(), new_end_date.and_utc()).await?;
-        }
+        insert_gameday_series(
+            organizer.player_id,
+            date,
+            season_end,
+            start_time,
+            end_time,
+            input_date.max_skaters,
+            input_date.max_goalkeepers,
+        )
+        .await?;
+        return Ok(());
     }
 
+    insert_gameday(
+        organizer.player_id,
+        start_datetime,
+        end_datetime,
+        input_date.max_skaters,
+        input_date.max_goalkeepers,
+    )
+    .await?;
+
     Ok(())
 }
 
-#[server]
+#[cfg(feature = "ssr")]
 async fn insert_gameday(
+    actor_id: i32,
     start_date: DateTime<Utc>,
     end_date: DateTime<Utc>,
+    max_skaters: i32,
+    max_goalkeepers: i32,
 ) -> Result<(), ServerFnError> {
-    use crate::auth::validate_admin;
+    use crate::audit::record_event;
     use crate::database::get_db;
+    use crate::pages::season_page::assign_seasons;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
+    let created = async {
+        let mut tx = get_db().begin().await?;
+        let gameday_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO gameday (start_date, end_date, max_skaters, max_goalkeepers)
+            VALUES ($1, $2, $3, $4)
+            RETURNING gameday_id
+            "#,
+            start_date,
+            end_date,
+            max_skaters,
+            max_goalkeepers
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        assign_seasons(&mut tx, &[gameday_id]).await?;
+
+        record_event(
+            &mut *tx,
+            actor_id,
+            "gameday.create",
+            &format!("gameday:{}", gameday_id),
+            None,
+            Some(serde_json::json!({
+                "start_date": start_date,
+                "end_date": end_date,
+                "max_skaters": max_skaters,
+                "max_goalkeepers": max_goalkeepers,
+            })),
+        )
+        .await?;
+        tx.commit().await
     }
-    let pool = get_db();
-    match sqlx::query!(
-        r#"
-        INSERT INTO gameday (start_date, end_date)
-        VALUES ($1, $2)
-        "#,
-        start_date,
-        end_date
-    )
-    .execute(pool)
-    .await
-    {
+    .await;
+
+    match created {
         Ok(_) => {
             info!(
                 "Date inserted successfully! Start:{:?}, End:{:?}",
@@ -132,3 +206,136 @@ async fn insert_gameday(
         }
     }
 }
+
+#[cfg(feature = "ssr")]
+async fn insert_gameday_series(
+    actor_id: i32,
+    season_start: NaiveDate,
+    season_end: NaiveDate,
+    start_time: NaiveTime,
+    end_time: NaiveTime,
+    max_skaters: i32,
+    max_goalkeepers: i32,
+) -> Result<(), ServerFnError> {
+    use chrono::Duration;
+    use tracing::{error, info};
+
+    if season_end < season_start || season_end - season_start > Duration::days(366) {
+        return Err(ServerFnError::ServerError(
+            "A series can be at most one year long.".to_string(),
+        ));
+    }
+
+    match create_series(
+        actor_id,
+        season_start,
+        season_end,
+        start_time,
+        end_time,
+        max_skaters,
+        max_goalkeepers,
+    )
+    .await
+    {
+        Ok((series_id, count)) => {
+            info!(
+                "Series {:?} inserted successfully with {} gamedays",
+                series_id, count
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!("Failed to create series: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to create gameday series.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Creates a weekly series and a gameday for every occurrence in the season.
+/// Returns the series id and the number of gamedays created.
+#[cfg(feature = "ssr")]
+async fn create_series(
+    actor_id: i32,
+    season_start: NaiveDate,
+    season_end: NaiveDate,
+    start_time: NaiveTime,
+    end_time: NaiveTime,
+    max_skaters: i32,
+    max_goalkeepers: i32,
+) -> Result<(i32, usize), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::database::get_db;
+    use crate::models::GamedaySeries;
+    use crate::pages::season_page::assign_seasons;
+    use crate::timezone::from_club_time;
+    use chrono::Datelike;
+
+    let mut tx = get_db().begin().await?;
+
+    let series = sqlx::query_as!(
+        GamedaySeries,
+        r#"
+        INSERT INTO gameday_series
+            (weekday, start_time, end_time, season_start, season_end, max_skaters, max_goalkeepers)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING
+            series_id, weekday, start_time, end_time, season_start, season_end, exceptions,
+            max_skaters, max_goalkeepers, NULL::bigint as upcoming_count
+        "#,
+        season_start.weekday().number_from_monday() as i16,
+        start_time,
+        end_time,
+        season_start,
+        season_end,
+        max_skaters,
+        max_goalkeepers
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let occurrences = series.occurrences();
+    let mut gameday_ids = Vec::with_capacity(occurrences.len());
+    for date in &occurrences {
+        // Each occurrence is converted on its own so DST changes don't move the local time
+        let (Some(start_date), Some(end_date)) = (
+            from_club_time(*date, series.start_time),
+            from_club_time(*date, series.end_time),
+        ) else {
+            return Err(ServerFnError::ServerError(format!(
+                "{} is not a valid local time.",
+                date
+            )));
+        };
+
+        let gameday_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO gameday (start_date, end_date, max_skaters, max_goalkeepers, series_id)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING gameday_id
+            "#,
+            start_date,
+            end_date,
+            series.max_skaters,
+            series.max_goalkeepers,
+            series.series_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        gameday_ids.push(gameday_id);
+    }
+    assign_seasons(&mut tx, &gameday_ids).await?;
+
+    record_event(
+        &mut *tx,
+        actor_id,
+        "series.create",
+        &format!("series:{}", series.series_id),
+        None,
+        snapshot(&series),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok((series.series_id, occurrences.len()))
+}
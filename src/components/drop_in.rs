@@ -0,0 +1,507 @@
+use leptos::prelude::*;
+
+use crate::{
+    components::loading::Loading,
+    models::{BankImport, DropInFee, DropInPayment},
+    timezone::to_club_time,
+};
+
+/// The Swish QR code of a drop-in fee, with the details for paying by hand.
+#[component]
+pub fn SwishPayment(payment: DropInPayment) -> impl IntoView {
+    view! {
+        <div class="flex flex-col items-center gap-1">
+            <h3 class="text-lg font-bold">"Betala drop-in med Swish"</h3>
+            <div class="bg-white p-2 rounded-box w-48" inner_html=payment.qr_svg></div>
+            <p>{format!("{} kr till {}", payment.amount, payment.payee)}</p>
+            <p class="text-sm">"Meddelande: "{payment.message}</p>
+        </div>
+    }
+}
+
+/// Unpaid drop-in fees for the admin, marked as paid by hand or from a bank statement.
+#[component]
+pub fn DropInFees() -> impl IntoView {
+    let mark_paid = ServerAction::<MarkDropInPaid>::new();
+    let import = ServerAction::<ImportBankStatement>::new();
+    let fees = Resource::new(
+        move || (mark_paid.version().get(), import.version().get()),
+        |_| get_unpaid_drop_in_fees(),
+    );
+    let import_result = move || {
+        import.value().get().map(|result| match result {
+            Ok(import) if import.paid.is_empty() => {
+                format!("{} rader lästes, inga betalningar hittades.", import.rows)
+            }
+            Ok(import) => format!(
+                "{} rader lästes, betalda: {}",
+                import.rows,
+                import.paid.join(", ")
+            ),
+            Err(_) => "Kunde inte läsa kontoutdraget.".to_string(),
+        })
+    };
+
+    view! {
+        <h3 class="text-center text-xl mt-6">"Obetalda drop-in-avgifter"</h3>
+        <Transition fallback=move || view! { <Loading /> }>
+            {move || Suspend::new(async move {
+                let fees = fees.await.unwrap_or_default();
+                if fees.is_empty() {
+                    return view! { <p class="m-2">"Alla drop-in-avgifter är betalda."</p> }
+                        .into_any();
+                }
+                view! {
+                    <div class="overflow-x-auto w-11/12">
+                        <table class="table table-sm">
+                            <thead>
+                                <tr>
+                                    <th>"Speldag"</th>
+                                    <th>"Namn"</th>
+                                    <th>"Belopp"</th>
+                                    <th>"Meddelande"</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {fees
+                                    .into_iter()
+                                    .map(|fee| {
+                                        let message = fee.message();
+                                        view! {
+                                            <tr>
+                                                <td class="whitespace-nowrap">
+                                                    {to_club_time(fee.start_date)
+                                                        .format("%Y-%m-%d")
+                                                        .to_string()}
+                                                </td>
+                                                <td>{fee.name}</td>
+                                                <td>{format!("{} kr", fee.amount)}</td>
+                                                <td>{message}</td>
+                                                <td>
+                                                    <ActionForm action=mark_paid>
+                                                        <input
+                                                            type="hidden"
+                                                            name="gameday_id"
+                                                            value=fee.gameday_id
+                                                        />
+                                                        <input
+                                                            type="hidden"
+                                                            name="player_id"
+                                                            value=fee.player_id
+                                                        />
+                                                        <button type="submit" class="btn btn-xs btn-success">
+                                                            "Betald"
+                                                        </button>
+                                                    </ActionForm>
+                                                </td>
+                                            </tr>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </tbody>
+                        </table>
+                    </div>
+                }
+                    .into_any()
+            })}
+        </Transition>
+        <ActionForm action=import>
+            <div class="flex flex-col items-center gap-2 m-2">
+                <label for="statement">
+                    "Klistra in kontoutdraget som CSV, Swish-betalningar med rätt meddelande och belopp markeras som betalda"
+                </label>
+                <textarea
+                    id="statement"
+                    name="statement"
+                    required
+                    rows="6"
+                    class="textarea textarea-bordered w-full max-w-2xl font-mono text-xs"
+                ></textarea>
+                <button type="submit" class="btn btn-sm btn-primary">
+                    "Importera"
+                </button>
+            </div>
+        </ActionForm>
+        <p class="text-center m-2">{import_result}</p>
+    }
+}
+
+/// Drop-in fees nobody has paid yet, only those of a player or a gameday when given.
+/// Members of the gameday's season pay the season fee instead.
+#[cfg(feature = "ssr")]
+pub async fn unpaid_drop_in_fees(
+    player_id: Option<i32>,
+    gameday_id: Option<i32>,
+) -> Result<Vec<DropInFee>, sqlx::Error> {
+    use crate::database::get_db;
+
+    sqlx::query_as!(
+        DropInFee,
+        r#"
+        SELECT
+            pg.gameday_id,
+            pg.player_id,
+            p.name,
+            g.start_date,
+            s.drop_in_fee as amount
+        FROM player_gameday pg
+        JOIN player p ON pg.player_id = p.player_id
+        JOIN gameday g ON pg.gameday_id = g.gameday_id
+        JOIN season s ON g.season_id = s.season_id
+        WHERE s.drop_in_fee > 0
+          AND NOT EXISTS (
+              SELECT 1 FROM membership m
+              WHERE m.season_id = s.season_id AND m.player_id = pg.player_id
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM drop_in_payment d
+              WHERE d.gameday_id = pg.gameday_id AND d.player_id = pg.player_id
+          )
+          AND ($1::int IS NULL OR pg.player_id = $1)
+          AND ($2::int IS NULL OR pg.gameday_id = $2)
+        ORDER BY g.start_date, p.name
+        "#,
+        player_id,
+        gameday_id
+    )
+    .fetch_all(get_db())
+    .await
+}
+
+/// The Swish payment request for what the player owes for a gameday, `None` when nothing
+/// is owed or no Swish number is configured.
+#[cfg(feature = "ssr")]
+pub async fn drop_in_payment(
+    player_id: i32,
+    gameday_id: i32,
+) -> Result<Option<DropInPayment>, ServerFnError> {
+    use crate::config::get_config;
+
+    let Some(payee) = &get_config().club.swish_number else {
+        return Ok(None);
+    };
+    let fees = unpaid_drop_in_fees(Some(player_id), Some(gameday_id)).await?;
+    match fees.first() {
+        Some(fee) => Ok(Some(payment_request(payee, fee)?)),
+        None => Ok(None),
+    }
+}
+
+/// Renders the QR code of Swish's prefilled payment format,
+/// `C<payee>;<amount>;<message>;<locked fields>` where 7 locks all three fields.
+#[cfg(feature = "ssr")]
+fn payment_request(payee: &str, fee: &DropInFee) -> Result<DropInPayment, ServerFnError> {
+    use qrcode::{render::svg, QrCode};
+
+    let message = fee.message();
+    let payload = format!("C{};{};{};7", payee, fee.amount, message);
+    let code = QrCode::new(payload.as_bytes())?;
+    let qr_svg = code.render::<svg::Color>().min_dimensions(200, 200).build();
+
+    Ok(DropInPayment {
+        payee: payee.to_string(),
+        amount: fee.amount,
+        message,
+        qr_svg,
+    })
+}
+
+/// Amount of a bank statement field, `1 500,00` and `1500.00` alike.
+#[cfg(feature = "ssr")]
+fn parse_amount(field: &str) -> Option<f64> {
+    field
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .replace(',', ".")
+        .parse()
+        .ok()
+}
+
+/// Finds the fees paid in a bank statement. Banks export different columns, so a row pays
+/// a fee when one field is its message and another holds its amount. The whole field has
+/// to match, "Drop-in 12 Anna" is part of "Drop-in 12 Annabelle". Every row pays at most
+/// one fee. Returns the number of rows and the fees paid.
+#[cfg(feature = "ssr")]
+fn match_statement(
+    statement: &str,
+    fees: Vec<DropInFee>,
+) -> Result<(usize, Vec<DropInFee>), csv::Error> {
+    // Swedish banks separate with semicolons since the decimal separator is a comma
+    let first_line = statement.lines().next().unwrap_or_default();
+    let delimiter = if first_line.matches(';').count() > first_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+    let rows = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(statement.as_bytes())
+        .records()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut used = vec![false; rows.len()];
+    let mut paid = Vec::new();
+    for fee in fees {
+        let message = fee.message().to_lowercase();
+        let found = rows.iter().enumerate().position(|(index, row)| {
+            !used[index]
+                && row
+                    .iter()
+                    .any(|field| field.trim().to_lowercase() == message)
+                && row.iter().any(|field| {
+                    parse_amount(field)
+                        .is_some_and(|amount| (amount - f64::from(fee.amount)).abs() < 0.005)
+                })
+        });
+        if let Some(index) = found {
+            used[index] = true;
+            paid.push(fee);
+        }
+    }
+    Ok((rows.len(), paid))
+}
+
+/// Records a drop-in fee as paid.
+#[cfg(feature = "ssr")]
+async fn record_payment(
+    conn: &mut sqlx::PgConnection,
+    actor_id: i32,
+    fee: &DropInFee,
+    source: &str,
+) -> Result<(), sqlx::Error> {
+    use crate::audit::record_event;
+
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO drop_in_payment (gameday_id, player_id, amount)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (gameday_id, player_id) DO NOTHING
+        "#,
+        fee.gameday_id,
+        fee.player_id,
+        fee.amount
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    if inserted.rows_affected() > 0 {
+        record_event(
+            &mut *conn,
+            actor_id,
+            "drop_in.paid",
+            &format!("gameday:{}/player:{}", fee.gameday_id, fee.player_id),
+            None,
+            Some(serde_json::json!({ "amount": fee.amount, "source": source })),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// The logged-in player's payment request for a gameday, if the player owes a drop-in fee.
+#[server]
+pub async fn get_drop_in_payment(gameday_id: i32) -> Result<Option<DropInPayment>, ServerFnError> {
+    use crate::auth::require_player;
+    use tracing::error;
+
+    let user = require_player().await?;
+    match drop_in_payment(user.player_id, gameday_id).await {
+        Ok(payment) => Ok(payment),
+        Err(e) => {
+            error!("Failed to create payment request: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get the payment request.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn get_unpaid_drop_in_fees() -> Result<Vec<DropInFee>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::models::Permission;
+    use tracing::error;
+
+    require_permission(Permission::ManagePlayers).await?;
+
+    match unpaid_drop_in_fees(None, None).await {
+        Ok(fees) => Ok(fees),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get drop-in fees.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn mark_drop_in_paid(gameday_id: i32, player_id: i32) -> Result<(), ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use http::StatusCode;
+    use tracing::info;
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+
+    let fees = unpaid_drop_in_fees(Some(player_id), Some(gameday_id)).await?;
+    let Some(fee) = fees.first() else {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::ServerError(
+            "No unpaid drop-in fee found.".to_string(),
+        ));
+    };
+
+    let mut tx = get_db().begin().await?;
+    record_payment(&mut tx, admin.player_id, fee, "manual").await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} marked drop-in of player {:?} on gameday {:?} as paid",
+        admin.player_id, player_id, gameday_id
+    );
+    Ok(())
+}
+
+/// Marks the drop-in fees found in a pasted bank statement as paid.
+#[server]
+async fn import_bank_statement(statement: String) -> Result<BankImport, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use http::StatusCode;
+    use tracing::info;
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+
+    let fees = unpaid_drop_in_fees(None, None).await?;
+    let (rows, paid) = match match_statement(&statement, fees) {
+        Ok(matched) => matched,
+        Err(e) => {
+            info!("Could not read bank statement: {:?}", e);
+            let opts = expect_context::<leptos_axum::ResponseOptions>();
+            opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::ServerError(
+                "The bank statement is not valid CSV.".to_string(),
+            ));
+        }
+    };
+
+    let mut tx = get_db().begin().await?;
+    for fee in &paid {
+        record_payment(&mut tx, admin.player_id, fee, "bank_statement").await?;
+    }
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} imported a bank statement, {} of {} rows paid drop-in fees",
+        admin.player_id,
+        paid.len(),
+        rows
+    );
+    Ok(BankImport {
+        rows,
+        paid: paid.iter().map(DropInFee::message).collect(),
+    })
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn fee(gameday_id: i32, player_id: i32, name: &str, amount: i32) -> DropInFee {
+        DropInFee {
+            gameday_id,
+            player_id,
+            name: name.to_string(),
+            start_date: Utc.with_ymd_and_hms(2026, 10, 18, 17, 0, 0).unwrap(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn parses_swedish_and_plain_amounts() {
+        assert_eq!(parse_amount("1 500,00"), Some(1500.0));
+        assert_eq!(parse_amount("1\u{a0}500,00"), Some(1500.0));
+        assert_eq!(parse_amount("1500.00"), Some(1500.0));
+        assert_eq!(parse_amount(" 100 "), Some(100.0));
+        assert_eq!(parse_amount("-100,00"), Some(-100.0));
+        assert_eq!(parse_amount("Drop-in 12 Anna"), None);
+        assert_eq!(parse_amount(""), None);
+    }
+
+    #[test]
+    fn matches_message_and_amount() {
+        let statement = "\
+Datum;Text;Belopp
+2026-10-18;Drop-in 12 Anna;100,00
+2026-10-18;Drop-in 12 Bertil;50,00
+2026-10-18;Drop-in 13 Cesar;-100,00
+";
+        let fees = vec![
+            fee(12, 1, "Anna", 100),
+            fee(12, 2, "Bertil", 100),
+            fee(13, 3, "Cesar", 100),
+        ];
+
+        let (rows, paid) = match_statement(statement, fees).unwrap();
+
+        assert_eq!(rows, 4);
+        // Bertil paid the wrong amount and Cesar's row is a refund
+        assert_eq!(
+            paid.iter().map(|fee| fee.player_id).collect::<Vec<_>>(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn a_name_is_not_matched_by_a_longer_name() {
+        let statement = "2026-10-18,Drop-in 12 Annabelle,100.00\n";
+        let fees = vec![fee(12, 1, "Anna", 100), fee(12, 2, "Annabelle", 100)];
+
+        let (_, paid) = match_statement(statement, fees).unwrap();
+
+        assert_eq!(
+            paid.iter().map(|fee| fee.player_id).collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn every_row_pays_one_fee() {
+        let statement = "2026-10-18;drop-in 12 anna;100\n";
+        let fees = vec![fee(12, 1, "Anna", 100), fee(12, 1, "Anna", 100)];
+
+        let (_, paid) = match_statement(statement, fees).unwrap();
+
+        assert_eq!(paid.len(), 1);
+    }
+
+    #[test]
+    fn message_fits_in_swish() {
+        let long = fee(12, 1, "Anna-Karin Maria Elisabeth Johansson Lindqvist", 100);
+        let message = long.message();
+
+        assert_eq!(message.chars().count(), DropInFee::MAX_MESSAGE_LENGTH);
+        assert_eq!(message, "Drop-in 12 Anna-Karin Maria Elisabeth Johansson Li");
+        // Characters Swish or the bank export could change are left out
+        assert_eq!(fee(12, 1, "Åsa O'Brien!", 100).message(), "Drop-in 12 Åsa OBrien");
+    }
+
+    #[test]
+    fn payment_request_locks_the_fields() {
+        let payment = payment_request("1231234567", &fee(12, 1, "Anna", 100)).unwrap();
+
+        assert_eq!(payment.payee, "1231234567");
+        assert_eq!(payment.amount, 100);
+        assert_eq!(payment.message, "Drop-in 12 Anna");
+        assert!(payment.qr_svg.starts_with("<?xml"));
+    }
+}
@@ -4,10 +4,10 @@ use reactive_stores::Store;
 
 use crate::{
     components::{
-        date_card::DateCard, join_button::JoinButton, leave_button::LeaveButton,
-        num_players::NumPlayers, time_card::TimeCard,
+        date_card::DateCard, drop_in::SwishPayment, join_button::JoinButton,
+        leave_button::LeaveButton, num_players::NumPlayers, time_card::TimeCard,
     },
-    models::{Gameday, GlobalState, GlobalStateStoreFields},
+    models::{DropInPayment, Gameday, GlobalState, GlobalStateStoreFields},
 };
 
 #[component]
@@ -18,6 +18,8 @@ pub fn GamedayCard(
 ) -> impl IntoView {
     let state = expect_context::<Store<GlobalState>>();
     let logged_in = state.logged_in();
+    let max_players = gameday.max_skaters + gameday.max_goalkeepers;
+    let (payment, set_payment) = signal(None::<DropInPayment>);
 
     view! {
         <div class="card flex-row items-center justify-around bg-base-100 shadow-xl border">
@@ -28,7 +30,10 @@ pub fn GamedayCard(
                         <DateCard start=gameday.start_date show_month=true />
                         <div class="flex flex-col items-center justify-evenly mr-2">
                             <TimeCard start=gameday.start_date end=gameday.end_date />
-                            <NumPlayers num_players=gameday.player_count.unwrap_or(0) />
+                            <NumPlayers
+                                num_players=gameday.player_count.unwrap_or(0)
+                                max_players
+                            />
                         </div>
                     }
                 }
@@ -39,8 +44,27 @@ pub fn GamedayCard(
                 <div class="flex flex-col items-center justify-evenly mr-2">
                     <A href=format!("/day/{}", gameday.gameday_id)>
                         <TimeCard start=gameday.start_date end=gameday.end_date />
-                        <NumPlayers num_players=gameday.player_count.unwrap_or(0) />
+                        <NumPlayers num_players=gameday.player_count.unwrap_or(0) max_players />
                     </A>
+                    <Show when=move || { is_player_joined(gamedays_joined.get(), gameday.gameday_id) }>
+                        {move || match queue_position(gamedays_joined.get(), gameday.gameday_id) {
+                            Some(position) => {
+                                view! {
+                                    <span class="badge badge-warning">
+                                        {format!("Reserv #{}", position)}
+                                    </span>
+                                }
+                                    .into_any()
+                            }
+                            None => {
+                                view! { <span class="badge badge-success">"Bekräftad"</span> }
+                                    .into_any()
+                            }
+                        }}
+                    </Show>
+                    <Show when=move || { is_time_changed(gamedays_joined.get(), gameday.gameday_id) }>
+                        <span class="badge badge-error mt-1">"Ny tid!"</span>
+                    </Show>
                 </div>
             </Show>
             <Show
@@ -50,6 +74,7 @@ pub fn GamedayCard(
                         <JoinButton
                             gameday_id=gameday.gameday_id
                             set_gamedays_joined=set_gamedays_joined
+                            set_payment=set_payment
                         />
                     }
                 }
@@ -60,6 +85,24 @@ pub fn GamedayCard(
                     set_gamedays_joined=set_gamedays_joined
                 />
             </Show>
+            {move || {
+                payment
+                    .get()
+                    .map(|payment| {
+                        view! {
+                            <div class="modal modal-open">
+                                <div class="modal-box">
+                                    <SwishPayment payment />
+                                    <div class="modal-action">
+                                        <button class="btn" on:click=move |_| set_payment.set(None)>
+                                            "Stäng"
+                                        </button>
+                                    </div>
+                                </div>
+                            </div>
+                        }
+                    })
+            }}
         </div>
     }
 }
@@ -68,3 +111,16 @@ fn is_player_joined(gamedays_joined: Vec<Gameday>, gameday_id: i32) -> bool {
         .iter()
         .any(|day| day.gameday_id == gameday_id)
 }
+
+fn queue_position(gamedays_joined: Vec<Gameday>, gameday_id: i32) -> Option<i64> {
+    gamedays_joined
+        .iter()
+        .find(|day| day.gameday_id == gameday_id)
+        .and_then(|day| day.queue_position)
+}
+
+fn is_time_changed(gamedays_joined: Vec<Gameday>, gameday_id: i32) -> bool {
+    gamedays_joined
+        .iter()
+        .any(|day| day.gameday_id == gameday_id && day.time_changed)
+}
@@ -23,6 +23,11 @@ pub fn GamedayCreate(
                     <NumPlayers num_players=gameday.player_count.unwrap_or(0) />
                 </A>
             </div>
+            <A href=format!("/day/edit/{}", gameday.gameday_id)>
+                <button class="btn btn-warning h-20 m-2 flex-col">
+                    <p class="font-bold">Ändra</p>
+                </button>
+            </A>
             <button
                 class="btn btn-error h-20 m-2 flex-col"
                 on:click=move |_| {
@@ -43,28 +48,93 @@ pub fn GamedayCreate(
 
 #[server]
 async fn delete_gameday(gameday_id: i32, redirect_on_delete: bool) -> Result<(), ServerFnError> {
-    use crate::auth::validate_admin;
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
     use crate::database::get_db;
+    use crate::mailer::{gameday_time, notify_players, Notification};
+    use crate::models::Permission;
+    use crate::timezone::club_timezone;
+    use chrono::Utc;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
-    }
+    let organizer = require_permission(Permission::EditGameday).await?;
 
-    let pool = get_db();
+    let deleted = async {
+        let mut tx = get_db().begin().await?;
 
-    match sqlx::query!(
-        r#"
-        DELETE FROM gameday
-        WHERE gameday_id = $1
-        "#,
-        gameday_id
-    )
-    .execute(pool)
-    .await
-    {
-        Ok(_) => {
+        // Cancelling an occurrence of a series is recorded as an exception on the series.
+        // The registrations are read before the cascade removes them.
+        let deleted = sqlx::query!(
+            r#"
+            WITH exception AS (
+                UPDATE gameday_series s
+                SET exceptions = array_append(s.exceptions, (g.start_date AT TIME ZONE $2)::date)
+                FROM gameday g
+                WHERE g.gameday_id = $1 AND g.series_id = s.series_id
+            ),
+            deleted AS (
+                DELETE FROM gameday
+                WHERE gameday_id = $1
+                RETURNING start_date, end_date, max_skaters, max_goalkeepers, series_id
+            )
+            SELECT
+                d.start_date,
+                d.end_date,
+                d.max_skaters,
+                d.max_goalkeepers,
+                d.series_id,
+                ARRAY(
+                    SELECT player_id FROM player_gameday WHERE gameday_id = $1
+                ) as "registered!",
+                ARRAY(
+                    SELECT player_id FROM waitlist_gameday WHERE gameday_id = $1
+                ) as "waitlist!"
+            FROM deleted d
+            "#,
+            gameday_id,
+            club_timezone().name()
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(gameday) = &deleted {
+            record_event(
+                &mut *tx,
+                organizer.player_id,
+                "gameday.delete",
+                &format!("gameday:{}", gameday_id),
+                Some(serde_json::json!({
+                    "start_date": gameday.start_date,
+                    "end_date": gameday.end_date,
+                    "max_skaters": gameday.max_skaters,
+                    "max_goalkeepers": gameday.max_goalkeepers,
+                    "series_id": gameday.series_id,
+                    "registered": gameday.registered,
+                    "waitlist": gameday.waitlist,
+                })),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(deleted)
+    }
+    .await;
+
+    match deleted {
+        Ok(deleted) => {
             info!("Gameday {:?} deleted successfully.", gameday_id);
+            if let Some(gameday) = deleted.filter(|gameday| gameday.start_date > Utc::now()) {
+                notify_players(
+                    Notification::Cancellation,
+                    [gameday.registered, gameday.waitlist].concat(),
+                    "Speldag inställd".to_string(),
+                    format!(
+                        "Speldagen {} är inställd.",
+                        gameday_time(gameday.start_date, gameday.end_date)
+                    ),
+                );
+            }
             if redirect_on_delete {
                 leptos_axum::redirect("/");
             }
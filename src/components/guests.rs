@@ -0,0 +1,344 @@
+use leptos::prelude::*;
+
+use crate::models::Guest;
+
+/// Adding and removing guests, the guest lists are refetched when either has run.
+#[derive(Clone, Copy)]
+pub struct GuestActions {
+    pub add: ServerAction<AddGuest>,
+    pub remove: ServerAction<RemoveGuest>,
+}
+
+impl GuestActions {
+    pub fn new() -> Self {
+        GuestActions {
+            add: ServerAction::new(),
+            remove: ServerAction::new(),
+        }
+    }
+
+    pub fn version(&self) -> usize {
+        self.add.version().get() + self.remove.version().get()
+    }
+}
+
+impl Default for GuestActions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lets a registered player bring a friend without a login.
+#[component]
+pub fn AddGuestForm(gameday_id: i32, actions: GuestActions) -> impl IntoView {
+    let error = move || {
+        actions
+            .add
+            .value()
+            .get()
+            .and_then(|result| result.err())
+            .map(|_| {
+                "Kunde inte lägga till gästen. Platserna kan vara slut, speldagen ha börjat \
+                 eller så har du redan med dig så många gäster som tillåts."
+            })
+    };
+
+    view! {
+        <ActionForm action=actions.add>
+            <div class="flex flex-wrap items-center justify-center gap-2 mt-4">
+                <input type="hidden" name="gameday_id" value=gameday_id />
+                <input
+                    type="text"
+                    name="name"
+                    placeholder="Gästens namn"
+                    class="input input-bordered input-sm"
+                    maxlength=Guest::MAX_NAME_LENGTH
+                    required
+                />
+                <select class="select select-bordered select-sm" name="is_goalkeeper">
+                    <option value="false">"Utespelare"</option>
+                    <option value="true">"Målvakt"</option>
+                </select>
+                <button type="submit" class="btn btn-sm btn-primary">
+                    "Ta med gäst"
+                </button>
+            </div>
+        </ActionForm>
+        <p class="text-error">{error}</p>
+    }
+}
+
+/// A guest in the roster, the host and organizers may remove it.
+#[component]
+pub fn GuestEntry(guest: Guest, can_remove: bool, actions: GuestActions) -> impl IntoView {
+    view! {
+        <li class="flex flex-col items-center my-1">
+            <p>{guest.name}" (gäst till "{guest.host_name}")"</p>
+            {can_remove
+                .then(|| {
+                    view! {
+                        <ActionForm action=actions.remove>
+                            <input type="hidden" name="guest_id" value=guest.guest_id />
+                            <button type="submit" class="btn btn-xs btn-error">
+                                "Ta bort gäst"
+                            </button>
+                        </ActionForm>
+                    }
+                })}
+        </li>
+    }
+}
+
+#[server]
+pub async fn get_guests_by_gameday(gameday_id: i32) -> Result<Vec<Guest>, ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use crate::models::{Attendance, Permission};
+    use tracing::error;
+
+    let user = require_player().await?;
+    let manages_roster = user.role.has_permission(Permission::ManageRoster);
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        Guest,
+        r#"
+        SELECT
+            gg.guest_id,
+            gg.name,
+            gg.is_goalkeeper,
+            gg.host_id,
+            p.name as host_name,
+            CASE WHEN $2 THEN gg.attendance END as "attendance: Attendance"
+        FROM guest_gameday gg
+        JOIN player p ON gg.host_id = p.player_id
+        WHERE gg.gameday_id = $1
+        ORDER BY gg.created_at
+        "#,
+        gameday_id,
+        manages_roster
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(guests) => Ok(guests),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get guests.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Adds a guest of the logged-in player before the gameday starts. The host needs a
+/// confirmed spot and the guest gets one too, guests don't go on the waitlist. A player
+/// brings at most `club.max_guests_per_player` guests.
+#[server]
+pub async fn add_guest(
+    gameday_id: i32,
+    name: String,
+    is_goalkeeper: bool,
+) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_player;
+    use crate::components::join_button::confirmed_count;
+    use crate::components::leave_button::{notify_promoted, promote_from_waitlist};
+    use crate::config::get_config;
+    use crate::database::get_db;
+    use http::StatusCode;
+    use tracing::info;
+
+    let user = require_player().await?;
+
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > Guest::MAX_NAME_LENGTH {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::ServerError(
+            "The guest needs a name.".to_string(),
+        ));
+    }
+
+    let mut tx = get_db().begin().await?;
+
+    // Lock the gameday so the guest can't take a spot that is being handed out
+    let capacity = sqlx::query!(
+        r#"
+        SELECT
+            g.max_skaters,
+            g.max_goalkeepers,
+            g.start_date <= NOW() as "started!",
+            EXISTS (
+                SELECT 1 FROM player_gameday pg
+                WHERE pg.gameday_id = g.gameday_id AND pg.player_id = $2
+            ) as "registered!",
+            (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id AND gg.host_id = $2
+            ) as "guest_count!"
+        FROM gameday g
+        WHERE g.gameday_id = $1
+        FOR UPDATE
+        "#,
+        gameday_id,
+        user.player_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(capacity) = capacity.filter(|capacity| capacity.registered) else {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "Only registered players can bring guests.".to_string(),
+        ));
+    };
+    if capacity.started {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "The gameday has already started.".to_string(),
+        ));
+    }
+    // One player shouldn't be able to fill the gameday with friends
+    if capacity.guest_count >= i64::from(get_config().club.max_guests_per_player) {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "No more guests allowed.".to_string(),
+        ));
+    }
+
+    // The waitlist goes before guests
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+
+    let max_players = if is_goalkeeper {
+        capacity.max_goalkeepers
+    } else {
+        capacity.max_skaters
+    };
+    if confirmed_count(&mut tx, gameday_id, is_goalkeeper).await? >= i64::from(max_players) {
+        // Keep the promotions even if the guest doesn't fit
+        tx.commit().await?;
+        notify_promoted(gameday_id, promoted);
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "The gameday is full.".to_string(),
+        ));
+    }
+
+    let guest_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO guest_gameday (gameday_id, host_id, name, is_goalkeeper)
+        VALUES ($1, $2, $3, $4)
+        RETURNING guest_id
+        "#,
+        gameday_id,
+        user.player_id,
+        name,
+        is_goalkeeper
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_event(
+        &mut *tx,
+        user.player_id,
+        "guest.add",
+        &format!("gameday:{}/guest:{}", gameday_id, guest_id),
+        None,
+        Some(serde_json::json!({
+            "name": name,
+            "is_goalkeeper": is_goalkeeper,
+            "host_id": user.player_id,
+        })),
+    )
+    .await?;
+    tx.commit().await?;
+    notify_promoted(gameday_id, promoted);
+
+    info!(
+        "Player: {:?} added guest {:?} to gameday: {:?}",
+        user.player_id, guest_id, gameday_id
+    );
+    Ok(())
+}
+
+/// Removes a guest, allowed for the host and for those managing the roster. The freed
+/// spot goes to the waitlist.
+#[server]
+pub async fn remove_guest(guest_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_player;
+    use crate::components::leave_button::{notify_promoted, promote_from_waitlist};
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use http::StatusCode;
+    use tracing::info;
+
+    let user = require_player().await?;
+    let manages_roster = user.role.has_permission(Permission::ManageRoster);
+
+    let mut tx = get_db().begin().await?;
+
+    // Lock the gameday first, like every other change of its spots
+    let gameday_id = sqlx::query_scalar!(
+        r#"
+        SELECT g.gameday_id
+        FROM gameday g
+        JOIN guest_gameday gg ON g.gameday_id = gg.gameday_id
+        WHERE gg.guest_id = $1 AND (gg.host_id = $2 OR $3)
+        FOR UPDATE OF g
+        "#,
+        guest_id,
+        user.player_id,
+        manages_roster
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let removed = if gameday_id.is_some() {
+        sqlx::query!(
+            r#"
+            DELETE FROM guest_gameday
+            WHERE guest_id = $1
+            RETURNING name, is_goalkeeper, host_id
+            "#,
+            guest_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+    } else {
+        None
+    };
+    let (Some(gameday_id), Some(removed)) = (gameday_id, removed) else {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::ServerError("No guest found.".to_string()));
+    };
+
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+
+    record_event(
+        &mut *tx,
+        user.player_id,
+        "guest.remove",
+        &format!("gameday:{}/guest:{}", gameday_id, guest_id),
+        Some(serde_json::json!({
+            "name": removed.name,
+            "is_goalkeeper": removed.is_goalkeeper,
+            "host_id": removed.host_id,
+        })),
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+    notify_promoted(gameday_id, promoted);
+
+    info!(
+        "Player: {:?} removed guest {:?} from gameday: {:?}",
+        user.player_id, guest_id, gameday_id
+    );
+    Ok(())
+}
@@ -11,7 +11,7 @@ use crate::{
 pub fn Header(player: Resource<Result<Player, ServerFnError>>) -> impl IntoView {
     let state = expect_context::<Store<GlobalState>>();
     let logged_in = state.logged_in();
-    let is_admin = state.is_admin();
+    let role = state.role();
     view! {
         <header>
             <div class="absolute top-4 right-4 flex flex-col justify-center">
@@ -53,7 +53,7 @@ pub fn Header(player: Resource<Result<Player, ServerFnError>>) -> impl IntoView
                 </Show>
             </div>
             <div class="absolute top-4 left-4">
-                <Show when=move || { is_admin.get() }>
+                <Show when=move || { !role.get().permissions().is_empty() }>
                     <A href="/create">
                         <button class="btn btn-xs btn-success">Adminpanel</button>
                     </A>
@@ -1,12 +1,13 @@
 use leptos::{prelude::*, task::spawn_local};
 use reactive_stores::Store;
 
-use crate::models::{Gameday, GlobalState, GlobalStateStoreFields};
+use crate::models::{DropInPayment, Gameday, GlobalState, GlobalStateStoreFields};
 
 #[component]
 pub fn JoinButton(
     gameday_id: i32,
     set_gamedays_joined: WriteSignal<Vec<Gameday>>,
+    set_payment: WriteSignal<Option<DropInPayment>>,
 ) -> impl IntoView {
     let state = expect_context::<Store<GlobalState>>();
     let logged_in = state.logged_in();
@@ -16,8 +17,9 @@ pub fn JoinButton(
             class="btn btn-success h-20 m-2 flex-col"
             on:click=move |_| {
                 spawn_local(async move {
-                    if join_gameday(gameday_id).await.is_ok() {
+                    if let Ok(payment) = join_gameday(gameday_id).await {
                         add_joined(set_gamedays_joined).await;
+                        set_payment.set(payment);
                     }
                 });
             }
@@ -35,90 +37,328 @@ async fn add_joined(set_gamedays_joined: WriteSignal<Vec<Gameday>>) {
     }
 }
 
+/// Joins the gameday, with a confirmed spot the Swish payment request for the drop-in
+/// fee is returned if the player owes one.
 #[server]
-async fn join_gameday(gameday_id: i32) -> Result<(), ServerFnError> {
-    use crate::auth::user_from_session;
-    use crate::database::get_db;
+async fn join_gameday(gameday_id: i32) -> Result<Option<DropInPayment>, ServerFnError> {
+    use crate::auth::require_player;
+    use crate::components::drop_in::drop_in_payment;
+    use crate::components::leave_button::notify_promoted;
+    use http::StatusCode;
     use tracing::{error, info};
 
-    match user_from_session().await {
-        Ok(user) => {
-            let pool = get_db();
-            match sqlx::query!(
-                r#"
-        INSERT INTO player_gameday (player_id, gameday_id)
-        VALUES ($1, $2)
-        "#,
-                user.player_id,
-                gameday_id
-            )
-            .execute(pool)
-            .await
-            {
-                Ok(_) => {
-                    info!("Player: {:?} joined: {:?}", user.player_id, gameday_id);
-                    Ok(())
-                }
-                Err(e) => {
-                    error!("Database error: {:?}", e);
-                    Err(ServerFnError::ServerError(
-                        "Failed to add player to gameday.".to_string(),
-                    ))
+    match require_player().await {
+        Ok(user) => match register_player(user.player_id, user.is_goalkeeper(), gameday_id).await {
+            Ok(SignUp {
+                confirmed: true,
+                promoted,
+            }) => {
+                info!("Player: {:?} joined: {:?}", user.player_id, gameday_id);
+                notify_joined(user.player_id, gameday_id, true);
+                notify_promoted(gameday_id, promoted);
+                // The spot is taken either way, so a failed payment request isn't an error
+                match drop_in_payment(user.player_id, gameday_id).await {
+                    Ok(payment) => Ok(payment),
+                    Err(e) => {
+                        error!("Failed to create payment request: {:?}", e);
+                        Ok(None)
+                    }
                 }
             }
-        }
+            Ok(SignUp {
+                confirmed: false,
+                promoted,
+            }) => {
+                info!(
+                    "Player: {:?} joined waitlist for: {:?}",
+                    user.player_id, gameday_id
+                );
+                notify_joined(user.player_id, gameday_id, false);
+                notify_promoted(gameday_id, promoted);
+                Ok(None)
+            }
+            Err(RegistrationError::AlreadyRegistered) => {
+                let opts = expect_context::<leptos_axum::ResponseOptions>();
+                opts.set_status(StatusCode::CONFLICT);
+                Err(ServerFnError::ServerError(
+                    "Already registered for the gameday.".to_string(),
+                ))
+            }
+            Err(RegistrationError::Started) => {
+                let opts = expect_context::<leptos_axum::ResponseOptions>();
+                opts.set_status(StatusCode::CONFLICT);
+                Err(ServerFnError::ServerError(
+                    "The gameday has already started.".to_string(),
+                ))
+            }
+            Err(e) => {
+                error!("Database error: {:?}", e);
+                Err(ServerFnError::ServerError(
+                    "Failed to add player to gameday.".to_string(),
+                ))
+            }
+        },
         Err(err) => Err(err),
     }
 }
 
+/// Emails the player that joined, about the spot or the place on the waitlist.
+#[cfg(feature = "ssr")]
+pub fn notify_joined(player_id: i32, gameday_id: i32, confirmed_spot: bool) {
+    use crate::mailer::{notify_gameday_players, Notification};
+
+    if confirmed_spot {
+        notify_gameday_players(
+            Notification::Registration,
+            gameday_id,
+            vec![player_id],
+            "Anmäld till gubbhockey".to_string(),
+            "Du är anmäld till speldagen.".to_string(),
+        );
+    } else {
+        notify_gameday_players(
+            Notification::Registration,
+            gameday_id,
+            vec![player_id],
+            "Du står på reservlistan".to_string(),
+            "Speldagen är full så du står på reservlistan. Du får ett mejl om en plats blir ledig."
+                .to_string(),
+        );
+    }
+}
+
+/// Why a player couldn't be registered on a gameday.
+#[cfg(feature = "ssr")]
+#[derive(Debug, thiserror::Error)]
+pub enum RegistrationError {
+    #[error("The gameday has already started.")]
+    Started,
+    #[error("Already registered for the gameday.")]
+    AlreadyRegistered,
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// The outcome of a sign-up.
+#[cfg(feature = "ssr")]
+#[derive(Debug)]
+pub struct SignUp {
+    /// The player got a spot, not a place on the waitlist
+    pub confirmed: bool,
+    /// Players that got open spots from the waitlist before the sign-up
+    pub promoted: Vec<i32>,
+}
+
+/// Registers a player on a gameday, or on its waitlist when the players position is full.
+/// Gamedays that have started are closed, so the attendance statistics only count
+/// sign-ups made in advance.
+#[cfg(feature = "ssr")]
+pub async fn register_player(
+    player_id: i32,
+    is_goalkeeper: bool,
+    gameday_id: i32,
+) -> Result<SignUp, RegistrationError> {
+    use crate::audit::record_event;
+    use crate::components::leave_button::promote_from_waitlist;
+    use crate::database::get_db;
+
+    let mut tx = get_db().begin().await?;
+
+    // Lock the gameday so concurrent sign-ups can't exceed the capacity
+    let capacity = sqlx::query!(
+        r#"
+        SELECT
+            g.max_skaters,
+            g.max_goalkeepers,
+            g.start_date <= NOW() as "started!",
+            EXISTS (
+                SELECT 1 FROM player_gameday pg
+                WHERE pg.gameday_id = g.gameday_id AND pg.player_id = $2
+            ) OR EXISTS (
+                SELECT 1 FROM waitlist_gameday wg
+                WHERE wg.gameday_id = g.gameday_id AND wg.player_id = $2
+            ) as "registered!"
+        FROM gameday g
+        WHERE g.gameday_id = $1
+        FOR UPDATE
+        "#,
+        gameday_id,
+        player_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if capacity.started {
+        return Err(RegistrationError::Started);
+    }
+    if capacity.registered {
+        return Err(RegistrationError::AlreadyRegistered);
+    }
+
+    // Fill any open spots first so nobody gets ahead of the queue
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+
+    let confirmed = confirmed_count(&mut tx, gameday_id, is_goalkeeper).await?;
+
+    let max_players = if is_goalkeeper {
+        capacity.max_goalkeepers
+    } else {
+        capacity.max_skaters
+    };
+
+    let confirmed_spot = confirmed < i64::from(max_players);
+    let inserted = if confirmed_spot {
+        sqlx::query!(
+            r#"
+        INSERT INTO player_gameday (player_id, gameday_id, is_goalkeeper)
+        VALUES ($1, $2, $3)
+        "#,
+            player_id,
+            gameday_id,
+            is_goalkeeper
+        )
+        .execute(&mut *tx)
+        .await?
+    } else {
+        sqlx::query!(
+            r#"
+        INSERT INTO waitlist_gameday (player_id, gameday_id, joined_at)
+        SELECT $1, $2, NOW()
+        WHERE NOT EXISTS (
+            SELECT 1 FROM player_gameday
+            WHERE player_id = $1 AND gameday_id = $2
+        )
+        "#,
+            player_id,
+            gameday_id
+        )
+        .execute(&mut *tx)
+        .await?
+    };
+
+    if inserted.rows_affected() > 0 {
+        record_event(
+            &mut *tx,
+            player_id,
+            "gameday.join",
+            &format!("gameday:{}/player:{}", gameday_id, player_id),
+            None,
+            Some(serde_json::json!({
+                "is_goalkeeper": is_goalkeeper,
+                "waitlist": !confirmed_spot,
+            })),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(SignUp {
+        confirmed: confirmed_spot,
+        promoted,
+    })
+}
+
+/// Players and guests with a confirmed spot in a position on a gameday.
+#[cfg(feature = "ssr")]
+pub async fn confirmed_count(
+    conn: &mut sqlx::PgConnection,
+    gameday_id: i32,
+    is_goalkeeper: bool,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT (
+            SELECT COUNT(*) FROM player_gameday pg
+            WHERE pg.gameday_id = $1 AND pg.is_goalkeeper = $2
+        ) + (
+            SELECT COUNT(*) FROM guest_gameday gg
+            WHERE gg.gameday_id = $1 AND gg.is_goalkeeper = $2
+        ) as "count!"
+        "#,
+        gameday_id,
+        is_goalkeeper
+    )
+    .fetch_one(conn)
+    .await
+}
+
 #[server]
 pub async fn get_gamedays_by_player() -> Result<Vec<Gameday>, ServerFnError> {
-    use crate::auth::user_from_session;
-    use crate::database::get_db;
+    use crate::auth::require_player;
     use tracing::{error, info};
 
-    match user_from_session().await {
-        Ok(user) => {
-            let pool = get_db();
+    match require_player().await {
+        Ok(user) => match gamedays_by_player(user.player_id).await {
+            Ok(gamedays) => {
+                info!(
+                    "Successfully retrieved {} gamedays for player {}",
+                    gamedays.len(),
+                    user.player_id
+                );
+                Ok(gamedays)
+            }
+            Err(e) => {
+                error!("Database error while fetching gamedays for player: {:?}", e);
+                Err(ServerFnError::from(e))
+            }
+        },
+        Err(err) => Err(err),
+    }
+}
 
-            match sqlx::query_as!(
-                Gameday,
-                r#"
+/// All gamedays a player is registered for, newest first.
+#[cfg(feature = "ssr")]
+pub async fn gamedays_by_player(player_id: i32) -> Result<Vec<Gameday>, sqlx::Error> {
+    use crate::config::get_config;
+    use crate::database::get_db;
+
+    sqlx::query_as!(
+        Gameday,
+        r#"
         SELECT
             g.gameday_id,
             g.start_date,
             g.end_date,
-            COUNT(pg.player_id) as player_count
+            (
+                SELECT COUNT(*) FROM player_gameday pg
+                WHERE pg.gameday_id = g.gameday_id
+            ) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id
+            ) as player_count,
+            g.max_skaters,
+            g.max_goalkeepers,
+            w.queue_position as "queue_position?",
+            EXISTS (
+                SELECT 1 FROM time_change_notice n
+                WHERE n.gameday_id = g.gameday_id AND n.player_id = $1
+            ) as "time_changed!",
+            g.start_date < NOW() + make_interval(hours => $2) as "late_cancellation!"
         FROM
             Gameday g
-        LEFT JOIN 
-            player_gameday pg ON g.gameday_id = pg.gameday_id
+        LEFT JOIN (
+            SELECT
+                wg.gameday_id,
+                wg.player_id,
+                ROW_NUMBER() OVER (
+                    PARTITION BY wg.gameday_id, p.is_goalkeeper
+                    ORDER BY wg.joined_at
+                ) as queue_position
+            FROM
+                waitlist_gameday wg
+            JOIN
+                player p ON wg.player_id = p.player_id
+        ) w ON g.gameday_id = w.gameday_id AND w.player_id = $1
         WHERE
-            pg.player_id = $1
-        GROUP BY 
-            g.gameday_id, g.start_date, g.end_date
+            w.player_id IS NOT NULL
+            OR EXISTS (
+                SELECT 1 FROM player_gameday pg
+                WHERE pg.gameday_id = g.gameday_id AND pg.player_id = $1
+            )
         ORDER BY
             g.start_date DESC        
         "#,
-                user.player_id
-            )
-            .fetch_all(pool)
-            .await
-            {
-                Ok(gamedays) => {
-                    info!(
-                        "Successfully retrieved {} gamedays for player {}",
-                        gamedays.len(),
-                        user.player_id
-                    );
-                    Ok(gamedays)
-                }
-                Err(e) => {
-                    error!("Database error while fetching gamedays for player: {:?}", e);
-                    Err(ServerFnError::from(e))
-                }
-            }
-        }
-        Err(err) => Err(err),
-    }
+        player_id,
+        get_config().club.late_cancellation_hours
+    )
+    .fetch_all(get_db())
+    .await
 }
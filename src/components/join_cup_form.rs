@@ -1,6 +1,9 @@
 use leptos::{prelude::*, task::spawn_local};
 
-use crate::{models::Cup, pages::cup_page::get_cups_by_player};
+use crate::{
+    models::{Cup, Position},
+    pages::cup_page::get_cups_by_player,
+};
 
 #[component]
 pub fn JoinCupForm(
@@ -29,9 +32,12 @@ pub fn JoinCupForm(
                     <option disabled selected>
                         "Spelarposition?"
                     </option>
-                    <option value="goalkeeper">Målvakt</option>
-                    <option value="forward">Forward</option>
-                    <option value="defender">Back</option>
+                    {Position::ALL
+                        .into_iter()
+                        .map(|position| {
+                            view! { <option value=position.as_str()>{position.label()}</option> }
+                        })
+                        .collect_view()}
                 </select>
                 <input type="hidden" name="cup_id" value=cup_id />
                 <button class="btn btn-success mt-4" type="submit">
@@ -49,38 +55,57 @@ async fn add_joined(set_cups_joined: WriteSignal<Vec<Cup>>) {
 }
 
 #[server]
-async fn join_cup(position: String, cup_id: i32) -> Result<(), ServerFnError> {
-    use crate::auth::user_from_session;
-    use crate::database::get_db;
+async fn join_cup(position: Position, cup_id: i32) -> Result<(), ServerFnError> {
+    use crate::auth::require_player;
     use tracing::{error, info};
 
-    match user_from_session().await {
-        Ok(user) => {
-            let pool = get_db();
-            match sqlx::query!(
-                r#"
-        INSERT INTO player_cup (player_id, cup_id, position)
-        VALUES ($1, $2, $3)
-        "#,
-                user.player_id,
-                cup_id,
-                position
-            )
-            .execute(pool)
-            .await
-            {
-                Ok(_) => {
-                    info!("Player: {:?} joined cup: {:?}", user.player_id, cup_id);
-                    Ok(())
-                }
-                Err(e) => {
-                    error!("Database error: {:?}", e);
-                    Err(ServerFnError::ServerError(
-                        "Failed to add player to cup.".to_string(),
-                    ))
-                }
+    match require_player().await {
+        Ok(user) => match register_cup_player(user.player_id, cup_id, position).await {
+            Ok(_) => {
+                info!("Player: {:?} joined cup: {:?}", user.player_id, cup_id);
+                Ok(())
             }
-        }
+            Err(e) => {
+                error!("Database error: {:?}", e);
+                Err(ServerFnError::ServerError(
+                    "Failed to add player to cup.".to_string(),
+                ))
+            }
+        },
         Err(err) => Err(err),
     }
 }
+
+/// Registers a player on a cup in a position.
+#[cfg(feature = "ssr")]
+pub async fn register_cup_player(
+    player_id: i32,
+    cup_id: i32,
+    position: Position,
+) -> Result<(), sqlx::Error> {
+    use crate::audit::record_event;
+    use crate::database::get_db;
+
+    let mut tx = get_db().begin().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO player_cup (player_id, cup_id, position)
+        VALUES ($1, $2, $3)
+        "#,
+        player_id,
+        cup_id,
+        position as Position
+    )
+    .execute(&mut *tx)
+    .await?;
+    record_event(
+        &mut *tx,
+        player_id,
+        "cup.join",
+        &format!("cup:{}/player:{}", cup_id, player_id),
+        None,
+        Some(serde_json::json!({ "position": position })),
+    )
+    .await?;
+    tx.commit().await
+}
@@ -1,6 +1,7 @@
 use leptos::{prelude::*, task::spawn_local};
+use reactive_stores::Store;
 
-use crate::models::Gameday;
+use crate::models::{CancellationPolicy, Gameday, GlobalState, GlobalStateStoreFields};
 
 #[component]
 pub fn LeaveButton(
@@ -8,23 +9,69 @@ pub fn LeaveButton(
     gamedays_joined: ReadSignal<Vec<Gameday>>,
     set_gamedays_joined: WriteSignal<Vec<Gameday>>,
 ) -> impl IntoView {
+    let state = expect_context::<Store<GlobalState>>();
+    let policy = state.cancellation_policy();
+
+    // Decided by the server so the render and the hydration agree, leaving the waitlist
+    // is never late
+    let late = move || {
+        gamedays_joined.with(|gamedays| {
+            gamedays.iter().any(|day| {
+                day.gameday_id == gameday_id
+                    && day.queue_position.is_none()
+                    && day.late_cancellation
+            })
+        })
+    };
+    let blocked = move || late() && policy.get().block;
+
     view! {
-        <button
-            class="btn btn-error h-20 m-2 flex-col"
-            on:click=move |_| {
-                spawn_local(async move {
-                    if leave_gameday(gameday_id).await.is_ok() {
-                        delete_joined(set_gamedays_joined, gamedays_joined, gameday_id);
+        <div class="flex flex-col items-center">
+            <button
+                class="btn btn-error h-20 m-2 flex-col"
+                on:click=move |_| {
+                    if late() && !confirm_late_cancellation(policy.get_untracked()) {
+                        return;
                     }
-                });
-            }
-        >
-            <p class="font-bold">Kommer</p>
-            <p class="font-bold">inte</p>
-        </button>
+                    spawn_local(async move {
+                        if leave_gameday(gameday_id).await.is_ok() {
+                            delete_joined(set_gamedays_joined, gamedays_joined, gameday_id);
+                        }
+                    });
+                }
+                disabled=blocked
+            >
+                <p class="font-bold">Kommer</p>
+                <p class="font-bold">inte</p>
+            </button>
+            <Show when=late>
+                <p class="text-xs text-center max-w-28 mb-2">
+                    {move || {
+                        let policy = policy.get();
+                        if policy.block {
+                            format!(
+                                "Avanmälan stängde {} h före start, kontakta en organisatör",
+                                policy.hours,
+                            )
+                        } else {
+                            format!("Mindre än {} h kvar, räknas som sen avanmälan", policy.hours)
+                        }
+                    }}
+                </p>
+            </Show>
+        </div>
     }
 }
 
+fn confirm_late_cancellation(policy: CancellationPolicy) -> bool {
+    window()
+        .confirm_with_message(&format!(
+            "Det är mindre än {} timmar kvar till start och avanmälan räknas som sen. Vill du avanmäla dig?",
+            policy.hours
+        ))
+        .unwrap_or(false)
+}
+
 fn delete_joined(
     set_gamedays_joined: WriteSignal<Vec<Gameday>>,
     gamedays_joined: ReadSignal<Vec<Gameday>>,
@@ -41,39 +88,285 @@ fn delete_joined(
 
 #[server]
 async fn leave_gameday(gameday_id: i32) -> Result<(), ServerFnError> {
-    use crate::auth::user_from_session;
-    use crate::database::get_db;
+    use crate::auth::require_player;
+    use http::StatusCode;
     use tracing::{error, info};
 
-    match user_from_session().await {
-        Ok(user) => {
-            let pool = get_db();
-            match sqlx::query!(
-                r#"
-        DELETE FROM player_gameday
+    let user = require_player().await?;
+
+    match unregister_player(user.player_id, gameday_id).await {
+        Ok(promoted) => {
+            info!(
+                "Player: {:?} left gameday: {:?}",
+                user.player_id, gameday_id
+            );
+            notify_left(user.player_id, gameday_id, promoted);
+            Ok(())
+        }
+        Err(LeaveError::NotRegistered) => {
+            let opts = expect_context::<leptos_axum::ResponseOptions>();
+            opts.set_status(StatusCode::NOT_FOUND);
+            Err(ServerFnError::ServerError(
+                "Not registered for the gameday.".to_string(),
+            ))
+        }
+        Err(LeaveError::Closed) => {
+            info!(
+                "Player: {:?} tried to leave gameday {:?} after the cutoff",
+                user.player_id, gameday_id
+            );
+            let opts = expect_context::<leptos_axum::ResponseOptions>();
+            opts.set_status(StatusCode::CONFLICT);
+            Err(ServerFnError::ServerError(
+                "It is too late to leave the gameday, ask an organizer.".to_string(),
+            ))
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to remove player from gameday.".to_string(),
+            ))
+        }
+    }
+}
+
+/// The late cancellation rules, so the leave button can explain them.
+#[server]
+pub async fn get_cancellation_policy() -> Result<CancellationPolicy, ServerFnError> {
+    use crate::config::{get_config, LateCancellationPolicy};
+
+    let club = &get_config().club;
+    Ok(CancellationPolicy {
+        hours: club.late_cancellation_hours,
+        block: club.late_cancellation_policy == LateCancellationPolicy::Block,
+    })
+}
+
+/// Why a player couldn't leave a gameday.
+#[cfg(feature = "ssr")]
+#[derive(Debug, thiserror::Error)]
+pub enum LeaveError {
+    #[error("Not registered for the gameday.")]
+    NotRegistered,
+    /// The gameday has started, or the late cancellation policy holds a confirmed spot
+    /// within the late cancellation window. Before the start the waitlist can always be left
+    #[error("It is too late to leave the gameday.")]
+    Closed,
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Removes a player and the player's guests from a gameday or its waitlist and fills the
+/// freed spots from the waitlist. Leaving a confirmed spot is recorded as a cancellation.
+/// Returns the ids of the promoted players.
+#[cfg(feature = "ssr")]
+pub async fn unregister_player(player_id: i32, gameday_id: i32) -> Result<Vec<i32>, LeaveError> {
+    use crate::audit::record_event;
+    use crate::config::{get_config, LateCancellationPolicy};
+    use crate::database::get_db;
+
+    let club = &get_config().club;
+    let mut tx = get_db().begin().await?;
+
+    // Lock the gameday so promotions don't race with new sign-ups, and the cutoff is
+    // checked against the registration that is removed
+    let registration = sqlx::query!(
+        r#"
+        SELECT
+            g.start_date <= NOW() as "started!",
+            g.start_date < NOW() + make_interval(hours => $3) as "late!",
+            EXISTS (
+                SELECT 1 FROM player_gameday pg
+                WHERE pg.player_id = $1 AND pg.gameday_id = g.gameday_id
+            ) as "confirmed!",
+            EXISTS (
+                SELECT 1 FROM waitlist_gameday wg
+                WHERE wg.player_id = $1 AND wg.gameday_id = g.gameday_id
+            ) as "waitlisted!"
+        FROM gameday g
+        WHERE g.gameday_id = $2
+        FOR UPDATE
+        "#,
+        player_id,
+        gameday_id,
+        club.late_cancellation_hours
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(registration) =
+        registration.filter(|registration| registration.confirmed || registration.waitlisted)
+    else {
+        return Err(LeaveError::NotRegistered);
+    };
+    // Leaving afterwards would erase the attendance and turn a no-show into a cancellation
+    if registration.started {
+        return Err(LeaveError::Closed);
+    }
+    if registration.confirmed
+        && registration.late
+        && club.late_cancellation_policy == LateCancellationPolicy::Block
+    {
+        return Err(LeaveError::Closed);
+    }
+
+    let guests = sqlx::query_scalar!(
+        r#"
+        DELETE FROM guest_gameday
+        WHERE host_id = $1 AND gameday_id = $2
+        RETURNING name
+        "#,
+        player_id,
+        gameday_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // Giving up a confirmed spot is kept for the attendance statistics
+    let cancellation = sqlx::query!(
+        r#"
+        WITH removed AS (
+            DELETE FROM player_gameday
+            WHERE player_id = $1 AND gameday_id = $2
+            RETURNING player_id, gameday_id, is_goalkeeper
+        )
+        INSERT INTO gameday_cancellation (player_id, gameday_id, late)
+        SELECT r.player_id, r.gameday_id, g.start_date < NOW() + make_interval(hours => $3)
+        FROM removed r
+        JOIN gameday g ON r.gameday_id = g.gameday_id
+        RETURNING late
+        "#,
+        player_id,
+        gameday_id,
+        club.late_cancellation_hours
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let waitlist = sqlx::query!(
+        r#"
+        DELETE FROM waitlist_gameday
         WHERE player_id = $1 AND gameday_id = $2
         "#,
-                user.player_id,
-                gameday_id
-            )
-            .execute(pool)
-            .await
-            {
-                Ok(_) => {
-                    info!(
-                        "Player: {:?} left gameday: {:?}",
-                        user.player_id, gameday_id
-                    );
-                    Ok(())
-                }
-                Err(e) => {
-                    error!("Database error: {:?}", e);
-                    Err(ServerFnError::ServerError(
-                        "Failed to remove player from gameday.".to_string(),
-                    ))
-                }
-            }
-        }
-        Err(err) => Err(err),
+        player_id,
+        gameday_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+
+    if cancellation.is_some() || waitlist.rows_affected() > 0 {
+        record_event(
+            &mut *tx,
+            player_id,
+            "gameday.leave",
+            &format!("gameday:{}/player:{}", gameday_id, player_id),
+            Some(serde_json::json!({
+                "waitlist": cancellation.is_none(),
+                "late": cancellation.map(|cancellation| cancellation.late),
+                "guests": guests,
+            })),
+            None,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(promoted)
+}
+
+/// Emails the player that left and those who got a spot from the waitlist.
+#[cfg(feature = "ssr")]
+pub fn notify_left(player_id: i32, gameday_id: i32, promoted: Vec<i32>) {
+    use crate::mailer::{notify_gameday_players, Notification};
+
+    notify_gameday_players(
+        Notification::Registration,
+        gameday_id,
+        vec![player_id],
+        "Avanmäld från gubbhockey".to_string(),
+        "Du är avanmäld från speldagen.".to_string(),
+    );
+    notify_promoted(gameday_id, promoted);
+}
+
+/// Logs and emails the players that got a spot from the waitlist.
+#[cfg(feature = "ssr")]
+pub fn notify_promoted(gameday_id: i32, promoted: Vec<i32>) {
+    use crate::mailer::{notify_gameday_players, Notification};
+    use tracing::info;
+
+    if promoted.is_empty() {
+        return;
     }
+
+    info!(
+        "Promoted from waitlist on gameday {:?}: {:?}",
+        gameday_id, promoted
+    );
+    notify_gameday_players(
+        Notification::Waitlist,
+        gameday_id,
+        promoted,
+        "Du har fått en plats".to_string(),
+        "En plats har blivit ledig och du är nu anmäld till speldagen.".to_string(),
+    );
+}
+
+/// Moves players from the waitlist to the gameday, in sign-up order, until every
+/// position is full. Guests hold their spots like players. The caller is expected to
+/// hold a lock on the gameday row.
+#[cfg(feature = "ssr")]
+pub async fn promote_from_waitlist(
+    conn: &mut sqlx::PgConnection,
+    gameday_id: i32,
+) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        WITH capacity AS (
+            SELECT max_skaters, max_goalkeepers
+            FROM gameday
+            WHERE gameday_id = $1
+        ),
+        confirmed AS (
+            SELECT spots.is_goalkeeper, COUNT(*) as count
+            FROM (
+                SELECT pg.is_goalkeeper FROM player_gameday pg WHERE pg.gameday_id = $1
+                UNION ALL
+                SELECT gg.is_goalkeeper FROM guest_gameday gg WHERE gg.gameday_id = $1
+            ) spots
+            GROUP BY spots.is_goalkeeper
+        ),
+        queue AS (
+            SELECT
+                wg.player_id,
+                p.is_goalkeeper,
+                ROW_NUMBER() OVER (PARTITION BY p.is_goalkeeper ORDER BY wg.joined_at) as position
+            FROM waitlist_gameday wg
+            JOIN player p ON wg.player_id = p.player_id
+            WHERE wg.gameday_id = $1
+        ),
+        promoted AS (
+            DELETE FROM waitlist_gameday wg
+            USING queue q, capacity c
+            WHERE wg.gameday_id = $1
+              AND wg.player_id = q.player_id
+              AND q.position <= (
+                  CASE WHEN q.is_goalkeeper THEN c.max_goalkeepers ELSE c.max_skaters END
+              ) - COALESCE(
+                  (SELECT count FROM confirmed WHERE confirmed.is_goalkeeper = q.is_goalkeeper),
+                  0
+              )
+            RETURNING wg.player_id
+        )
+        INSERT INTO player_gameday (player_id, gameday_id, is_goalkeeper)
+        SELECT pr.player_id, $1, q.is_goalkeeper
+        FROM promoted pr
+        JOIN queue q ON pr.player_id = q.player_id
+        RETURNING player_id
+        "#,
+        gameday_id
+    )
+    .fetch_all(conn)
+    .await
 }
@@ -45,36 +45,56 @@ fn delete_joined(
 
 #[server]
 async fn leave_cup(cup_id: i32) -> Result<(), ServerFnError> {
-    use crate::auth::user_from_session;
-    use crate::database::get_db;
+    use crate::auth::require_player;
     use tracing::{error, info};
 
-    match user_from_session().await {
-        Ok(user) => {
-            let pool = get_db();
-            match sqlx::query!(
-                r#"
+    match require_player().await {
+        Ok(user) => match unregister_cup_player(user.player_id, cup_id).await {
+            Ok(_) => {
+                info!("Player: {:?} left cup: {:?}", user.player_id, cup_id);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Database error: {:?}", e);
+                Err(ServerFnError::ServerError(
+                    "Failed to remove player from cup.".to_string(),
+                ))
+            }
+        },
+        Err(err) => Err(err),
+    }
+}
+
+/// Removes a player from a cup. Returns whether the player was registered.
+#[cfg(feature = "ssr")]
+pub async fn unregister_cup_player(player_id: i32, cup_id: i32) -> Result<bool, sqlx::Error> {
+    use crate::audit::record_event;
+    use crate::database::get_db;
+    use crate::models::Position;
+
+    let mut tx = get_db().begin().await?;
+    let removed = sqlx::query_scalar!(
+        r#"
         DELETE FROM player_cup
         WHERE player_id = $1 AND cup_id = $2
+        RETURNING position as "position: Position"
         "#,
-                user.player_id,
-                cup_id
-            )
-            .execute(pool)
-            .await
-            {
-                Ok(_) => {
-                    info!("Player: {:?} left cup: {:?}", user.player_id, cup_id);
-                    Ok(())
-                }
-                Err(e) => {
-                    error!("Database error: {:?}", e);
-                    Err(ServerFnError::ServerError(
-                        "Failed to remove player from cup.".to_string(),
-                    ))
-                }
-            }
-        }
-        Err(err) => Err(err),
+        player_id,
+        cup_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(position) = removed {
+        record_event(
+            &mut *tx,
+            player_id,
+            "cup.leave",
+            &format!("cup:{}/player:{}", cup_id, player_id),
+            Some(serde_json::json!({ "position": position })),
+            None,
+        )
+        .await?;
     }
+    tx.commit().await?;
+    Ok(removed.is_some())
 }
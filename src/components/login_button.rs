@@ -4,16 +4,24 @@ use crate::auth::get_auth_url;
 
 #[component]
 pub fn LoginButton() -> impl IntoView {
+    let remember = RwSignal::new(false);
     view! {
-        <button
-            on:click=move |_| {
-                spawn_local(async {
-                    let _ = get_auth_url().await;
-                });
-            }
-            class="btn btn-sm btn-info"
-        >
-            "Logga in"
-        </button>
+        <div class="flex flex-col items-end">
+            <button
+                on:click=move |_| {
+                    let remember = remember.get();
+                    spawn_local(async move {
+                        let _ = get_auth_url(remember).await;
+                    });
+                }
+                class="btn btn-sm btn-info"
+            >
+                "Logga in"
+            </button>
+            <label class="label cursor-pointer gap-1">
+                <span class="label-text text-xs">"Kom ihåg mig i 30 dagar"</span>
+                <input type="checkbox" class="checkbox checkbox-xs" bind:checked=remember />
+            </label>
+        </div>
     }
 }
@@ -1,11 +1,14 @@
 pub mod cup_card;
 pub mod cup_form;
+pub mod cup_lineup;
 pub mod date_card;
 pub mod date_picker;
+pub mod drop_in;
 pub mod event_tab;
 pub mod footer;
 pub mod gameday_card;
 pub mod gameday_create;
+pub mod guests;
 pub mod header;
 pub mod join_button;
 pub mod join_cup_form;
@@ -16,5 +19,8 @@ pub mod login_button;
 pub mod logout_button;
 pub mod not_found;
 pub mod num_players;
+pub mod roster;
+pub mod series_card;
+pub mod teams;
 pub mod theme;
 pub mod time_card;
@@ -1,6 +1,10 @@
 use leptos::prelude::*;
 
 #[component]
-pub fn NumPlayers(num_players: i64) -> impl IntoView {
+pub fn NumPlayers(num_players: i64, #[prop(optional)] max_players: Option<i32>) -> impl IntoView {
+    let num_players = match max_players {
+        Some(max_players) => format!("{}/{}", num_players, max_players),
+        None => num_players.to_string(),
+    };
     view! { <p class="text-center m-2">{num_players}" Spelare kommer"</p> }
 }
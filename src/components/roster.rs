@@ -0,0 +1,676 @@
+use leptos::prelude::*;
+
+use crate::models::{Attendance, GamedayPlayer, Player};
+
+/// The actions organizers use to change the roster of a gameday, the lists are
+/// refetched when any of them has run.
+#[derive(Clone, Copy)]
+pub struct RosterActions {
+    pub add: ServerAction<AddToRoster>,
+    pub remove: ServerAction<RemoveFromRoster>,
+    pub change_position: ServerAction<ChangeRosterPosition>,
+    pub set_attendance: ServerAction<SetAttendance>,
+}
+
+impl RosterActions {
+    pub fn new() -> Self {
+        RosterActions {
+            add: ServerAction::new(),
+            remove: ServerAction::new(),
+            change_position: ServerAction::new(),
+            set_attendance: ServerAction::new(),
+        }
+    }
+
+    pub fn version(&self) -> usize {
+        self.add.version().get()
+            + self.remove.version().get()
+            + self.change_position.version().get()
+            + self.set_attendance.version().get()
+    }
+}
+
+impl Default for RosterActions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Organizer controls next to a registered player.
+#[component]
+pub fn RosterControls(
+    gameday_id: i32,
+    player: GamedayPlayer,
+    actions: RosterActions,
+) -> impl IntoView {
+    view! {
+        <div class="flex flex-wrap items-center gap-1">
+            <AttendanceButtons
+                gameday_id
+                player_id=player.player_id
+                attendance=player.attendance
+                action=actions.set_attendance
+            />
+            <ActionForm action=actions.change_position>
+                <input type="hidden" name="gameday_id" value=gameday_id />
+                <input type="hidden" name="player_id" value=player.player_id />
+                <input
+                    type="hidden"
+                    name="is_goalkeeper"
+                    value=(!player.is_goalkeeper).to_string()
+                />
+                <button type="submit" class="btn btn-xs btn-warning">
+                    {if player.is_goalkeeper { "Till utespelare" } else { "Till målvakt" }}
+                </button>
+            </ActionForm>
+            <ActionForm action=actions.remove>
+                <input type="hidden" name="gameday_id" value=gameday_id />
+                <input type="hidden" name="player_id" value=player.player_id />
+                <button type="submit" class="btn btn-xs btn-error">
+                    "Ta bort"
+                </button>
+            </ActionForm>
+        </div>
+    }
+}
+
+/// Marks a player as attended or no-show, pressing the marked status again clears it.
+#[component]
+pub fn AttendanceButtons(
+    gameday_id: i32,
+    player_id: i32,
+    attendance: Option<Attendance>,
+    action: ServerAction<SetAttendance>,
+    #[prop(optional)] large: bool,
+) -> impl IntoView {
+    let button = move |status: Attendance| {
+        let marked = attendance == Some(status);
+        view! {
+            <ActionForm action>
+                <input type="hidden" name="gameday_id" value=gameday_id />
+                <input type="hidden" name="player_id" value=player_id />
+                <Show when=move || !marked>
+                    <input type="hidden" name="attendance" value=status.as_str() />
+                </Show>
+                <button type="submit" class=attendance_class(status, marked, large)>
+                    {status.label()}
+                </button>
+            </ActionForm>
+        }
+    };
+
+    view! {
+        {button(Attendance::Attended)}
+        {button(Attendance::NoShow)}
+    }
+}
+
+/// Same as [`AttendanceButtons`] for a guest.
+#[component]
+pub fn GuestAttendanceButtons(
+    guest_id: i32,
+    attendance: Option<Attendance>,
+    action: ServerAction<SetGuestAttendance>,
+    #[prop(optional)] large: bool,
+) -> impl IntoView {
+    let button = move |status: Attendance| {
+        let marked = attendance == Some(status);
+        view! {
+            <ActionForm action>
+                <input type="hidden" name="guest_id" value=guest_id />
+                <Show when=move || !marked>
+                    <input type="hidden" name="attendance" value=status.as_str() />
+                </Show>
+                <button type="submit" class=attendance_class(status, marked, large)>
+                    {status.label()}
+                </button>
+            </ActionForm>
+        }
+    };
+
+    view! {
+        {button(Attendance::Attended)}
+        {button(Attendance::NoShow)}
+    }
+}
+
+fn attendance_class(status: Attendance, marked: bool, large: bool) -> String {
+    let color = match (status, marked) {
+        (Attendance::Attended, true) => "btn-success",
+        (Attendance::NoShow, true) => "btn-error",
+        (_, false) => "btn-outline",
+    };
+    let size = if large { "btn-md" } else { "btn-xs" };
+    format!("btn {} {}", size, color)
+}
+
+/// Search for players that aren't registered and add them to the gameday.
+#[component]
+pub fn AddToRosterSearch(gameday_id: i32, actions: RosterActions) -> impl IntoView {
+    let query = RwSignal::new(String::new());
+    let results = Resource::new(
+        move || (query.get(), actions.version()),
+        move |(query, _)| async move {
+            if query.trim().chars().count() < 2 {
+                return Ok(Vec::new());
+            }
+            search_players(gameday_id, query).await
+        },
+    );
+
+    view! {
+        <div class="flex flex-col items-center w-11/12 max-w-md mt-4">
+            <input
+                type="search"
+                placeholder="Lägg till spelare"
+                class="input input-bordered input-sm w-full"
+                bind:value=query
+            />
+            <Transition>
+                <ul class="flex flex-col w-full">
+                    {move || Suspend::new(async move {
+                        results
+                            .await
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|player| {
+                                view! {
+                                    <li class="flex items-center justify-between border-b py-1">
+                                        <p>{player.name}" ("{player.email}")"</p>
+                                        <ActionForm action=actions.add>
+                                            <input type="hidden" name="gameday_id" value=gameday_id />
+                                            <input
+                                                type="hidden"
+                                                name="player_id"
+                                                value=player.player_id
+                                            />
+                                            <button type="submit" class="btn btn-xs btn-success">
+                                                "Lägg till"
+                                            </button>
+                                        </ActionForm>
+                                    </li>
+                                }
+                            })
+                            .collect_view()
+                    })}
+                </ul>
+            </Transition>
+        </div>
+    }
+}
+
+/// A row of `player_gameday` as stored in the audit log.
+#[cfg(feature = "ssr")]
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+struct Registration {
+    player_id: i32,
+    gameday_id: i32,
+    is_goalkeeper: bool,
+    attendance: Option<Attendance>,
+}
+
+#[cfg(feature = "ssr")]
+fn audit_target(gameday_id: i32, player_id: i32) -> String {
+    format!("gameday:{}/player:{}", gameday_id, player_id)
+}
+
+/// Locks the gameday and the registration of the player, if there is one.
+#[cfg(feature = "ssr")]
+async fn lock_registration(
+    conn: &mut sqlx::PgConnection,
+    gameday_id: i32,
+    player_id: i32,
+) -> Result<Option<Registration>, ServerFnError> {
+    let gameday = sqlx::query_scalar!(
+        r#"
+        SELECT gameday_id
+        FROM gameday
+        WHERE gameday_id = $1
+        FOR UPDATE
+        "#,
+        gameday_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    if gameday.is_none() {
+        return Err(ServerFnError::ServerError("No gameday found.".to_string()));
+    }
+
+    Ok(sqlx::query_as!(
+        Registration,
+        r#"
+        SELECT player_id, gameday_id, is_goalkeeper, attendance as "attendance: Attendance"
+        FROM player_gameday
+        WHERE gameday_id = $1 AND player_id = $2
+        "#,
+        gameday_id,
+        player_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?)
+}
+
+#[server]
+async fn search_players(gameday_id: i32, query: String) -> Result<Vec<Player>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::error;
+
+    require_permission(Permission::ManageRoster).await?;
+
+    let pattern = format!(
+        "%{}%",
+        query
+            .trim()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    let pool = get_db();
+    match sqlx::query_as!(
+        Player,
+        r#"
+        SELECT
+            p.player_id,
+            p.name,
+            p.given_name,
+            p.family_name,
+            p.email,
+            p.role as "role: _",
+            p.position as "position: _"
+        FROM player p
+        WHERE (p.name ILIKE $2 OR p.email ILIKE $2)
+          AND NOT EXISTS (
+              SELECT 1 FROM player_gameday pg
+              WHERE pg.gameday_id = $1 AND pg.player_id = p.player_id
+          )
+        ORDER BY p.name
+        LIMIT 10
+        "#,
+        gameday_id,
+        pattern
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(players) => Ok(players),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to search players.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Registers a player with a confirmed spot, also when the gameday is full.
+/// A player on the waitlist is taken off it.
+#[server]
+pub async fn add_to_roster(gameday_id: i32, player_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::components::leave_button::{notify_promoted, promote_from_waitlist};
+    use crate::database::get_db;
+    use crate::mailer::{notify_gameday_players, Notification};
+    use crate::models::Permission;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    if lock_registration(&mut tx, gameday_id, player_id)
+        .await?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        DELETE FROM waitlist_gameday
+        WHERE player_id = $1 AND gameday_id = $2
+        "#,
+        player_id,
+        gameday_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Open spots go to the waitlist first, the organizer adds the player on top of them
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+
+    let Some(added) = sqlx::query_as!(
+        Registration,
+        r#"
+        INSERT INTO player_gameday (player_id, gameday_id, is_goalkeeper)
+        SELECT player_id, $2, is_goalkeeper
+        FROM player
+        WHERE player_id = $1
+        RETURNING player_id, gameday_id, is_goalkeeper, attendance as "attendance: Attendance"
+        "#,
+        player_id,
+        gameday_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Err(ServerFnError::ServerError("No player found.".to_string()));
+    };
+
+    record_event(
+        &mut *tx,
+        organizer.player_id,
+        "roster.add",
+        &audit_target(gameday_id, player_id),
+        None,
+        snapshot(&added),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} added player {:?} to gameday {:?}",
+        organizer.player_id, player_id, gameday_id
+    );
+    notify_gameday_players(
+        Notification::Registration,
+        gameday_id,
+        vec![player_id],
+        "Anmäld till gubbhockey".to_string(),
+        format!("{} har anmält dig till speldagen.", organizer.name),
+    );
+    notify_promoted(gameday_id, promoted);
+    Ok(())
+}
+
+/// Removes a player from the gameday, the spot goes to the waitlist.
+#[server]
+pub async fn remove_from_roster(gameday_id: i32, player_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::components::leave_button::{notify_promoted, promote_from_waitlist};
+    use crate::database::get_db;
+    use crate::mailer::{notify_gameday_players, Notification};
+    use crate::models::Permission;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    let Some(removed) = lock_registration(&mut tx, gameday_id, player_id).await? else {
+        return Ok(());
+    };
+
+    sqlx::query!(
+        r#"
+        DELETE FROM player_gameday
+        WHERE player_id = $1 AND gameday_id = $2
+        "#,
+        player_id,
+        gameday_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+
+    record_event(
+        &mut *tx,
+        organizer.player_id,
+        "roster.remove",
+        &audit_target(gameday_id, player_id),
+        snapshot(&removed),
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} removed player {:?} from gameday {:?}",
+        organizer.player_id, player_id, gameday_id
+    );
+    notify_gameday_players(
+        Notification::Registration,
+        gameday_id,
+        vec![player_id],
+        "Avanmäld från gubbhockey".to_string(),
+        format!("{} har avanmält dig från speldagen.", organizer.name),
+    );
+    notify_promoted(gameday_id, promoted);
+    Ok(())
+}
+
+/// Lets a player play goalkeeper or skater on this gameday only, regardless of the
+/// capacity. A spot freed in the other position goes to the waitlist.
+#[server]
+pub async fn change_roster_position(
+    gameday_id: i32,
+    player_id: i32,
+    is_goalkeeper: bool,
+) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::components::leave_button::{notify_promoted, promote_from_waitlist};
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    let Some(before) = lock_registration(&mut tx, gameday_id, player_id).await? else {
+        return Err(ServerFnError::ServerError(
+            "The player isn't registered.".to_string(),
+        ));
+    };
+
+    let after = sqlx::query_as!(
+        Registration,
+        r#"
+        UPDATE player_gameday
+        SET is_goalkeeper = $3
+        WHERE player_id = $1 AND gameday_id = $2
+        RETURNING player_id, gameday_id, is_goalkeeper, attendance as "attendance: Attendance"
+        "#,
+        player_id,
+        gameday_id,
+        is_goalkeeper
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+
+    record_event(
+        &mut *tx,
+        organizer.player_id,
+        "roster.change_position",
+        &audit_target(gameday_id, player_id),
+        snapshot(&before),
+        snapshot(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} set goalkeeper {:?} for player {:?} on gameday {:?}",
+        organizer.player_id, is_goalkeeper, player_id, gameday_id
+    );
+    notify_promoted(gameday_id, promoted);
+    Ok(())
+}
+
+/// Marks whether a registered player showed up, `None` clears the mark.
+#[server]
+pub async fn set_attendance(
+    gameday_id: i32,
+    player_id: i32,
+    attendance: Option<Attendance>,
+) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    let Some(before) = lock_registration(&mut tx, gameday_id, player_id).await? else {
+        return Err(ServerFnError::ServerError(
+            "The player isn't registered.".to_string(),
+        ));
+    };
+
+    let after = sqlx::query_as!(
+        Registration,
+        r#"
+        UPDATE player_gameday
+        SET attendance = $3
+        WHERE player_id = $1 AND gameday_id = $2
+        RETURNING player_id, gameday_id, is_goalkeeper, attendance as "attendance: Attendance"
+        "#,
+        player_id,
+        gameday_id,
+        attendance as Option<Attendance>
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_event(
+        &mut *tx,
+        organizer.player_id,
+        "roster.set_attendance",
+        &audit_target(gameday_id, player_id),
+        snapshot(&before),
+        snapshot(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} marked player {:?} on gameday {:?} as {:?}",
+        organizer.player_id, player_id, gameday_id, attendance
+    );
+    Ok(())
+}
+
+/// Marks whether a guest showed up, `None` clears the mark.
+#[server]
+pub async fn set_guest_attendance(
+    guest_id: i32,
+    attendance: Option<Attendance>,
+) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    let Some(before) = sqlx::query!(
+        r#"
+        SELECT gameday_id, attendance as "attendance: Attendance"
+        FROM guest_gameday
+        WHERE guest_id = $1
+        FOR UPDATE
+        "#,
+        guest_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Err(ServerFnError::ServerError("No guest found.".to_string()));
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE guest_gameday
+        SET attendance = $2
+        WHERE guest_id = $1
+        "#,
+        guest_id,
+        attendance as Option<Attendance>
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_event(
+        &mut *tx,
+        organizer.player_id,
+        "roster.set_guest_attendance",
+        &format!("gameday:{}/guest:{}", before.gameday_id, guest_id),
+        Some(serde_json::json!({ "attendance": before.attendance })),
+        Some(serde_json::json!({ "attendance": attendance })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} marked guest {:?} on gameday {:?} as {:?}",
+        organizer.player_id, guest_id, before.gameday_id, attendance
+    );
+    Ok(())
+}
+
+/// Checks in everyone on the gameday, guests included, that hasn't been marked yet as
+/// attended.
+#[server]
+pub async fn mark_remaining_attended(gameday_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    let player_ids = sqlx::query_scalar!(
+        r#"
+        UPDATE player_gameday
+        SET attendance = 'attended'
+        WHERE gameday_id = $1 AND attendance IS NULL
+        RETURNING player_id
+        "#,
+        gameday_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let guest_ids = sqlx::query_scalar!(
+        r#"
+        UPDATE guest_gameday
+        SET attendance = 'attended'
+        WHERE gameday_id = $1 AND attendance IS NULL
+        RETURNING guest_id
+        "#,
+        gameday_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if !player_ids.is_empty() || !guest_ids.is_empty() {
+        record_event(
+            &mut *tx,
+            organizer.player_id,
+            "roster.mark_remaining_attended",
+            &format!("gameday:{}", gameday_id),
+            None,
+            Some(serde_json::json!({ "attended": player_ids, "guests": guest_ids })),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} checked in {} players and {} guests on gameday {:?}",
+        organizer.player_id,
+        player_ids.len(),
+        guest_ids.len(),
+        gameday_id
+    );
+    Ok(())
+}
@@ -0,0 +1,460 @@
+use leptos::{prelude::*, task::spawn_local};
+use serde::{Deserialize, Serialize};
+
+use crate::models::GamedaySeries;
+
+#[component]
+pub fn SeriesCard(
+    series: GamedaySeries,
+    set_invalidate_gamedays: WriteSignal<bool>,
+) -> impl IntoView {
+    let submit = ServerAction::<ShiftSeries>::new();
+    let show_shift = RwSignal::new(false);
+    Effect::new(move || {
+        if submit.value().get().is_some_and(|result| result.is_ok()) {
+            show_shift.set(false);
+            set_invalidate_gamedays.set(true);
+        }
+    });
+
+    let error = move || {
+        submit
+            .value()
+            .get()
+            .and_then(|result| result.err())
+            .map(|_| "Kunde inte flytta serien, ingen speldag får hamna före nu.")
+    };
+
+    let series_id = series.series_id;
+    let weekday = series.weekday;
+    let start = StoredValue::new(series.start_time.format("%H:%M").to_string());
+    let end = StoredValue::new(series.end_time.format("%H:%M").to_string());
+
+    view! {
+        <div class="card items-center bg-base-100 shadow-xl border p-2">
+            <p class="text-center font-bold">
+                {format!(
+                    "{} {} - {}",
+                    weekday_name(weekday),
+                    start.get_value(),
+                    end.get_value(),
+                )}
+            </p>
+            <p class="text-center text-sm">
+                {format!("{} till {}", series.season_start, series.season_end)}
+            </p>
+            <p class="text-center text-sm">
+                {series.upcoming_count.unwrap_or(0)}" kommande speldagar"
+            </p>
+            <Show when=move || { show_shift.get() }>
+                <ActionForm action=submit>
+                    <div class="flex flex-col m-2">
+                        <label for="input[weekday]">Veckodag</label>
+                        <select class="select select-bordered" name="input[weekday]">
+                            {(1..=7)
+                                .map(|day| {
+                                    view! {
+                                        <option value=day selected=day == weekday>
+                                            {weekday_name(day)}
+                                        </option>
+                                    }
+                                })
+                                .collect_view()}
+                        </select>
+                        <div class="flex gap-2 mt-2">
+                            <div class="flex flex-col">
+                                <label for="input[start]">Start</label>
+                                <input
+                                    type="time"
+                                    required
+                                    value=start.get_value()
+                                    name="input[start]"
+                                    class="input input-bordered"
+                                />
+                            </div>
+                            <div class="flex flex-col">
+                                <label for="input[end]">Slut</label>
+                                <input
+                                    type="time"
+                                    required
+                                    value=end.get_value()
+                                    name="input[end]"
+                                    class="input input-bordered"
+                                />
+                            </div>
+                        </div>
+                        <input type="hidden" name="input[series_id]" value=series_id />
+                        <button class="btn btn-success mt-4" type="submit">
+                            "Flytta kommande speldagar"
+                        </button>
+                        <p class="text-error">{error}</p>
+                    </div>
+                </ActionForm>
+            </Show>
+            <div class="flex">
+                <button
+                    class="btn btn-warning m-2"
+                    on:click=move |_| show_shift.set(!show_shift.get())
+                >
+                    Flytta
+                </button>
+                <button
+                    class="btn btn-error m-2"
+                    on:click=move |_| {
+                        spawn_local(async move {
+                            if delete_series(series_id).await.is_ok() {
+                                set_invalidate_gamedays.set(true);
+                            }
+                        });
+                    }
+                >
+                    "Ta bort serie"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+fn weekday_name(weekday: i16) -> &'static str {
+    match weekday {
+        1 => "Måndag",
+        2 => "Tisdag",
+        3 => "Onsdag",
+        4 => "Torsdag",
+        5 => "Fredag",
+        6 => "Lördag",
+        7 => "Söndag",
+        _ => "",
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct InputSeriesShift {
+    series_id: i32,
+    weekday: i16,
+    start: String,
+    end: String,
+}
+
+#[server]
+async fn shift_series(input: InputSeriesShift) -> Result<(), ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::models::Permission;
+    use chrono::NaiveTime;
+    use http::StatusCode;
+    use tracing::{error, info};
+
+    let organizer = require_permission(Permission::EditGameday).await?;
+
+    info!("Shifting series with values: {:?}", input);
+    if !(1..=7).contains(&input.weekday) {
+        return Err(ServerFnError::ServerError("Invalid weekday.".to_string()));
+    }
+    let start_time = NaiveTime::parse_from_str(&input.start, "%H:%M")?;
+    let end_time = NaiveTime::parse_from_str(&input.end, "%H:%M")?;
+
+    match shift_remaining_gamedays(
+        organizer.player_id,
+        input.series_id,
+        input.weekday,
+        start_time,
+        end_time,
+    )
+    .await
+    {
+        Ok(count) => {
+            info!(
+                "Series {:?} shifted, {} gamedays moved",
+                input.series_id, count
+            );
+            Ok(())
+        }
+        Err(ShiftError::Database(e)) => {
+            error!("Failed to shift series: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to shift gameday series.".to_string(),
+            ))
+        }
+        Err(e) => {
+            info!("Series {:?} not shifted: {}", input.series_id, e);
+            let opts = expect_context::<leptos_axum::ResponseOptions>();
+            opts.set_status(StatusCode::BAD_REQUEST);
+            Err(ServerFnError::ServerError(e.to_string()))
+        }
+    }
+}
+
+/// Why a series couldn't be shifted.
+#[cfg(feature = "ssr")]
+#[derive(Debug, thiserror::Error)]
+enum ShiftError {
+    #[error("{0} is not a valid local time.")]
+    InvalidTime(chrono::NaiveDate),
+    /// Upcoming gamedays can't be moved to a time that has already passed
+    #[error("The gameday on {0} would be moved to the past.")]
+    Past(chrono::NaiveDate),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Moves every upcoming gameday in a series to a new weekday and time, keeping the
+/// registrations and flagging the registered players about the new time. Nothing is moved
+/// if any gameday would end up before now. Returns the number of gamedays moved.
+#[cfg(feature = "ssr")]
+async fn shift_remaining_gamedays(
+    actor_id: i32,
+    series_id: i32,
+    weekday: i16,
+    start_time: chrono::NaiveTime,
+    end_time: chrono::NaiveTime,
+) -> Result<usize, ShiftError> {
+    use crate::audit::record_event;
+    use crate::database::get_db;
+    use crate::pages::season_page::assign_seasons;
+    use crate::timezone::{from_club_time, to_club_time};
+    use chrono::{Duration, Utc};
+
+    let mut tx = get_db().begin().await?;
+
+    let previous = sqlx::query!(
+        r#"
+        SELECT weekday, start_time, end_time
+        FROM gameday_series
+        WHERE series_id = $1
+        FOR UPDATE
+        "#,
+        series_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let offset = Duration::days(i64::from(weekday - previous.weekday));
+
+    let gamedays = sqlx::query!(
+        r#"
+        SELECT gameday_id, start_date, end_date
+        FROM gameday
+        WHERE series_id = $1 AND start_date >= NOW()
+        FOR UPDATE
+        "#,
+        series_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut moved = Vec::with_capacity(gamedays.len());
+    for gameday in &gamedays {
+        let date = to_club_time(gameday.start_date).date_naive() + offset;
+        let (Some(start_date), Some(end_date)) = (
+            from_club_time(date, start_time),
+            from_club_time(date, end_time),
+        ) else {
+            return Err(ShiftError::InvalidTime(date));
+        };
+        if start_date < Utc::now() {
+            return Err(ShiftError::Past(date));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE gameday
+            SET start_date = $1, end_date = $2, reminder_sent = reminder_sent AND start_date = $1
+            WHERE gameday_id = $3
+            "#,
+            start_date,
+            end_date,
+            gameday.gameday_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        moved.push(serde_json::json!({
+            "gameday_id": gameday.gameday_id,
+            "start_date": start_date,
+            "end_date": end_date,
+        }));
+
+        sqlx::query!(
+            r#"
+            INSERT INTO time_change_notice (player_id, gameday_id)
+            SELECT player_id, gameday_id FROM player_gameday WHERE gameday_id = $1
+            UNION
+            SELECT player_id, gameday_id FROM waitlist_gameday WHERE gameday_id = $1
+            ON CONFLICT (player_id, gameday_id) DO UPDATE SET created_at = NOW()
+            "#,
+            gameday.gameday_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE gameday_series
+        SET weekday = $1,
+            start_time = $2,
+            end_time = $3,
+            exceptions = ARRAY(SELECT e + $4::int FROM UNNEST(exceptions) AS e)
+        WHERE series_id = $5
+        "#,
+        weekday,
+        start_time,
+        end_time,
+        i32::from(weekday - previous.weekday),
+        series_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let gameday_ids = gamedays
+        .iter()
+        .map(|gameday| gameday.gameday_id)
+        .collect::<Vec<_>>();
+    assign_seasons(&mut tx, &gameday_ids).await?;
+
+    record_event(
+        &mut *tx,
+        actor_id,
+        "series.shift",
+        &format!("series:{}", series_id),
+        Some(serde_json::json!({
+            "weekday": previous.weekday,
+            "start_time": previous.start_time,
+            "end_time": previous.end_time,
+            "gamedays": gamedays
+                .iter()
+                .map(|gameday| serde_json::json!({
+                    "gameday_id": gameday.gameday_id,
+                    "start_date": gameday.start_date,
+                    "end_date": gameday.end_date,
+                }))
+                .collect::<Vec<_>>(),
+        })),
+        Some(serde_json::json!({
+            "weekday": weekday,
+            "start_time": start_time,
+            "end_time": end_time,
+            "gamedays": moved,
+        })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(gamedays.len())
+}
+
+#[server]
+async fn delete_series(series_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::mailer::{gameday_time, notify_players, Notification};
+    use crate::models::Permission;
+    use tracing::{error, info};
+
+    let organizer = require_permission(Permission::EditGameday).await?;
+
+    let deleted = async {
+        let mut tx = get_db().begin().await?;
+
+        let series = sqlx::query_as!(
+            GamedaySeries,
+            r#"
+            SELECT
+                series_id, weekday, start_time, end_time, season_start, season_end, exceptions,
+                max_skaters, max_goalkeepers, NULL::bigint as upcoming_count
+            FROM gameday_series
+            WHERE series_id = $1
+            FOR UPDATE
+            "#,
+            series_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // The registrations are read before the cascade removes them
+        let gamedays = sqlx::query!(
+            r#"
+            SELECT
+                g.gameday_id,
+                g.start_date,
+                g.end_date,
+                ARRAY(
+                    SELECT player_id FROM player_gameday pg WHERE pg.gameday_id = g.gameday_id
+                ) as "registered!",
+                ARRAY(
+                    SELECT player_id FROM waitlist_gameday wg WHERE wg.gameday_id = g.gameday_id
+                ) as "waitlist!"
+            FROM gameday g
+            WHERE g.series_id = $1 AND g.start_date >= NOW()
+            FOR UPDATE
+            "#,
+            series_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // Past gamedays are kept, the foreign key sets their series to NULL
+        sqlx::query!(
+            r#"
+            WITH upcoming AS (
+                DELETE FROM gameday
+                WHERE series_id = $1 AND start_date >= NOW()
+            )
+            DELETE FROM gameday_series
+            WHERE series_id = $1
+            "#,
+            series_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(series) = series {
+            record_event(
+                &mut *tx,
+                organizer.player_id,
+                "series.delete",
+                &format!("series:{}", series_id),
+                Some(serde_json::json!({
+                    "series": snapshot(&series),
+                    "gamedays": gamedays
+                        .iter()
+                        .map(|gameday| serde_json::json!({
+                            "gameday_id": gameday.gameday_id,
+                            "start_date": gameday.start_date,
+                            "end_date": gameday.end_date,
+                            "registered": gameday.registered,
+                            "waitlist": gameday.waitlist,
+                        }))
+                        .collect::<Vec<_>>(),
+                })),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(gamedays)
+    }
+    .await;
+
+    match deleted {
+        Ok(gamedays) => {
+            info!("Series {:?} deleted successfully.", series_id);
+            for gameday in gamedays {
+                notify_players(
+                    Notification::Cancellation,
+                    [gameday.registered, gameday.waitlist].concat(),
+                    "Speldag inställd".to_string(),
+                    format!(
+                        "Speldagen {} är inställd.",
+                        gameday_time(gameday.start_date, gameday.end_date)
+                    ),
+                );
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to delete gameday series.".to_string(),
+            ))
+        }
+    }
+}
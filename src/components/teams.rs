@@ -0,0 +1,630 @@
+use leptos::prelude::*;
+
+#[cfg(feature = "ssr")]
+use crate::models::{Position, RatedPlayer};
+use crate::{
+    components::roster::RosterActions,
+    models::{GamedayTeams, Team, TeamPlayer},
+};
+
+/// Light and dark lineups of a gameday. Organizers generate, regenerate and lock them,
+/// players see them once they are locked.
+#[component]
+pub fn TeamLineups(gameday_id: i32, can_manage: bool, roster: RosterActions) -> impl IntoView {
+    let generate = ServerAction::<GenerateTeams>::new();
+    let lock = ServerAction::<LockTeams>::new();
+    let teams = Resource::new(
+        move || {
+            (
+                roster.version(),
+                generate.version().get(),
+                lock.version().get(),
+            )
+        },
+        move |_| async move { get_teams(gameday_id).await },
+    );
+    let error = move || {
+        generate
+            .value()
+            .get()
+            .or_else(|| lock.value().get())
+            .and_then(|result| result.err())
+            .map(|_| "Kunde inte uppdatera lagen.")
+    };
+
+    view! {
+        <Transition>
+            {move || Suspend::new(async move {
+                let teams = teams.await.unwrap_or_default();
+                if !teams.locked && !can_manage {
+                    return ().into_any();
+                }
+                let generated = teams.generated();
+                let locked = teams.locked;
+                let unassigned = teams
+                    .players
+                    .iter()
+                    .filter(|player| player.team.is_none())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                view! {
+                    <h2 class="text-center text-bold text-2xl mt-6">"Lag"</h2>
+                    {(!locked)
+                        .then(|| {
+                            view! {
+                                <p class="text-sm text-center">
+                                    "Lagen visas för spelarna när de är låsta."
+                                </p>
+                            }
+                        })}
+                    <Show when=move || generated>
+                        <div class="flex w-11/12 max-w-2xl gap-4 mt-2">
+                            {Team::ALL
+                                .into_iter()
+                                .map(|team| {
+                                    let players = teams
+                                        .players
+                                        .iter()
+                                        .filter(|player| player.team == Some(team))
+                                        .cloned()
+                                        .collect::<Vec<_>>();
+                                    view! { <TeamColumn team players /> }
+                                })
+                                .collect_view()}
+                        </div>
+                    </Show>
+                    {(can_manage && generated && !unassigned.is_empty())
+                        .then(|| {
+                            view! {
+                                <p class="text-center m-2">
+                                    "Utan lag: "
+                                    {unassigned
+                                        .into_iter()
+                                        .map(|player| player.label())
+                                        .collect::<Vec<_>>()
+                                        .join(", ")}
+                                </p>
+                            }
+                        })}
+                    {can_manage
+                        .then(|| {
+                            view! {
+                                <div class="flex gap-2 m-2">
+                                    <Show when=move || !locked>
+                                        <ActionForm action=generate>
+                                            <input type="hidden" name="gameday_id" value=gameday_id />
+                                            <button type="submit" class="btn btn-sm btn-primary">
+                                                {if generated { "Generera om" } else { "Generera lag" }}
+                                            </button>
+                                        </ActionForm>
+                                    </Show>
+                                    <Show when=move || generated>
+                                        <ActionForm action=lock>
+                                            <input type="hidden" name="gameday_id" value=gameday_id />
+                                            <input
+                                                type="hidden"
+                                                name="locked"
+                                                value=(!locked).to_string()
+                                            />
+                                            <button type="submit" class="btn btn-sm btn-warning">
+                                                {if locked { "Lås upp" } else { "Lås och publicera" }}
+                                            </button>
+                                        </ActionForm>
+                                    </Show>
+                                </div>
+                                <p class="text-error">{error}</p>
+                            }
+                        })}
+                }
+                    .into_any()
+            })}
+        </Transition>
+    }
+}
+
+#[component]
+fn TeamColumn(team: Team, players: Vec<TeamPlayer>) -> impl IntoView {
+    let color = match team {
+        Team::Light => "bg-base-100 border border-base-300",
+        Team::Dark => "bg-neutral text-neutral-content",
+    };
+    let (goalkeepers, skaters): (Vec<_>, Vec<_>) =
+        players.into_iter().partition(|player| player.is_goalkeeper);
+
+    view! {
+        <div class=format!("flex flex-col flex-1 items-center rounded-box p-2 {}", color)>
+            <h3 class="text-xl text-bold">{team.label()}</h3>
+            <ul class="flex flex-col items-center">
+                {goalkeepers
+                    .into_iter()
+                    .map(|player| view! { <li>{player.label()}" (MV)"</li> })
+                    .collect_view()}
+                {skaters
+                    .into_iter()
+                    .map(|player| view! { <li>{player.label()}</li> })
+                    .collect_view()}
+            </ul>
+        </div>
+    }
+}
+
+/// Someone holding a spot on the gameday, guests get a team like the players.
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Participant {
+    Player(i32),
+    Guest(i32),
+}
+
+/// A registered player or guest as the generator sees them.
+#[cfg(feature = "ssr")]
+#[derive(Clone)]
+struct RatedRegistration {
+    participant: Participant,
+    is_goalkeeper: bool,
+    position: Position,
+    skill: i16,
+}
+
+#[cfg(feature = "ssr")]
+impl RatedRegistration {
+    /// Position on this gameday, a goalkeeper playing as skater counts as forward.
+    fn playing_position(&self) -> Position {
+        match (self.is_goalkeeper, self.position) {
+            (true, _) => Position::Goalkeeper,
+            (false, Position::Defender) => Position::Defender,
+            (false, _) => Position::Forward,
+        }
+    }
+}
+
+/// Splits the players into two teams. Each position is dealt out on its own so the
+/// goalkeepers end up one per team and the defenders are split evenly, strongest first
+/// to the team with the lowest total skill. The odd player of a position goes to the
+/// smaller team, so the team sizes never differ by more than one.
+/// Players with the same skill are shuffled, so regenerating gives new lineups.
+#[cfg(feature = "ssr")]
+fn balance_teams(
+    players: Vec<RatedRegistration>,
+    rng: &mut impl rand::Rng,
+) -> Vec<(Participant, Team)> {
+    use rand::seq::SliceRandom;
+    use std::cmp::{Ordering, Reverse};
+
+    let mut lineup = Vec::with_capacity(players.len());
+    let mut size = [0usize; 2];
+    let mut skill = [0i32; 2];
+    for position in Position::ALL {
+        let mut group = players
+            .iter()
+            .filter(|player| player.playing_position() == position)
+            .collect::<Vec<_>>();
+        group.shuffle(rng);
+        group.sort_by_key(|player| Reverse(player.skill));
+
+        let mut max = [group.len() / 2; 2];
+        if group.len() % 2 == 1 {
+            let extra = match (size[0], skill[0]).cmp(&(size[1], skill[1])) {
+                Ordering::Less => 0,
+                Ordering::Greater => 1,
+                Ordering::Equal => rng.gen_range(0..2),
+            };
+            max[extra] += 1;
+        }
+        let mut count = [0usize; 2];
+        for player in group {
+            let team = if count[0] == max[0] {
+                1
+            } else if count[1] == max[1] {
+                0
+            } else {
+                match (skill[0], size[0]).cmp(&(skill[1], size[1])) {
+                    Ordering::Less => 0,
+                    Ordering::Greater => 1,
+                    Ordering::Equal => rng.gen_range(0..2),
+                }
+            };
+            count[team] += 1;
+            skill[team] += i32::from(player.skill);
+            size[team] += 1;
+            lineup.push((player.participant, Team::ALL[team]));
+        }
+    }
+    lineup
+}
+
+/// Locks the gameday and returns whether its teams are locked.
+#[cfg(feature = "ssr")]
+async fn lock_gameday(
+    conn: &mut sqlx::PgConnection,
+    gameday_id: i32,
+) -> Result<bool, ServerFnError> {
+    let locked = sqlx::query_scalar!(
+        r#"
+        SELECT teams_locked
+        FROM gameday
+        WHERE gameday_id = $1
+        FOR UPDATE
+        "#,
+        gameday_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match locked {
+        Some(locked) => Ok(locked),
+        None => Err(ServerFnError::ServerError("No gameday found.".to_string())),
+    }
+}
+
+#[server]
+pub async fn get_teams(gameday_id: i32) -> Result<GamedayTeams, ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use tracing::error;
+
+    let user = require_player().await?;
+
+    let pool = get_db();
+    let locked = match sqlx::query_scalar!(
+        r#"
+        SELECT teams_locked
+        FROM gameday
+        WHERE gameday_id = $1
+        "#,
+        gameday_id
+    )
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(locked) => locked.unwrap_or(false),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            return Err(ServerFnError::ServerError(
+                "Failed to get teams.".to_string(),
+            ));
+        }
+    };
+    // Drafts are only for those making the teams
+    if !locked && !user.role.has_permission(Permission::ManageRoster) {
+        return Ok(GamedayTeams::default());
+    }
+
+    match sqlx::query_as!(
+        TeamPlayer,
+        r#"
+        SELECT
+            p.player_id as "player_id!",
+            NULL::INT as guest_id,
+            p.name as "name!",
+            pg.is_goalkeeper as "is_goalkeeper!",
+            pg.team as "team: Team"
+        FROM player p
+        JOIN player_gameday pg ON p.player_id = pg.player_id
+        WHERE pg.gameday_id = $1
+        UNION ALL
+        SELECT gg.host_id, gg.guest_id, gg.name, gg.is_goalkeeper, gg.team
+        FROM guest_gameday gg
+        WHERE gg.gameday_id = $1
+        ORDER BY 3
+        "#,
+        gameday_id
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(players) => Ok(GamedayTeams { locked, players }),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get teams.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Puts every registered player and guest in the light or the dark team, replacing
+/// the previous lineups.
+#[server]
+pub async fn generate_teams(gameday_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use http::StatusCode;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    if lock_gameday(&mut tx, gameday_id).await? {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "The teams are locked.".to_string(),
+        ));
+    }
+
+    let mut players = sqlx::query!(
+        r#"
+        SELECT pg.player_id, pg.is_goalkeeper, p.position as "position: Position", p.skill
+        FROM player_gameday pg
+        JOIN player p ON p.player_id = pg.player_id
+        WHERE pg.gameday_id = $1
+        "#,
+        gameday_id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|player| RatedRegistration {
+        participant: Participant::Player(player.player_id),
+        is_goalkeeper: player.is_goalkeeper,
+        position: player.position,
+        skill: player.skill,
+    })
+    .collect::<Vec<_>>();
+
+    // Guests aren't rated, they count as an average skater or goalkeeper
+    let guests = sqlx::query!(
+        r#"
+        SELECT guest_id, is_goalkeeper
+        FROM guest_gameday
+        WHERE gameday_id = $1
+        "#,
+        gameday_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    players.extend(guests.into_iter().map(|guest| RatedRegistration {
+        participant: Participant::Guest(guest.guest_id),
+        is_goalkeeper: guest.is_goalkeeper,
+        position: Position::Forward,
+        skill: RatedPlayer::SKILL_LEVELS[RatedPlayer::SKILL_LEVELS.len() / 2],
+    }));
+
+    let lineup = balance_teams(players, &mut rand::thread_rng());
+    let team_ids = |team: Team, guests: bool| {
+        lineup
+            .iter()
+            .filter(|(_, assigned)| *assigned == team)
+            .filter_map(|(participant, _)| match (participant, guests) {
+                (Participant::Player(player_id), false) => Some(*player_id),
+                (Participant::Guest(guest_id), true) => Some(*guest_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let light = team_ids(Team::Light, false);
+    let dark = team_ids(Team::Dark, false);
+    let light_guests = team_ids(Team::Light, true);
+    let dark_guests = team_ids(Team::Dark, true);
+
+    sqlx::query!(
+        r#"
+        UPDATE player_gameday
+        SET team = CASE
+            WHEN player_id = ANY($2) THEN 'light'::team_color
+            WHEN player_id = ANY($3) THEN 'dark'::team_color
+        END
+        WHERE gameday_id = $1
+        "#,
+        gameday_id,
+        &light,
+        &dark
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE guest_gameday
+        SET team = CASE
+            WHEN guest_id = ANY($2) THEN 'light'::team_color
+            WHEN guest_id = ANY($3) THEN 'dark'::team_color
+        END
+        WHERE gameday_id = $1
+        "#,
+        gameday_id,
+        &light_guests,
+        &dark_guests
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_event(
+        &mut *tx,
+        organizer.player_id,
+        "teams.generate",
+        &format!("gameday:{}", gameday_id),
+        None,
+        Some(serde_json::json!({
+            "light": light,
+            "dark": dark,
+            "light_guests": light_guests,
+            "dark_guests": dark_guests,
+        })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} generated teams for gameday {:?}",
+        organizer.player_id, gameday_id
+    );
+    Ok(())
+}
+
+/// Locking publishes the lineups to the players and keeps them from being regenerated,
+/// the teams have to be generated first.
+#[server]
+pub async fn lock_teams(gameday_id: i32, locked: bool) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use http::StatusCode;
+    use tracing::info;
+
+    let organizer = require_permission(Permission::ManageRoster).await?;
+
+    let mut tx = get_db().begin().await?;
+    let before = lock_gameday(&mut tx, gameday_id).await?;
+    let generated = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM player_gameday WHERE gameday_id = $1 AND team IS NOT NULL
+        ) OR EXISTS (
+            SELECT 1 FROM guest_gameday WHERE gameday_id = $1 AND team IS NOT NULL
+        ) as "generated!"
+        "#,
+        gameday_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if locked && !generated {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "No teams have been generated.".to_string(),
+        ));
+    }
+    sqlx::query!(
+        r#"
+        UPDATE gameday
+        SET teams_locked = $2
+        WHERE gameday_id = $1
+        "#,
+        gameday_id,
+        locked
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_event(
+        &mut *tx,
+        organizer.player_id,
+        "teams.lock",
+        &format!("gameday:{}", gameday_id),
+        Some(serde_json::json!({ "locked": before })),
+        Some(serde_json::json!({ "locked": locked })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} set teams locked {:?} for gameday {:?}",
+        organizer.player_id, locked, gameday_id
+    );
+    Ok(())
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::{balance_teams, Participant, RatedRegistration};
+    use crate::models::{Position, Team};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn player(player_id: i32, position: Position, skill: i16) -> RatedRegistration {
+        RatedRegistration {
+            participant: Participant::Player(player_id),
+            is_goalkeeper: position == Position::Goalkeeper,
+            position,
+            skill,
+        }
+    }
+
+    fn team_of(lineup: &[(Participant, Team)], player_id: i32) -> Team {
+        lineup
+            .iter()
+            .find(|(participant, _)| *participant == Participant::Player(player_id))
+            .map(|(_, team)| *team)
+            .expect("player not in a team")
+    }
+
+    #[test]
+    fn teams_are_even_in_size_and_skill() {
+        let players = (1..=16)
+            .map(|player_id| {
+                let position = if player_id % 3 == 0 {
+                    Position::Defender
+                } else {
+                    Position::Forward
+                };
+                player(player_id, position, (player_id % 5 + 1) as i16)
+            })
+            .collect::<Vec<_>>();
+        let skills = players
+            .iter()
+            .map(|player| player.skill)
+            .collect::<Vec<_>>();
+
+        for seed in 0..50 {
+            let lineup = balance_teams(players.clone(), &mut StdRng::seed_from_u64(seed));
+            assert_eq!(lineup.len(), 16);
+            let mut size = [0; 2];
+            let mut skill = [0i32; 2];
+            for (player_id, team) in (1..=16).map(|id| (id, team_of(&lineup, id))) {
+                let index = Team::ALL.iter().position(|t| *t == team).unwrap();
+                size[index] += 1;
+                skill[index] += i32::from(skills[player_id as usize - 1]);
+            }
+            assert_eq!(size, [8, 8]);
+            assert!(
+                (skill[0] - skill[1]).abs() <= 5,
+                "seed {}: {:?}",
+                seed,
+                skill
+            );
+        }
+    }
+
+    #[test]
+    fn odd_positions_even_out_the_team_sizes() {
+        // The lone defender and forward would both follow the weaker goalkeeper
+        let players = vec![
+            player(1, Position::Goalkeeper, 4),
+            player(2, Position::Goalkeeper, 1),
+            player(3, Position::Defender, 1),
+            player(4, Position::Forward, 3),
+        ];
+
+        for seed in 0..20 {
+            let lineup = balance_teams(players.clone(), &mut StdRng::seed_from_u64(seed));
+            assert_ne!(team_of(&lineup, 1), team_of(&lineup, 2));
+            assert_ne!(team_of(&lineup, 3), team_of(&lineup, 4));
+        }
+    }
+
+    #[test]
+    fn goalkeepers_are_split_between_the_teams() {
+        let players = vec![
+            player(1, Position::Goalkeeper, 5),
+            player(2, Position::Goalkeeper, 1),
+            player(3, Position::Forward, 3),
+            player(4, Position::Forward, 3),
+            player(5, Position::Defender, 2),
+            player(6, Position::Defender, 4),
+        ];
+
+        for seed in 0..20 {
+            let lineup = balance_teams(players.clone(), &mut StdRng::seed_from_u64(seed));
+            assert_ne!(team_of(&lineup, 1), team_of(&lineup, 2));
+            assert_ne!(team_of(&lineup, 3), team_of(&lineup, 4));
+            assert_ne!(team_of(&lineup, 5), team_of(&lineup, 6));
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_lineup() {
+        let players = || {
+            (1..=10)
+                .map(|player_id| player(player_id, Position::Forward, 3))
+                .collect::<Vec<_>>()
+        };
+        let first = balance_teams(players(), &mut StdRng::seed_from_u64(7));
+        let second = balance_teams(players(), &mut StdRng::seed_from_u64(7));
+        assert_eq!(first, second);
+    }
+}
@@ -1,10 +1,12 @@
 use chrono::{DateTime, Utc};
 use leptos::prelude::*;
 
+use crate::timezone::to_club_time;
+
 #[component]
 pub fn TimeCard(start: DateTime<Utc>, end: DateTime<Utc>) -> impl IntoView {
-    let start = start.with_timezone(&Utc).format("%H:%M").to_string();
-    let end = end.with_timezone(&Utc).format("%H:%M").to_string();
+    let start = to_club_time(start).format("%H:%M").to_string();
+    let end = to_club_time(end).format("%H:%M").to_string();
     view! {
         <div class="flex flex-row items-center justify-center m-2 w-full">
             <p class="text-center">{start}</p>
@@ -1,16 +1,20 @@
 #![cfg(feature = "ssr")]
 
 use sqlx::*;
+
+use crate::config::get_config;
 use tracing::info;
 
 static DB: std::sync::OnceLock<sqlx::PgPool> = std::sync::OnceLock::new();
 
 async fn create_pool() -> sqlx::PgPool {
     info!("Creating database pool");
-    let database_url = std::env::var("DATABASE_URL").expect("no database url specify");
+    let config = &get_config().database;
     let pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(10)
-        .connect(database_url.as_str())
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(std::time::Duration::from_secs(config.acquire_timeout_secs))
+        .connect(config.url.as_str())
         .await
         .expect("could not connect to database_url");
 
@@ -1,9 +1,15 @@
+pub mod api;
 pub mod app;
+pub mod audit;
 pub mod auth;
+pub mod calendar;
 mod components;
+pub mod config;
 pub mod database;
+pub mod mailer;
 pub mod models;
 pub mod pages;
+pub mod timezone;
 
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
@@ -0,0 +1,269 @@
+#![cfg(feature = "ssr")]
+
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use thiserror::Error;
+use tracing::{error, info};
+
+use crate::{
+    config::{get_config, MailBackend},
+    database::get_db,
+    timezone::to_club_time,
+};
+
+static MAILER: OnceLock<Box<dyn Mailer>> = OnceLock::new();
+
+#[derive(Error, Debug)]
+pub enum MailerError {
+    #[error("invalid address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("could not build email: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("smtp error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("could not write email: {0}")]
+    File(#[from] lettre::transport::file::Error),
+}
+
+/// Delivers emails. Implemented by the SMTP backend used in production and a file
+/// backend for local development and tests.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: Message) -> Result<(), MailerError>;
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(host: &str, username: String, password: String) -> Result<Self, MailerError> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+            .credentials(Credentials::new(username, password))
+            .build();
+        Ok(SmtpMailer { transport })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: Message) -> Result<(), MailerError> {
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Writes every email as an `.eml` file to a directory instead of sending it.
+pub struct FileMailer {
+    transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileMailer {
+    pub fn new(dir: &str) -> Self {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            error!("Could not create mail directory {}: {:?}", dir, e);
+        }
+        FileMailer {
+            transport: AsyncFileTransport::new(dir),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: Message) -> Result<(), MailerError> {
+        let id = self.transport.send(message).await?;
+        info!("Email written to file {}.eml", id);
+        Ok(())
+    }
+}
+
+/// Sets up the configured backend.
+pub fn init_mailer() {
+    let config = &get_config().mail;
+    let mailer: Box<dyn Mailer> = match config.backend {
+        MailBackend::Smtp => {
+            info!("Sending email through {}", config.smtp_host);
+            Box::new(
+                SmtpMailer::new(
+                    &config.smtp_host,
+                    config.smtp_username.clone(),
+                    config.smtp_password.expose().to_string(),
+                )
+                .expect("invalid smtp settings"),
+            )
+        }
+        MailBackend::File => {
+            info!("Writing email to {}", config.dir);
+            Box::new(FileMailer::new(&config.dir))
+        }
+    };
+
+    if MAILER.set(mailer).is_err() {
+        error!("Mailer already initialized");
+    }
+}
+
+pub fn get_mailer<'a>() -> &'a dyn Mailer {
+    MAILER.get().expect("mailer unitialized").as_ref()
+}
+
+/// The kinds of emails a player can opt out of from the profile page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notification {
+    /// Confirmation when the player joins or leaves a gameday
+    Registration,
+    /// The player got a spot from the waitlist
+    Waitlist,
+    /// A gameday the player signed up for was cancelled
+    Cancellation,
+    /// Reminder before a gameday the player signed up for
+    Reminder,
+    /// Goalkeepers are asked to join a gameday with too few goalkeepers
+    GoalkeeperShortage,
+}
+
+/// Emails the players that haven't opted out of the notification.
+/// Runs in the background so a slow mail server doesn't hold up the request.
+pub fn notify_players(
+    notification: Notification,
+    player_ids: Vec<i32>,
+    subject: String,
+    text: String,
+) {
+    if player_ids.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        if let Err(e) = send_notification(notification, &player_ids, &subject, &text).await {
+            error!("Failed to send {:?} notification: {:?}", notification, e);
+        }
+    });
+}
+
+/// Same as `notify_players` with the time of the gameday appended to the text.
+pub fn notify_gameday_players(
+    notification: Notification,
+    gameday_id: i32,
+    player_ids: Vec<i32>,
+    subject: String,
+    text: String,
+) {
+    if player_ids.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let gameday = sqlx::query!(
+            r#"
+            SELECT start_date, end_date
+            FROM gameday
+            WHERE gameday_id = $1
+            "#,
+            gameday_id
+        )
+        .fetch_one(get_db())
+        .await;
+
+        match gameday {
+            Ok(gameday) => {
+                let text = format!(
+                    "{}\n\n{}",
+                    text,
+                    gameday_time(gameday.start_date, gameday.end_date)
+                );
+                notify_players(notification, player_ids, subject, text);
+            }
+            Err(e) => error!("Database error: {:?}", e),
+        }
+    });
+}
+
+/// Formats a gameday in the club's timezone, e.g. `2025-01-14 kl 19:00-20:00`.
+pub fn gameday_time(start_date: DateTime<Utc>, end_date: DateTime<Utc>) -> String {
+    format!(
+        "{} kl {}-{}",
+        to_club_time(start_date).format("%Y-%m-%d"),
+        to_club_time(start_date).format("%H:%M"),
+        to_club_time(end_date).format("%H:%M")
+    )
+}
+
+async fn send_notification(
+    notification: Notification,
+    player_ids: &[i32],
+    subject: &str,
+    text: &str,
+) -> Result<(), MailerError> {
+    let recipients = match sqlx::query!(
+        r#"
+        SELECT
+            given_name,
+            email,
+            notify_registration,
+            notify_waitlist,
+            notify_cancellation,
+            notify_reminder,
+            notify_goalkeeper_shortage
+        FROM player
+        WHERE player_id = ANY($1)
+        "#,
+        player_ids
+    )
+    .fetch_all(get_db())
+    .await
+    {
+        Ok(recipients) => recipients,
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            return Ok(());
+        }
+    };
+
+    let from: Mailbox = get_config().mail.from.parse()?;
+
+    for recipient in recipients {
+        let opted_in = match notification {
+            Notification::Registration => recipient.notify_registration,
+            Notification::Waitlist => recipient.notify_waitlist,
+            Notification::Cancellation => recipient.notify_cancellation,
+            Notification::Reminder => recipient.notify_reminder,
+            Notification::GoalkeeperShortage => recipient.notify_goalkeeper_shortage,
+        };
+        if !opted_in {
+            continue;
+        }
+
+        // One bad address shouldn't stop the rest
+        let to: Mailbox = match recipient.email.parse() {
+            Ok(to) => to,
+            Err(e) => {
+                error!("Invalid address {}: {:?}", recipient.email, e);
+                continue;
+            }
+        };
+        let message = Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(format!(
+                "Hej {}!\n\n{}\n\nDu kan stänga av mejlen under din profil på gubbhockey.com.\n",
+                recipient.given_name, text
+            ))?;
+
+        if let Err(e) = get_mailer().send(message).await {
+            error!("Failed to email {}: {:?}", recipient.email, e);
+        }
+    }
+
+    Ok(())
+}
@@ -6,9 +6,15 @@ use tracing::{error, info};
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() {
-    use axum::Router;
+    use axum::{middleware, routing::get, Router};
+    use gubbhockey::api;
     use gubbhockey::app::*;
+    use gubbhockey::auth::{refresh_session, AuthClient, AuthConfig};
+    use gubbhockey::calendar::{player_calendar, public_calendar};
+    use gubbhockey::config::{get_config, init_config, Config};
     use gubbhockey::database::init_db;
+    use gubbhockey::mailer::init_mailer;
+    use gubbhockey::timezone::set_club_timezone;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
     extern crate dotenv;
@@ -28,13 +34,36 @@ async fn main() {
 
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
+    let config = Config::load().unwrap_or_else(|e| panic!("Unable to load config: {}", e));
+    if std::env::args().any(|arg| arg == "--print-config") {
+        print!("{}", config.to_redacted_toml());
+        if let Err(e) = config.validate() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Err(e) = config.validate() {
+        panic!("{}", e);
+    }
+    set_club_timezone(config.club_timezone());
+    init_config(config);
+    let config = get_config();
+
     let conf = get_configuration(None).unwrap();
     let addr = conf.leptos_options.site_addr;
     let leptos_options = conf.leptos_options;
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(App);
 
+    let auth_config = AuthConfig::new(&config.auth)
+        .unwrap_or_else(|e| panic!("Invalid auth configuration: {}", e));
+    let auth_client = AuthClient::discover(auth_config)
+        .await
+        .unwrap_or_else(|e| panic!("Unable to set up login: {}", e));
+
     init_db().await.expect("Unable to init db");
+    init_mailer();
 
     let sched = JobScheduler::new()
         .await
@@ -42,8 +71,7 @@ async fn main() {
     // Add async job to clear db
     sched
         .add(
-            //3am every day
-            Job::new_async("0 0 3 * * *", |uuid, mut l| {
+            Job::new_async(config.scheduler.cleanup_cron.as_str(), |uuid, mut l| {
                 Box::pin(async move {
                     info!("Running scheduled job for cleaning up db");
                     if let Err(err) = delete_old_sessions().await {
@@ -67,22 +95,49 @@ async fn main() {
         .await
         .expect("unable to create scheduled job");
 
+    sched
+        .add(
+            Job::new_async(config.scheduler.reminder_cron.as_str(), |_uuid, _l| {
+                Box::pin(async move {
+                    if let Err(err) = send_gameday_reminders().await {
+                        error!("Failed to send gameday reminders: {}", err);
+                    }
+                })
+            })
+            .expect("unable to create async job"),
+        )
+        .await
+        .expect("unable to create scheduled job");
+
     sched.start().await.expect("unable to start scheduler");
 
     let app = Router::new()
-        .leptos_routes(&leptos_options, routes, {
-            let leptos_options = leptos_options.clone();
-            move || shell(leptos_options.clone())
-        })
+        .route("/calendar.ics", get(public_calendar))
+        .route("/calendar/:token", get(player_calendar))
+        .nest("/api/v1", api::router())
+        .leptos_routes_with_context(
+            &leptos_options,
+            routes,
+            move || provide_context(auth_client.clone()),
+            {
+                let leptos_options = leptos_options.clone();
+                move || shell(leptos_options.clone())
+            },
+        )
+        .layer(middleware::from_fn(refresh_session))
         .layer(CookieManagerLayer::new())
         .fallback(leptos_axum::file_and_error_handler(shell))
         .with_state(leptos_options);
 
     info!("listening on http://{}", &addr);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app.into_make_service())
-        .await
-        .unwrap();
+    // The peer address is the fallback for the session list when there is no proxy in front
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
 #[cfg(feature = "ssr")]
@@ -143,6 +198,105 @@ async fn delete_old_pkce() -> Result<(), ServerFnError> {
     }
 }
 
+/// Goalkeepers are asked to join when fewer than this are registered.
+#[cfg(feature = "ssr")]
+const MIN_GOALKEEPERS: i64 = 2;
+
+/// Reminds registered players `scheduler.reminder_hours` before each gameday and asks
+/// the other goalkeepers to join when the gameday is short on goalkeepers.
+#[cfg(feature = "ssr")]
+async fn send_gameday_reminders() -> Result<(), ServerFnError> {
+    use gubbhockey::config::get_config;
+    use gubbhockey::database::get_db;
+    use gubbhockey::mailer::{gameday_time, notify_players, Notification};
+
+    let reminder_hours = get_config().scheduler.reminder_hours;
+
+    let pool = get_db();
+
+    // Marking the gamedays in the same statement makes sure each reminder goes out once
+    let gamedays = match sqlx::query!(
+        r#"
+        WITH due AS (
+            UPDATE gameday
+            SET reminder_sent = TRUE
+            WHERE NOT reminder_sent
+              AND start_date > NOW()
+              AND start_date <= NOW() + make_interval(hours => $1)
+            RETURNING gameday_id, start_date, end_date
+        )
+        SELECT
+            d.gameday_id,
+            d.start_date,
+            d.end_date,
+            ARRAY(
+                SELECT pg.player_id FROM player_gameday pg
+                WHERE pg.gameday_id = d.gameday_id
+            ) as "registered!",
+            (
+                SELECT COUNT(*) FROM player_gameday pg
+                WHERE pg.gameday_id = d.gameday_id AND pg.is_goalkeeper
+            ) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = d.gameday_id AND gg.is_goalkeeper
+            ) as "goalkeeper_count!",
+            ARRAY(
+                SELECT p.player_id FROM player p
+                WHERE p.is_goalkeeper AND NOT EXISTS (
+                    SELECT 1 FROM player_gameday pg
+                    WHERE pg.gameday_id = d.gameday_id AND pg.player_id = p.player_id
+                )
+            ) as "available_goalkeepers!"
+        FROM due d
+        "#,
+        reminder_hours
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(gamedays) => gamedays,
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            return Err(ServerFnError::ServerError(
+                "Failed to get upcoming gamedays.".to_string(),
+            ));
+        }
+    };
+
+    for gameday in gamedays {
+        let time = gameday_time(gameday.start_date, gameday.end_date);
+        info!("Sending reminders for gameday {:?}", gameday.gameday_id);
+
+        notify_players(
+            Notification::Reminder,
+            gameday.registered,
+            format!("Påminnelse: gubbhockey {}", time),
+            format!(
+                "Du är anmäld till speldagen {}.\n\nKan du inte komma? Avanmäl dig på gubbhockey.com så att någon på reservlistan får din plats.",
+                time
+            ),
+        );
+
+        if gameday.goalkeeper_count < MIN_GOALKEEPERS {
+            info!(
+                "Gameday {:?} has {} goalkeepers, asking for more",
+                gameday.gameday_id, gameday.goalkeeper_count
+            );
+            notify_players(
+                Notification::GoalkeeperShortage,
+                gameday.available_goalkeepers,
+                "Vi behöver målvakter".to_string(),
+                format!(
+                    "Det är bara {} målvakter anmälda till speldagen {}.\n\nKan du ställa upp? Anmäl dig på gubbhockey.com.",
+                    gameday.goalkeeper_count, time
+                ),
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(not(feature = "ssr"))]
 pub fn main() {
     // no client-side main function
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use leptos::prelude::*;
 use reactive_stores::Store;
 use serde::{Deserialize, Serialize};
@@ -6,22 +6,315 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[cfg_attr(feature = "ssr", derive(FromRow))]
+#[cfg_attr(feature = "ssr", derive(FromRow, utoipa::ToSchema))]
 pub struct Player {
     pub player_id: i32,
     pub name: String,
     pub given_name: String,
     pub family_name: String,
     pub email: String,
-    pub access_group: Option<String>,
-    pub is_goalkeeper: bool,
+    pub role: Role,
+    pub position: Position, // Preferred position, used when joining gamedays and cups
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Player {
+    pub fn is_goalkeeper(&self) -> bool {
+        self.position == Position::Goalkeeper
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type, utoipa::ToSchema))]
+#[cfg_attr(
+    feature = "ssr",
+    sqlx(type_name = "player_position", rename_all = "snake_case")
+)]
+pub enum Position {
+    Goalkeeper,
+    Defender,
+    #[default]
+    Forward,
+}
+
+impl Position {
+    pub const ALL: [Position; 3] = [Position::Goalkeeper, Position::Defender, Position::Forward];
+
+    /// Same name as in the database and in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Position::Goalkeeper => "goalkeeper",
+            Position::Defender => "defender",
+            Position::Forward => "forward",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Position::Goalkeeper => "Målvakt",
+            Position::Defender => "Back",
+            Position::Forward => "Forward",
+        }
+    }
+
+    pub fn short_label(self) -> &'static str {
+        match self {
+            Position::Goalkeeper => "G",
+            Position::Defender => "D",
+            Position::Forward => "F",
+        }
+    }
+}
+
+/// What a player is allowed to do besides signing up.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type, utoipa::ToSchema))]
+#[cfg_attr(
+    feature = "ssr",
+    sqlx(type_name = "player_role", rename_all = "snake_case")
+)]
+pub enum Role {
+    #[default]
+    Player,
+    /// Coach or organizer, runs the gamedays
+    Organizer,
+    CupManager,
+    Admin,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Permission {
+    /// Create gamedays and recurring series
+    CreateGameday,
+    /// Edit, move and delete gamedays and series
+    EditGameday,
+    /// Add, remove and move players on a gameday and mark attendance
+    ManageRoster,
+    /// Create, edit and delete cups
+    EditCup,
+    /// See every player and assign roles
+    ManagePlayers,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Player, Role::Organizer, Role::CupManager, Role::Admin];
+
+    pub fn permissions(self) -> &'static [Permission] {
+        match self {
+            Role::Player => &[],
+            Role::Organizer => &[
+                Permission::CreateGameday,
+                Permission::EditGameday,
+                Permission::ManageRoster,
+            ],
+            Role::CupManager => &[Permission::EditCup],
+            Role::Admin => &[
+                Permission::CreateGameday,
+                Permission::EditGameday,
+                Permission::ManageRoster,
+                Permission::EditCup,
+                Permission::ManagePlayers,
+            ],
+        }
+    }
+
+    pub fn has_permission(self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    /// Same name as in the database and in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Player => "player",
+            Role::Organizer => "organizer",
+            Role::CupManager => "cup_manager",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Role::Player => "Spelare",
+            Role::Organizer => "Organisatör",
+            Role::CupManager => "Cupansvarig",
+            Role::Admin => "Admin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct NotificationSettings {
+    pub notify_registration: bool,
+    pub notify_waitlist: bool,
+    pub notify_cancellation: bool,
+    pub notify_reminder: bool,
+    pub notify_goalkeeper_shortage: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(FromRow, utoipa::ToSchema))]
 pub struct CupPlayer {
     pub name: String,
-    pub position: String,
+    pub position: Position,
+}
+
+/// Spot in a cup team, forwards play in lines and defenders in pairs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(
+    feature = "ssr",
+    sqlx(type_name = "lineup_position", rename_all = "snake_case")
+)]
+pub enum LineupPosition {
+    LeftWing,
+    Center,
+    RightWing,
+    LeftDefense,
+    RightDefense,
+    Goalkeeper,
+}
+
+impl LineupPosition {
+    pub const FORWARDS: [LineupPosition; 3] = [
+        LineupPosition::LeftWing,
+        LineupPosition::Center,
+        LineupPosition::RightWing,
+    ];
+    pub const DEFENDERS: [LineupPosition; 2] =
+        [LineupPosition::LeftDefense, LineupPosition::RightDefense];
+
+    pub fn short_label(self) -> &'static str {
+        match self {
+            LineupPosition::LeftWing => "LW",
+            LineupPosition::Center => "C",
+            LineupPosition::RightWing => "RW",
+            LineupPosition::LeftDefense => "LD",
+            LineupPosition::RightDefense => "RD",
+            LineupPosition::Goalkeeper => "G",
+        }
+    }
+
+    /// The position players pick when they join the cup.
+    pub fn cup_position(self) -> Position {
+        match self {
+            LineupPosition::LeftWing | LineupPosition::Center | LineupPosition::RightWing => {
+                Position::Forward
+            }
+            LineupPosition::LeftDefense | LineupPosition::RightDefense => Position::Defender,
+            LineupPosition::Goalkeeper => Position::Goalkeeper,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LineupSpot {
+    pub cup_team_id: i32,
+    pub position: LineupPosition,
+    pub line: i16, // Forward line, defense pair or G1/G2
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct CupTeam {
+    pub cup_team_id: i32,
+    pub name: String,
+}
+
+/// A player registered for a cup and the spot they have in the lineup, if any.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct CupLineupPlayer {
+    pub player_id: i32,
+    pub name: String,
+    pub position: Position,
+    pub cup_team_id: Option<i32>,
+    pub lineup_position: Option<LineupPosition>,
+    pub line: Option<i16>,
+}
+
+impl CupLineupPlayer {
+    pub fn spot(&self) -> Option<LineupSpot> {
+        Some(LineupSpot {
+            cup_team_id: self.cup_team_id?,
+            position: self.lineup_position?,
+            line: self.line?,
+        })
+    }
+}
+
+/// Teams of a cup, players only get them once the cup manager has published them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CupLineup {
+    pub published: bool,
+    pub teams: Vec<CupTeam>,
+    pub players: Vec<CupLineupPlayer>,
+}
+
+/// Spots of one cup position over all teams compared to the players registered for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionShortage {
+    pub spots: usize,
+    pub registered: usize,
+    pub placed: usize,
+}
+
+impl PositionShortage {
+    /// Players missing to fill every spot.
+    pub fn missing(&self) -> usize {
+        self.spots.saturating_sub(self.registered)
+    }
+}
+
+impl CupLineup {
+    pub const FORWARD_LINES: i16 = 3;
+    pub const DEFENSE_PAIRS: i16 = 3;
+    pub const GOALKEEPERS: i16 = 2;
+
+    /// Every spot of a team, forward lines first, then defense pairs and goalkeepers.
+    pub fn spots(cup_team_id: i32) -> Vec<LineupSpot> {
+        let spot = |position, line| LineupSpot {
+            cup_team_id,
+            position,
+            line,
+        };
+        let forwards = (1..=Self::FORWARD_LINES)
+            .flat_map(|line| LineupPosition::FORWARDS.map(|position| spot(position, line)));
+        let defenders = (1..=Self::DEFENSE_PAIRS)
+            .flat_map(|line| LineupPosition::DEFENDERS.map(|position| spot(position, line)));
+        let goalkeepers =
+            (1..=Self::GOALKEEPERS).map(|line| spot(LineupPosition::Goalkeeper, line));
+        forwards.chain(defenders).chain(goalkeepers).collect()
+    }
+
+    pub fn player_at(&self, spot: LineupSpot) -> Option<&CupLineupPlayer> {
+        self.players
+            .iter()
+            .find(|player| player.spot() == Some(spot))
+    }
+
+    pub fn shortage(&self, cup_position: Position) -> PositionShortage {
+        let spots_per_team = Self::spots(0)
+            .iter()
+            .filter(|spot| spot.position.cup_position() == cup_position)
+            .count();
+        PositionShortage {
+            spots: spots_per_team * self.teams.len(),
+            registered: self
+                .players
+                .iter()
+                .filter(|player| player.position == cup_position)
+                .count(),
+            placed: self
+                .players
+                .iter()
+                .filter_map(|player| player.spot())
+                .filter(|spot| spot.position.cup_position() == cup_position)
+                .count(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,22 +325,408 @@ pub struct UserInfo {
     pub email: String,
 }
 
+/// A device the player is logged in on, listed on the profile page.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct ActiveSession {
+    pub session_id: uuid::Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub current: bool, // The session of the request
+}
+
+/// A personal token for the JSON API, the token itself is only shown when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct ApiToken {
+    pub api_token_id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    pub const MAX_NAME_LENGTH: usize = 50;
+}
+
 #[derive(Clone, Debug, Default, Store)]
 pub struct GlobalState {
     pub logged_in: bool,
-    pub is_admin: bool,
+    pub role: Role,
+    pub cancellation_policy: CancellationPolicy,
+}
+
+/// When leaving a gameday counts as a late cancellation, and whether players may still
+/// leave then.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct CancellationPolicy {
+    pub hours: i32,
+    pub block: bool,
+}
+
+impl Default for CancellationPolicy {
+    fn default() -> Self {
+        CancellationPolicy {
+            hours: 24,
+            block: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[cfg_attr(feature = "ssr", derive(FromRow))]
+#[cfg_attr(feature = "ssr", derive(FromRow, utoipa::ToSchema))]
 pub struct Gameday {
     pub gameday_id: i32,
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
     pub player_count: Option<i64>,
+    pub max_skaters: i32,
+    pub max_goalkeepers: i32,
+    pub queue_position: Option<i64>, // Position on the waitlist, None when confirmed
+    pub time_changed: bool,          // Time was changed since the player signed up
+    pub late_cancellation: bool,     // Leaving now counts as a late cancellation
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct GamedaySeries {
+    pub series_id: i32,
+    pub weekday: i16, // ISO weekday, 1 = Monday
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub season_start: NaiveDate,
+    pub season_end: NaiveDate,
+    pub exceptions: Vec<NaiveDate>,
+    pub max_skaters: i32,
+    pub max_goalkeepers: i32,
+    pub upcoming_count: Option<i64>,
+}
+
+impl GamedaySeries {
+    /// Local dates of every occurrence in the season, skipping cancelled ones.
+    pub fn occurrences(&self) -> Vec<NaiveDate> {
+        use chrono::{Datelike, Duration};
+
+        let offset = (i64::from(self.weekday)
+            - i64::from(self.season_start.weekday().number_from_monday()))
+        .rem_euclid(7);
+        let mut date = self.season_start + Duration::days(offset);
+        let mut dates = Vec::new();
+        while date <= self.season_end {
+            if !self.exceptions.contains(&date) {
+                dates.push(date);
+            }
+            date += Duration::weeks(1);
+        }
+        dates
+    }
+}
+
+/// A player registered for a gameday.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct GamedayPlayer {
+    pub player_id: i32,
+    pub name: String,
+    pub is_goalkeeper: bool, // Position on this gameday, may differ from the player's usual
+    pub attendance: Option<Attendance>, // Only sent to those managing the roster
+    pub fee_unpaid: bool, // Season or drop-in fee not paid, also only for those managing the roster
+}
+
+/// A friend without a login that a registered player brings to a gameday.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow, utoipa::ToSchema))]
+pub struct Guest {
+    pub guest_id: i32,
+    pub name: String,
+    pub is_goalkeeper: bool,
+    pub host_id: i32,
+    pub host_name: String,
+    pub attendance: Option<Attendance>, // Only sent to those managing the roster
+}
+
+impl Guest {
+    pub const MAX_NAME_LENGTH: usize = 50;
+}
+
+/// Whether a registered player showed up, marked by the organizer on the day.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type, utoipa::ToSchema))]
+#[cfg_attr(
+    feature = "ssr",
+    sqlx(type_name = "attendance_status", rename_all = "snake_case")
+)]
+pub enum Attendance {
+    Attended,
+    NoShow,
+}
+
+impl Attendance {
+    /// Same name as in the database and in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Attendance::Attended => "attended",
+            Attendance::NoShow => "no_show",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Attendance::Attended => "Kom",
+            Attendance::NoShow => "Kom inte",
+        }
+    }
+}
+
+/// Jersey color of a generated team.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(
+    feature = "ssr",
+    sqlx(type_name = "team_color", rename_all = "snake_case")
+)]
+pub enum Team {
+    Light,
+    Dark,
+}
+
+impl Team {
+    pub const ALL: [Team; 2] = [Team::Light, Team::Dark];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Team::Light => "Ljusa",
+            Team::Dark => "Mörka",
+        }
+    }
+}
+
+/// A registered player or guest and the team they were put in, `None` when the teams
+/// were generated before they joined.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct TeamPlayer {
+    pub player_id: i32, // The host for guests
+    pub guest_id: Option<i32>,
+    pub name: String,
+    pub is_goalkeeper: bool,
+    pub team: Option<Team>,
+}
+
+impl TeamPlayer {
+    /// Name in the lineups, guests are marked so they can be told apart from players.
+    pub fn label(&self) -> String {
+        match self.guest_id {
+            Some(_) => format!("{} (gäst)", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// The lineups of a gameday. Players only get them once the organizer has locked them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GamedayTeams {
+    pub locked: bool,
+    pub players: Vec<TeamPlayer>,
+}
+
+impl GamedayTeams {
+    pub fn generated(&self) -> bool {
+        self.players.iter().any(|player| player.team.is_some())
+    }
+}
+
+/// A player with the skill rating admins set, used to balance the teams.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct RatedPlayer {
+    pub player_id: i32,
+    pub name: String,
+    pub email: String,
+    pub role: Role,
+    pub skill: i16,
+}
+
+impl RatedPlayer {
+    pub const SKILL_LEVELS: [i16; 5] = [1, 2, 3, 4, 5];
+}
+
+/// How reliably a player shows up, counting gamedays that have started.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct AttendanceStats {
+    pub player_id: i32,
+    pub name: String,
+    pub registered: i64,         // Gamedays the player was registered for
+    pub attended: i64,           // Checked in as attended
+    pub no_shows: i64,           // Checked in as no-show
+    pub late_cancellations: i64, // Left within the late cancellation window
+}
+
+impl AttendanceStats {
+    /// Share of the checked gamedays the player showed up for, `None` before any check-in.
+    pub fn attendance_rate(&self) -> Option<f64> {
+        let checked = self.attended + self.no_shows;
+        (checked > 0).then(|| self.attended as f64 / checked as f64)
+    }
+
+    /// The rate as a whole percentage, `-` before any check-in.
+    pub fn attendance_percent(&self) -> String {
+        self.attendance_rate()
+            .map(|rate| format!("{:.0} %", rate * 100.0))
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// Attendance statistics of every player, or only of `player_id`.
+#[cfg(feature = "ssr")]
+pub async fn attendance_stats(player_id: Option<i32>) -> Result<Vec<AttendanceStats>, sqlx::Error> {
+    use crate::database::get_db;
+
+    sqlx::query_as!(
+        AttendanceStats,
+        r#"
+        SELECT
+            p.player_id,
+            p.name,
+            COUNT(g.gameday_id) as "registered!",
+            COUNT(g.gameday_id) FILTER (WHERE pg.attendance = 'attended') as "attended!",
+            COUNT(g.gameday_id) FILTER (WHERE pg.attendance = 'no_show') as "no_shows!",
+            (
+                SELECT COUNT(*) FROM gameday_cancellation c
+                WHERE c.player_id = p.player_id AND c.late
+            ) as "late_cancellations!"
+        FROM player p
+        LEFT JOIN player_gameday pg ON p.player_id = pg.player_id
+        LEFT JOIN gameday g ON pg.gameday_id = g.gameday_id AND g.start_date <= NOW()
+        WHERE $1::int IS NULL OR p.player_id = $1
+        GROUP BY p.player_id, p.name
+        ORDER BY p.name
+        "#,
+        player_id
+    )
+    .fetch_all(get_db())
+    .await
 }
+
+/// A season of play, grouping the gamedays within its dates. Fees are whole kronor.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct Season {
+    pub season_id: i32,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub fee: i32,         // Membership fee for the whole season
+    pub drop_in_fee: i32, // Per session for players without a membership
+    pub gameday_count: Option<i64>,
+}
+
+/// A player and the player's membership of a season, if any.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct PlayerMembership {
+    pub player_id: i32,
+    pub name: String,
+    pub amount: Option<i32>, // None when the player isn't a member
+    pub paid_at: Option<DateTime<Utc>>,
+}
+
+impl PlayerMembership {
+    pub fn is_member(&self) -> bool {
+        self.amount.is_some()
+    }
+
+    pub fn is_paid(&self) -> bool {
+        self.paid_at.is_some()
+    }
+}
+
+/// A drop-in fee a player without a membership owes for a gameday.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct DropInFee {
+    pub gameday_id: i32,
+    pub player_id: i32,
+    pub name: String,
+    pub start_date: DateTime<Utc>,
+    pub amount: i32,
+}
+
+impl DropInFee {
+    /// Longest message Swish accepts.
+    pub const MAX_MESSAGE_LENGTH: usize = 50;
+
+    /// The payment message, the bank statement is matched on it. Only letters, digits,
+    /// spaces and dashes so it survives Swish and the bank export unchanged.
+    pub fn message(&self) -> String {
+        format!("Drop-in {} {}", self.gameday_id, self.name)
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-')
+            .take(Self::MAX_MESSAGE_LENGTH)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// A Swish payment request for a drop-in fee, `qr_svg` is rendered on the server.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct DropInPayment {
+    pub payee: String,
+    pub amount: i32,
+    pub message: String,
+    pub qr_svg: String,
+}
+
+/// Result of importing a bank statement.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BankImport {
+    pub rows: usize,
+    pub paid: Vec<String>, // Messages of the fees marked as paid
+}
+
+/// A row of the audit log, `before` and `after` are the JSON as text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow))]
+pub struct AuditEvent {
+    pub audit_event_id: i32,
+    pub actor_name: Option<String>, // None when the player has been deleted
+    pub action: String,
+    pub target: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters of the audit log page, empty fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuditFilter {
+    pub action: String,
+    pub actor: String,
+    pub target: String,
+}
+
+impl AuditEvent {
+    pub const PAGE_SIZE: i64 = 50;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow, utoipa::ToSchema))]
+pub struct WaitlistPlayer {
+    pub player_id: i32,
+    pub name: String,
+    pub is_goalkeeper: bool,
+    pub queue_position: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(FromRow, utoipa::ToSchema))]
 pub struct Cup {
     pub cup_id: i32,
     pub start_date: DateTime<Utc>,
@@ -63,42 +742,55 @@ pub struct PkceStore {
     pub id: i32,                   // Unique identifier for the entry
     pub csrf_token: String,        // CSRF token for validation
     pub pkce_verifier: String,     // PKCE verifier
+    pub nonce: String,             // Nonce expected in the ID token
+    pub remember: bool,            // Keep the session for the longer "remember me" lifetime
     pub created_at: DateTime<Utc>, // Timestamp when the entry was created
     pub expires_at: DateTime<Utc>, // Expiration timestamp
 }
 
 #[server]
-pub async fn get_players_by_gameday(gameday_id: i32) -> Result<Vec<Player>, ServerFnError> {
-    use crate::auth::user_from_session;
+pub async fn get_players_by_gameday(gameday_id: i32) -> Result<Vec<GamedayPlayer>, ServerFnError> {
+    use crate::auth::require_player;
     use tracing::{error, info};
 
     use crate::database::get_db;
-    if let Err(err) = user_from_session().await {
-        return Err(err);
-    }
+    let user = require_player().await?;
+    let manages_roster = user.role.has_permission(Permission::ManageRoster);
 
     let pool = get_db();
     match sqlx::query_as!(
-        Player,
+        GamedayPlayer,
         r#"
         SELECT 
             p.player_id,
             p.name,
-            p.given_name,
-            p.family_name,
-            p.email,
-            p.access_group,
-            p.is_goalkeeper
+            pg.is_goalkeeper,
+            CASE WHEN $2 THEN pg.attendance END as "attendance: Attendance",
+            $2 AND EXISTS (
+                SELECT 1 FROM gameday g
+                JOIN season s ON g.season_id = s.season_id
+                LEFT JOIN membership m
+                    ON m.season_id = s.season_id AND m.player_id = p.player_id
+                WHERE g.gameday_id = pg.gameday_id
+                  AND CASE
+                      WHEN m.player_id IS NOT NULL THEN m.paid_at IS NULL
+                      ELSE s.drop_in_fee > 0 AND NOT EXISTS (
+                          SELECT 1 FROM drop_in_payment d
+                          WHERE d.gameday_id = pg.gameday_id AND d.player_id = p.player_id
+                      )
+                  END
+            ) as "fee_unpaid!"
         FROM 
             Player p
         JOIN 
             Player_Gameday pg ON p.player_id = pg.player_id
-        JOIN 
-            Gameday g ON pg.gameday_id = g.gameday_id
         WHERE 
-            g.gameday_id = $1
+            pg.gameday_id = $1
+        ORDER BY
+            p.name
         "#,
-        gameday_id
+        gameday_id,
+        manages_roster
     )
     .fetch_all(pool)
     .await
@@ -115,3 +807,114 @@ pub async fn get_players_by_gameday(gameday_id: i32) -> Result<Vec<Player>, Serv
         }
     }
 }
+
+#[server]
+pub async fn get_waitlist_by_gameday(
+    gameday_id: i32,
+) -> Result<Vec<WaitlistPlayer>, ServerFnError> {
+    use crate::auth::require_player;
+    use tracing::{error, info};
+
+    use crate::database::get_db;
+    require_player().await?;
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        WaitlistPlayer,
+        r#"
+        SELECT 
+            p.player_id,
+            p.name,
+            p.is_goalkeeper,
+            ROW_NUMBER() OVER (
+                PARTITION BY p.is_goalkeeper
+                ORDER BY wg.joined_at
+            ) AS queue_position
+        FROM 
+            Player p
+        JOIN 
+            Waitlist_Gameday wg ON p.player_id = wg.player_id
+        WHERE 
+            wg.gameday_id = $1
+        ORDER BY 
+            wg.joined_at ASC
+        "#,
+        gameday_id
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(players) => {
+            info!("Successfully got waitlist for {:?}", gameday_id);
+            Ok(players)
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get waitlist for gameday.".to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(weekday: i16, season_start: &str, season_end: &str) -> GamedaySeries {
+        GamedaySeries {
+            series_id: 1,
+            weekday,
+            start_time: NaiveTime::from_hms_opt(19, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(20, 30, 0).unwrap(),
+            season_start: season_start.parse().unwrap(),
+            season_end: season_end.parse().unwrap(),
+            exceptions: Vec::new(),
+            max_skaters: 20,
+            max_goalkeepers: 2,
+            upcoming_count: None,
+        }
+    }
+
+    fn dates(dates: &[&str]) -> Vec<NaiveDate> {
+        dates.iter().map(|date| date.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn occurrences_start_on_the_season_start_and_include_the_end() {
+        // 2025-09-01 is a Monday
+        assert_eq!(
+            series(1, "2025-09-01", "2025-09-29").occurrences(),
+            dates(&[
+                "2025-09-01",
+                "2025-09-08",
+                "2025-09-15",
+                "2025-09-22",
+                "2025-09-29",
+            ])
+        );
+    }
+
+    #[test]
+    fn occurrences_start_on_the_first_weekday_after_the_season_start() {
+        assert_eq!(
+            series(1, "2025-09-03", "2025-09-28").occurrences(),
+            dates(&["2025-09-08", "2025-09-15", "2025-09-22"])
+        );
+        assert_eq!(
+            series(7, "2025-09-01", "2025-09-20").occurrences(),
+            dates(&["2025-09-07", "2025-09-14"])
+        );
+        assert_eq!(series(3, "2025-09-04", "2025-09-09").occurrences(), []);
+    }
+
+    #[test]
+    fn occurrences_skip_the_exceptions() {
+        let mut series = series(1, "2025-09-01", "2025-09-29");
+        series.exceptions = dates(&["2025-09-15", "2025-09-29", "2025-09-16"]);
+        assert_eq!(
+            series.occurrences(),
+            dates(&["2025-09-01", "2025-09-08", "2025-09-22"])
+        );
+    }
+}
@@ -1,26 +1,59 @@
 use leptos::prelude::*;
-use leptos_router::components::Redirect;
+use leptos_router::components::{Redirect, A};
 
-use crate::{auth::validate_admin, components::loading::Loading, models::Player};
+use std::cmp::Ordering;
+
+use crate::{
+    auth::user_from_session,
+    components::loading::Loading,
+    models::{AttendanceStats, Permission, RatedPlayer, Role},
+};
 
 #[component]
 pub fn AdminPage() -> impl IntoView {
-    let admin_check = Resource::new(
+    let role = Resource::new(
         || (),
-        |_| async move { validate_admin().await.unwrap_or(false) },
+        |_| async move {
+            user_from_session()
+                .await
+                .map(|player| player.role)
+                .unwrap_or_default()
+        },
     );
-    let add_admin = ServerAction::<AddAdmin>::new();
-    let remove_admin = ServerAction::<RemoveAdmin>::new();
-
-    let (invalidate_gamedays, set_invalidate_gamedays) = signal(false);
-    let players = Resource::new(|| (), |_| async move { get_all_players().await });
-
-    Effect::new(move || {
-        if invalidate_gamedays.get() {
-            players.refetch();
-            set_invalidate_gamedays.set(false);
+    let set_role = ServerAction::<SetRole>::new();
+    let set_skill = ServerAction::<SetSkill>::new();
+    let revoke_sessions = ServerAction::<RevokePlayerSessions>::new();
+    let players = Resource::new(
+        move || (set_role.version().get(), set_skill.version().get()),
+        |_| async move { get_all_players().await },
+    );
+    let attendance = Resource::new(|| (), |_| get_attendance_stats());
+    // Column and whether it is sorted descending
+    let sort = RwSignal::new((StatsColumn::NoShows, true));
+    let header = move |label: &'static str, column: StatsColumn| {
+        view! {
+            <th
+                class="cursor-pointer select-none"
+                on:click=move |_| {
+                    sort.update(|(current, descending)| {
+                        if *current == column {
+                            *descending = !*descending;
+                        } else {
+                            *current = column;
+                            *descending = column != StatsColumn::Name;
+                        }
+                    })
+                }
+            >
+                {label}
+                {move || match sort.get() {
+                    (current, true) if current == column => " ▼",
+                    (current, false) if current == column => " ▲",
+                    _ => "",
+                }}
+            </th>
         }
-    });
+    };
 
     view! {
         <Suspense fallback=move || {
@@ -32,110 +65,145 @@ pub fn AdminPage() -> impl IntoView {
             }
         }>
             {move || Suspend::new(async move {
-                let is_admin = admin_check.await;
+                let can_manage_players = role.await.has_permission(Permission::ManagePlayers);
                 view! {
-                    <Show when=move || { is_admin } fallback=|| view! { <Redirect path="/" /> }>
+                    <Show
+                        when=move || { can_manage_players }
+                        fallback=|| view! { <Redirect path="/" /> }
+                    >
                         <Transition fallback=move || view! { <Loading /> }>
-                            <ActionForm action=add_admin>
-                                <div class="flex flex-col items-center w-full">
-                                    <h3 class="text-center text-xl mt-2">
-                                        "Gör användare till admin"
-                                    </h3>
-                                    <select
-                                        class="select w-full max-w-xs select-bordered m-2"
-                                        name="player_id"
-                                    >
-                                        <option disabled selected>
-                                            "Välj användare"
-                                        </option>
-                                        {move || Suspend::new(async move {
-                                            let users = players.await.expect("No users found");
-                                            users
-                                                .into_iter()
-                                                .filter(|user| {
-                                                    user.access_group.as_deref() == Some("user")
-                                                })
-                                                .map(|user| {
-                                                    view! {
-                                                        <option value=user
-                                                            .player_id>{user.name}" ("{user.email}")"</option>
-                                                    }
-                                                })
-                                                .collect_view()
-                                        })}
-                                    </select>
-                                    <button type="submit" class="btn btn-success m-4">
-                                        "Lägg till admin"
-                                    </button>
-                                    <div class="divider" />
+                            <div class="flex flex-col w-full items-center">
+                                <div class="flex gap-2 mt-2">
+                                    <A href="/admin/audit" attr:class="btn btn-sm btn-primary">
+                                        "Händelselogg"
+                                    </A>
+                                    <A href="/admin/seasons" attr:class="btn btn-sm btn-primary">
+                                        "Säsonger och avgifter"
+                                    </A>
                                 </div>
-                            </ActionForm>
-                            <div class="flex w-full items-center justify-around">
-                                <div class="flex flex-col w-full items-center">
-                                    <h3 class="text-center text-xl mt-2">Admins</h3>
-                                    <ul class="flex flex-col items-center w-11/12">
-                                        {move || Suspend::new(async move {
-                                            let users = players.await.expect("No users found");
-                                            users
-                                                .into_iter()
-                                                .filter(|user| {
-                                                    user.access_group.as_deref() == Some("admin")
-                                                        || user.access_group.as_deref() == Some("super-admin")
-                                                })
-                                                .map(|user| {
-                                                    view! {
-                                                        <li class="flex justify-center my-2">
-                                                            <ActionForm action=remove_admin>
-                                                                <p>{user.name}</p>
-                                                                <button
-                                                                    type="submit"
-                                                                    class="btn btn-square btn-outline ml-2"
+                                <h3 class="text-center text-xl mt-2">"Användare och roller"</h3>
+                                <ul class="flex flex-col items-center w-11/12">
+                                    {move || Suspend::new(async move {
+                                        let users = players.await.unwrap_or_default();
+                                        users
+                                            .into_iter()
+                                            .map(|user| {
+                                                view! {
+                                                    <li class="flex flex-wrap items-center justify-center gap-2 my-2">
+                                                        <ActionForm action=set_role>
+                                                            <div class="flex items-center gap-2">
+                                                                <p>{user.name}" ("{user.email}")"</p>
+                                                                <input
+                                                                    type="hidden"
+                                                                    name="player_id"
                                                                     value=user.player_id
+                                                                />
+                                                                <select
+                                                                    class="select select-bordered select-sm"
+                                                                    name="role"
+                                                                >
+                                                                    {Role::ALL
+                                                                        .into_iter()
+                                                                        .map(|role| {
+                                                                            view! {
+                                                                                <option
+                                                                                    value=role.as_str()
+                                                                                    selected=role == user.role
+                                                                                >
+                                                                                    {role.label()}
+                                                                                </option>
+                                                                            }
+                                                                        })
+                                                                        .collect_view()}
+                                                                </select>
+                                                                <button type="submit" class="btn btn-sm btn-success">
+                                                                    Spara
+                                                                </button>
+                                                            </div>
+                                                        </ActionForm>
+                                                        <ActionForm action=set_skill>
+                                                            <div class="flex items-center gap-2">
+                                                                <input
+                                                                    type="hidden"
                                                                     name="player_id"
+                                                                    value=user.player_id
+                                                                />
+                                                                <label for=format!("skill-{}", user.player_id)>
+                                                                    "Nivå"
+                                                                </label>
+                                                                <select
+                                                                    id=format!("skill-{}", user.player_id)
+                                                                    class="select select-bordered select-sm"
+                                                                    name="skill"
                                                                 >
-                                                                    <svg
-                                                                        xmlns="http://www.w3.org/2000/svg"
-                                                                        class="h-4 w-4"
-                                                                        fill="none"
-                                                                        viewBox="0 0 24 24"
-                                                                        stroke="currentColor"
-                                                                    >
-                                                                        <path
-                                                                            stroke-linecap="round"
-                                                                            stroke-linejoin="round"
-                                                                            stroke-width="2"
-                                                                            d="M6 18L18 6M6 6l12 12"
-                                                                        />
-                                                                    </svg>
+                                                                    {RatedPlayer::SKILL_LEVELS
+                                                                        .into_iter()
+                                                                        .map(|skill| {
+                                                                            view! {
+                                                                                <option value=skill selected=skill == user.skill>
+                                                                                    {skill}
+                                                                                </option>
+                                                                            }
+                                                                        })
+                                                                        .collect_view()}
+                                                                </select>
+                                                                <button type="submit" class="btn btn-sm btn-success">
+                                                                    Spara
                                                                 </button>
-                                                            </ActionForm>
-                                                        </li>
-                                                    }
+                                                            </div>
+                                                        </ActionForm>
+                                                        <ActionForm action=revoke_sessions>
+                                                            <input
+                                                                type="hidden"
+                                                                name="player_id"
+                                                                value=user.player_id
+                                                            />
+                                                            <button type="submit" class="btn btn-sm btn-warning">
+                                                                "Logga ut överallt"
+                                                            </button>
+                                                        </ActionForm>
+                                                    </li>
+                                                }
+                                            })
+                                            .collect_view()
+                                    })}
+                                </ul>
+                                <h3 class="text-center text-xl mt-6">"Närvaro"</h3>
+                                <div class="overflow-x-auto w-11/12">
+                                    <table class="table table-sm">
+                                        <thead>
+                                            <tr>
+                                                {header("Namn", StatsColumn::Name)}
+                                                {header("Speldagar", StatsColumn::Registered)}
+                                                {header("Närvaro", StatsColumn::Attendance)}
+                                                {header("Uteblev", StatsColumn::NoShows)}
+                                                {header("Sena avanmälningar", StatsColumn::LateCancellations)}
+                                            </tr>
+                                        </thead>
+                                        <tbody>
+                                            {move || {
+                                                let (column, descending) = sort.get();
+                                                Suspend::new(async move {
+                                                    let mut stats = attendance.await.unwrap_or_default();
+                                                    sort_stats(&mut stats, column, descending);
+                                                    stats
+                                                        .into_iter()
+                                                        .map(|row| {
+                                                            view! {
+                                                                <tr>
+                                                                    <td>{row.name.clone()}</td>
+                                                                    <td>{row.registered}</td>
+                                                                    <td>{row.attendance_percent()}</td>
+                                                                    <td>{row.no_shows}</td>
+                                                                    <td>{row.late_cancellations}</td>
+                                                                </tr>
+                                                            }
+                                                        })
+                                                        .collect_view()
                                                 })
-                                                .collect_view()
-                                        })}
-                                    </ul>
-                                </div>
-                                <div class="flex flex-col w-full items-center">
-                                    <h3 class="text-center text-xl mt-2">Users</h3>
-                                    <ul class="flex flex-col items-center w-11/12">
-                                        {move || Suspend::new(async move {
-                                            let users = players.await.expect("No users found");
-                                            users
-                                                .into_iter()
-                                                .filter(|user| {
-                                                    user.access_group.as_deref() == Some("user")
-                                                })
-                                                .map(|user| {
-                                                    view! {
-                                                        <li class="flex justify-center my-2">
-                                                            <p>{user.name}</p>
-                                                        </li>
-                                                    }
-                                                })
-                                                .collect_view()
-                                        })}
-                                    </ul>
+                                            }}
+                                        </tbody>
+                                    </table>
                                 </div>
                             </div>
                         </Transition>
@@ -146,23 +214,76 @@ pub fn AdminPage() -> impl IntoView {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatsColumn {
+    Name,
+    Registered,
+    Attendance,
+    NoShows,
+    LateCancellations,
+}
+
+fn sort_stats(stats: &mut [AttendanceStats], column: StatsColumn, descending: bool) {
+    stats.sort_by(|a, b| {
+        let ordering = match column {
+            StatsColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            StatsColumn::Registered => a.registered.cmp(&b.registered),
+            StatsColumn::Attendance => a
+                .attendance_rate()
+                .partial_cmp(&b.attendance_rate())
+                .unwrap_or(Ordering::Equal),
+            StatsColumn::NoShows => a.no_shows.cmp(&b.no_shows),
+            StatsColumn::LateCancellations => a.late_cancellations.cmp(&b.late_cancellations),
+        };
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+#[server]
+async fn get_attendance_stats() -> Result<Vec<AttendanceStats>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::models::attendance_stats;
+    use tracing::error;
+
+    require_permission(Permission::ManagePlayers).await?;
+
+    match attendance_stats(None).await {
+        Ok(stats) => Ok(stats),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get attendance.".to_string(),
+            ))
+        }
+    }
+}
+
 #[server]
-async fn get_all_players() -> Result<Vec<Player>, ServerFnError> {
+async fn get_all_players() -> Result<Vec<RatedPlayer>, ServerFnError> {
+    use crate::auth::require_permission;
     use crate::database::get_db;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
-    }
+    require_permission(Permission::ManagePlayers).await?;
 
     let pool = get_db();
     match sqlx::query_as!(
-        Player,
+        RatedPlayer,
         r#"
         SELECT 
-            *
+            player_id,
+            name,
+            email,
+            role as "role: _",
+            skill
         FROM 
             player
+        ORDER BY
+            name
         "#
     )
     .fetch_all(pool)
@@ -182,68 +303,185 @@ async fn get_all_players() -> Result<Vec<Player>, ServerFnError> {
 }
 
 #[server]
-async fn add_admin(player_id: i32) -> Result<(), ServerFnError> {
+async fn set_role(player_id: i32, role: Role) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_admin;
     use crate::database::get_db;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
+    let admin = require_admin().await?;
+    // Keeps at least the current admin around
+    if admin.player_id == player_id && role != Role::Admin {
+        return Err(ServerFnError::ServerError(
+            "You can't remove your own admin role.".to_string(),
+        ));
     }
 
-    let pool = get_db();
-    match sqlx::query!(
-        r#"
-        UPDATE player
-        SET access_group = 'admin'
-        WHERE player_id = $1
-        "#,
-        player_id
-    )
-    .execute(pool)
-    .await
-    {
+    let updated = async {
+        let mut tx = get_db().begin().await?;
+        let before = sqlx::query_scalar!(
+            r#"
+            SELECT role as "role: Role"
+            FROM player
+            WHERE player_id = $1
+            FOR UPDATE
+            "#,
+            player_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(before) = before.filter(|before| *before != role) {
+            sqlx::query!(
+                r#"
+                UPDATE player
+                SET role = $1
+                WHERE player_id = $2
+                "#,
+                role as Role,
+                player_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            record_event(
+                &mut *tx,
+                admin.player_id,
+                "player.set_role",
+                &format!("player:{}", player_id),
+                Some(serde_json::json!({ "role": before })),
+                Some(serde_json::json!({ "role": role })),
+            )
+            .await?;
+        }
+        tx.commit().await
+    }
+    .await;
+
+    match updated {
         Ok(_) => {
-            info!("Successfully added admin.");
+            info!("Player {:?} is now {:?}.", player_id, role);
             Ok(())
         }
         Err(e) => {
             error!("Database error: {:?}", e);
             Err(ServerFnError::ServerError(
-                "Failed to add admin.".to_string(),
+                "Failed to set role.".to_string(),
             ))
         }
     }
 }
 
+/// Sets how good a player is, 1 to 5. Only used to balance the teams.
 #[server]
-async fn remove_admin(player_id: i32) -> Result<(), ServerFnError> {
+async fn set_skill(player_id: i32, skill: i16) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
     use crate::database::get_db;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
+    let admin = require_permission(Permission::ManagePlayers).await?;
+    if !RatedPlayer::SKILL_LEVELS.contains(&skill) {
+        return Err(ServerFnError::ServerError(
+            "The skill must be between 1 and 5.".to_string(),
+        ));
     }
 
-    let pool = get_db();
-    match sqlx::query!(
-        r#"
-        UPDATE player
-        SET access_group = 'user'
-        WHERE player_id = $1
-        "#,
-        player_id
-    )
-    .execute(pool)
-    .await
-    {
+    let updated = async {
+        let mut tx = get_db().begin().await?;
+        let before = sqlx::query_scalar!(
+            r#"
+            SELECT skill
+            FROM player
+            WHERE player_id = $1
+            FOR UPDATE
+            "#,
+            player_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(before) = before.filter(|before| *before != skill) {
+            sqlx::query!(
+                r#"
+                UPDATE player
+                SET skill = $1
+                WHERE player_id = $2
+                "#,
+                skill,
+                player_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            record_event(
+                &mut *tx,
+                admin.player_id,
+                "player.set_skill",
+                &format!("player:{}", player_id),
+                Some(serde_json::json!({ "skill": before })),
+                Some(serde_json::json!({ "skill": skill })),
+            )
+            .await?;
+        }
+        tx.commit().await
+    }
+    .await;
+
+    match updated {
         Ok(_) => {
-            info!("Successfully removed admin.");
+            info!("Player {:?} now has skill {:?}.", player_id, skill);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to set skill.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Ends every session and removes the API tokens of a player, e.g. when an account is
+/// compromised.
+#[server]
+async fn revoke_player_sessions(player_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::{delete_player_sessions, require_permission};
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+
+    let revoked = async {
+        let mut tx = get_db().begin().await?;
+        let (sessions, api_tokens) = delete_player_sessions(&mut *tx, player_id).await?;
+        record_event(
+            &mut *tx,
+            admin.player_id,
+            "player.revoke_sessions",
+            &format!("player:{}", player_id),
+            Some(serde_json::json!({ "sessions": sessions, "api_tokens": api_tokens })),
+            Some(serde_json::json!({ "sessions": 0, "api_tokens": 0 })),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>((sessions, api_tokens))
+    }
+    .await;
+
+    match revoked {
+        Ok((sessions, api_tokens)) => {
+            info!(
+                "Player {:?} logged out {} sessions and removed {} API tokens of player {:?}.",
+                admin.player_id, sessions, api_tokens, player_id
+            );
             Ok(())
         }
         Err(e) => {
             error!("Database error: {:?}", e);
             Err(ServerFnError::ServerError(
-                "Failed to remove admin.".to_string(),
+                "Failed to revoke sessions.".to_string(),
             ))
         }
     }
@@ -0,0 +1,245 @@
+use leptos::prelude::*;
+use leptos_router::components::{Redirect, A};
+
+use crate::{
+    auth::user_from_session,
+    components::loading::Loading,
+    models::{AuditEvent, AuditFilter, Permission},
+    timezone::to_club_time,
+};
+
+/// The audit log, newest first, filtered on action, actor and target.
+#[component]
+pub fn AuditPage() -> impl IntoView {
+    let role = Resource::new(
+        || (),
+        |_| async move {
+            user_from_session()
+                .await
+                .map(|player| player.role)
+                .unwrap_or_default()
+        },
+    );
+    let filter = RwSignal::new(AuditFilter::default());
+    let page = RwSignal::new(0i64);
+    let actions = Resource::new(|| (), |_| get_audit_actions());
+    let events = Resource::new(
+        move || (filter.get(), page.get()),
+        |(filter, page)| async move { get_audit_events(filter, page).await },
+    );
+    // Changing a filter starts over from the newest events
+    let update = move |set: fn(&mut AuditFilter, String), value: String| {
+        filter.update(|filter| set(filter, value));
+        page.set(0);
+    };
+
+    view! {
+        <Suspense fallback=move || view! { <Loading /> }>
+            {move || Suspend::new(async move {
+                let can_manage_players = role.await.has_permission(Permission::ManagePlayers);
+                let actions = actions.await.unwrap_or_default();
+                view! {
+                    <Show
+                        when=move || can_manage_players
+                        fallback=|| view! { <Redirect path="/" /> }
+                    >
+                        <div class="flex flex-col w-full items-center">
+                            <h3 class="text-center text-xl mt-2">"Händelselogg"</h3>
+                            <A href="/admin" attr:class="link text-sm">
+                                "Tillbaka till admin"
+                            </A>
+                            <div class="flex flex-wrap justify-center gap-2 m-2">
+                                <select
+                                    class="select select-bordered select-sm"
+                                    on:change=move |ev| {
+                                        update(
+                                            |filter, value| filter.action = value,
+                                            event_target_value(&ev),
+                                        )
+                                    }
+                                >
+                                    <option value="">"Alla händelser"</option>
+                                    {actions
+                                        .clone()
+                                        .into_iter()
+                                        .map(|action| {
+                                            view! { <option value=action.clone()>{action.clone()}</option> }
+                                        })
+                                        .collect_view()}
+                                </select>
+                                <input
+                                    type="search"
+                                    placeholder="Utförd av"
+                                    class="input input-bordered input-sm"
+                                    on:input=move |ev| {
+                                        update(
+                                            |filter, value| filter.actor = value,
+                                            event_target_value(&ev),
+                                        )
+                                    }
+                                />
+                                <input
+                                    type="search"
+                                    placeholder="Mål, t.ex. gameday:12"
+                                    class="input input-bordered input-sm"
+                                    on:input=move |ev| {
+                                        update(
+                                            |filter, value| filter.target = value,
+                                            event_target_value(&ev),
+                                        )
+                                    }
+                                />
+                            </div>
+                            <Transition fallback=move || view! { <Loading /> }>
+                                {move || Suspend::new(async move {
+                                    let events = events.await.unwrap_or_default();
+                                    let full_page = events.len() as i64 == AuditEvent::PAGE_SIZE;
+                                    view! {
+                                        <div class="overflow-x-auto w-11/12">
+                                            <table class="table table-sm">
+                                                <thead>
+                                                    <tr>
+                                                        <th>"Tid"</th>
+                                                        <th>"Utförd av"</th>
+                                                        <th>"Händelse"</th>
+                                                        <th>"Mål"</th>
+                                                        <th>"Före"</th>
+                                                        <th>"Efter"</th>
+                                                    </tr>
+                                                </thead>
+                                                <tbody>
+                                                    {events
+                                                        .into_iter()
+                                                        .map(|event| {
+                                                            view! {
+                                                                <tr>
+                                                                    <td class="whitespace-nowrap">
+                                                                        {to_club_time(event.created_at)
+                                                                            .format("%Y-%m-%d %H:%M")
+                                                                            .to_string()}
+                                                                    </td>
+                                                                    <td>{event.actor_name.unwrap_or("-".to_string())}</td>
+                                                                    <td>{event.action}</td>
+                                                                    <td>{event.target}</td>
+                                                                    <td class="font-mono text-xs break-all">
+                                                                        {event.before}
+                                                                    </td>
+                                                                    <td class="font-mono text-xs break-all">
+                                                                        {event.after}
+                                                                    </td>
+                                                                </tr>
+                                                            }
+                                                        })
+                                                        .collect_view()}
+                                                </tbody>
+                                            </table>
+                                        </div>
+                                        <div class="join m-4">
+                                            <button
+                                                class="join-item btn btn-sm"
+                                                disabled=move || page.get() == 0
+                                                on:click=move |_| page.update(|page| *page -= 1)
+                                            >
+                                                "Nyare"
+                                            </button>
+                                            <button class="join-item btn btn-sm">
+                                                {move || format!("Sida {}", page.get() + 1)}
+                                            </button>
+                                            <button
+                                                class="join-item btn btn-sm"
+                                                disabled=!full_page
+                                                on:click=move |_| page.update(|page| *page += 1)
+                                            >
+                                                "Äldre"
+                                            </button>
+                                        </div>
+                                    }
+                                })}
+                            </Transition>
+                        </div>
+                    </Show>
+                }
+            })}
+        </Suspense>
+    }
+}
+
+#[server]
+async fn get_audit_events(
+    filter: AuditFilter,
+    page: i64,
+) -> Result<Vec<AuditEvent>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::error;
+
+    require_permission(Permission::ManagePlayers).await?;
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        AuditEvent,
+        r#"
+        SELECT
+            a.audit_event_id,
+            p.name as "actor_name?",
+            a.action,
+            a.target,
+            a.before::text as before,
+            a.after::text as after,
+            a.created_at
+        FROM audit_event a
+        LEFT JOIN player p ON a.actor_id = p.player_id
+        WHERE ($1 = '' OR a.action = $1)
+        AND ($2 = '' OR p.name ILIKE '%' || $2 || '%')
+        AND ($3 = '' OR a.target ILIKE '%' || $3 || '%')
+        ORDER BY a.created_at DESC, a.audit_event_id DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        filter.action.trim(),
+        filter.actor.trim(),
+        filter.target.trim(),
+        AuditEvent::PAGE_SIZE,
+        page.max(0) * AuditEvent::PAGE_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(events) => Ok(events),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get audit events.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Every action in the log, for the filter.
+#[server]
+async fn get_audit_actions() -> Result<Vec<String>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::error;
+
+    require_permission(Permission::ManagePlayers).await?;
+
+    let pool = get_db();
+    match sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT action
+        FROM audit_event
+        ORDER BY action
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(actions) => Ok(actions),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get audit actions.".to_string(),
+            ))
+        }
+    }
+}
@@ -1,23 +1,25 @@
 use leptos::{prelude::*, task::spawn_local};
 use leptos_router::hooks::use_query_map;
-use std::env;
-use uuid::Uuid;
 
 #[cfg(feature = "ssr")]
-use tower_cookies::Cookie;
-
+use crate::auth::SessionClient;
+#[cfg(feature = "ssr")]
 use crate::models::{Player, UserInfo};
+#[cfg(feature = "ssr")]
+use tower_cookies::Cookies;
+#[cfg(feature = "ssr")]
+use uuid::Uuid;
 
 #[component]
 pub fn AuthPage() -> impl IntoView {
     let query = use_query_map();
 
     Effect::new(move |_| {
-        let id_token = query.read().get("code");
+        let code = query.read().get("code");
         let csrf_token = query.read().get("state");
-        if id_token.is_some() && csrf_token.is_some() {
+        if code.is_some() && csrf_token.is_some() {
             spawn_local(async {
-                let _ = set_loggin_session(csrf_token.unwrap(), id_token.unwrap()).await;
+                let _ = set_loggin_session(csrf_token.unwrap(), code.unwrap()).await;
             });
         }
     });
@@ -30,90 +32,132 @@ pub fn AuthPage() -> impl IntoView {
     }
 }
 
-//TODO: encrypt cookie
 #[server]
-async fn set_loggin_session(csrf_token: String, id_token: String) -> Result<(), ServerFnError> {
-    use crate::{auth::get_pkce_verifier, models::UserInfo};
-    use http::{header, HeaderValue};
-    use oauth2::{
-        basic::BasicClient, reqwest::async_http_client, AuthUrl, AuthorizationCode, ClientId,
-        ClientSecret, PkceCodeVerifier, RedirectUrl, TokenResponse, TokenUrl,
+async fn set_loggin_session(csrf_token: String, code: String) -> Result<(), ServerFnError> {
+    use crate::auth::{
+        delete_session, get_pkce_verifier, read_session_cookie, session_client, set_session_cookie,
+        AuthClient,
     };
-    use tracing::info;
+    use leptos_axum::extract;
+    use openidconnect::{
+        core::CoreUserInfoClaims, reqwest::async_http_client, AuthorizationCode,
+        ConfigurationError, Nonce, OAuth2TokenResponse, PkceCodeVerifier,
+    };
+    use tracing::{error, info};
 
     info!("Getting pkce verifier");
     if let Some(pkcestore) = get_pkce_verifier(csrf_token).await? {
-        info!("Creating Oath client");
-        //TODO: Make this global resource
-        let client = BasicClient::new(
-            ClientId::new(env::var("OAUTH_CLIENT_ID")?),
-            Some(ClientSecret::new(env::var("OAUTH_CLIENT_SECRET")?)),
-            AuthUrl::new(env::var("OAUTH_AUTH_URL")?)?,
-            Some(TokenUrl::new(env::var("OAUTH_TOKEN_URL")?)?),
-        )
-        // Set the URL the user will be redirected to after the authorization process.
-        .set_redirect_uri(RedirectUrl::new(env::var("OAUTH_REDIRECT_URL")?)?);
-
-        info!("Getting access token");
+        let auth_client = expect_context::<AuthClient>();
+        let client = auth_client.client().await;
+
+        info!("Getting tokens");
         let token_result = client
-            .exchange_code(AuthorizationCode::new(id_token))
+            .exchange_code(AuthorizationCode::new(code))
             // Set the PKCE code verifier.
             .set_pkce_verifier(PkceCodeVerifier::new(pkcestore.pkce_verifier))
             .request_async(async_http_client)
             .await?;
 
-        info!("Getting userinfo");
-        let client = reqwest::Client::new();
-        let userinfo = client
-            .get("https://dev-6368dhsgrpcts8kr.eu.auth0.com/userinfo")
-            .bearer_auth(token_result.access_token().secret())
-            .send()
-            .await?
-            .json::<UserInfo>()
-            .await?;
+        let claims = auth_client
+            .verify_id_token(&token_result, &Nonce::new(pkcestore.nonce))
+            .await
+            .inspect_err(|e| error!("{}", e))
+            .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        // Providers without a userinfo endpoint have to put the profile in the ID token
+        let userinfo = match client.user_info(
+            token_result.access_token().to_owned(),
+            Some(claims.subject().clone()),
+        ) {
+            Ok(request) => {
+                info!("Getting userinfo");
+                let claims: CoreUserInfoClaims = request.request_async(async_http_client).await?;
+                UserInfo {
+                    name: localized(claims.name()),
+                    given_name: localized(claims.given_name()),
+                    family_name: localized(claims.family_name()),
+                    email: claims
+                        .email()
+                        .map(|email| email.to_string())
+                        .ok_or_else(|| ServerFnError::new("No email in userinfo"))?,
+                }
+            }
+            Err(ConfigurationError::MissingUrl(_)) => UserInfo {
+                name: localized(claims.name()),
+                given_name: localized(claims.given_name()),
+                family_name: localized(claims.family_name()),
+                email: claims
+                    .email()
+                    .map(|email| email.to_string())
+                    .ok_or_else(|| ServerFnError::new("No email in ID token"))?,
+            },
+            Err(e) => return Err(e.into()),
+        };
 
         info!("userinfo{:?}", userinfo);
-        let response = expect_context::<leptos_axum::ResponseOptions>();
-
-        if let Some(player) = get_player_by_email(userinfo.email.clone()).await? {
-            info!("player exist{:?}", player);
-            let session = insert_session(player.player_id).await?;
-            let cookie = create_cookie(session);
-            if let Ok(cookie) = HeaderValue::from_str(&cookie.to_string()) {
-                response.insert_header(header::SET_COOKIE, cookie);
+        let cookies = extract::<Cookies>().await?;
+
+        let player = match get_player_by_email(userinfo.email.clone()).await? {
+            Some(player) => {
+                info!("player exist{:?}", player);
+                player
             }
-        } else {
-            let player = insert_player(userinfo).await?;
-            info!("player inserted: {:?}", player);
-            let session = insert_session(player.player_id).await?;
-            let cookie = create_cookie(session);
-            if let Ok(cookie) = HeaderValue::from_str(&cookie.to_string()) {
-                response.insert_header(header::SET_COOKIE, cookie);
+            None => {
+                let player = insert_player(userinfo).await?;
+                info!("player inserted: {:?}", player);
+                player
             }
+        };
+
+        // A new session id on every login, the one the browser had before is dropped
+        if let Some(previous) = read_session_cookie(&cookies) {
+            delete_session(previous.session_id).await?;
         }
+        let session =
+            insert_session(player.player_id, pkcestore.remember, session_client().await).await?;
+        set_session_cookie(&cookies, session, pkcestore.remember);
 
         leptos_axum::redirect("/");
     }
     Ok(())
 }
 
-#[server]
-async fn insert_session(player_id: i32) -> Result<Uuid, ServerFnError> {
+/// Picks the default language of a claim that may be localized.
+#[cfg(feature = "ssr")]
+fn localized<T: std::ops::Deref<Target = String>>(
+    claim: Option<&openidconnect::LocalizedClaim<T>>,
+) -> String {
+    claim
+        .and_then(|claim| claim.get(None))
+        .map(|value| value.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "ssr")]
+async fn insert_session(
+    player_id: i32,
+    remember: bool,
+    client: SessionClient,
+) -> Result<Uuid, ServerFnError> {
+    use crate::auth::session_ttl;
     use crate::database::get_db;
     use chrono::Utc;
     use tracing::{error, info};
 
     let pool = get_db();
     let session_id = uuid::Uuid::new_v4();
-    let expires_at = Utc::now() + chrono::Duration::days(1); // Set expiration to 1 day from now
+    let expires_at = Utc::now() + session_ttl(remember);
 
     match sqlx::query!(
         r#"
-        INSERT INTO session (session_id, player_id, created_at, expires_at)
-        VALUES ($1, $2, NOW(), $3)
+        INSERT INTO session (session_id, player_id, remember, user_agent, ip, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, NOW(), $6)
         "#,
         session_id,
         player_id,
+        remember,
+        client.user_agent,
+        client.ip,
         expires_at
     )
     .execute(pool)
@@ -136,19 +180,6 @@ async fn insert_session(player_id: i32) -> Result<Uuid, ServerFnError> {
 }
 
 #[cfg(feature = "ssr")]
-fn create_cookie(uuid: Uuid) -> Cookie<'static> {
-    use tower_cookies::cookie::{time::Duration, Cookie, SameSite};
-    let cookie: Cookie = Cookie::build(("session_id", uuid.to_string()))
-        .path("/")
-        .secure(true)
-        .http_only(true)
-        .max_age(Duration::days(1))
-        .same_site(SameSite::None)
-        .build();
-    cookie
-}
-
-#[server]
 async fn insert_player(userinfo: UserInfo) -> Result<Player, ServerFnError> {
     use crate::database::get_db;
     use tracing::{error, info};
@@ -158,15 +189,14 @@ async fn insert_player(userinfo: UserInfo) -> Result<Player, ServerFnError> {
     match sqlx::query_as!(
         Player,
         r#"
-        INSERT INTO player (name, given_name, family_name, email, access_group)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING player_id, name, given_name, family_name, email, access_group, is_goalkeeper
+        INSERT INTO player (name, given_name, family_name, email)
+        VALUES ($1, $2, $3, $4)
+        RETURNING player_id, name, given_name, family_name, email, role as "role: _", position as "position: _"
         "#,
         userinfo.name,
         userinfo.given_name,
         userinfo.family_name,
-        userinfo.email,
-        "user"
+        userinfo.email
     )
     .fetch_one(pool)
     .await
@@ -184,7 +214,7 @@ async fn insert_player(userinfo: UserInfo) -> Result<Player, ServerFnError> {
     }
 }
 
-#[server]
+#[cfg(feature = "ssr")]
 async fn get_player_by_email(email: String) -> Result<Option<Player>, ServerFnError> {
     use crate::database::get_db;
     use tracing::error;
@@ -194,7 +224,7 @@ async fn get_player_by_email(email: String) -> Result<Option<Player>, ServerFnEr
     match sqlx::query_as!(
         Player,
         r#"
-        SELECT p.player_id, p.name, p.given_name, p.family_name, p.email, p.access_group, p.is_goalkeeper
+        SELECT p.player_id, p.name, p.given_name, p.family_name, p.email, p.role as "role: _", p.position as "position: _"
         FROM player p
         WHERE p.email = $1
         "#,
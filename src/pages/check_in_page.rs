@@ -0,0 +1,175 @@
+use leptos::prelude::*;
+use leptos_router::{components::Redirect, hooks::use_params, params::Params};
+
+use crate::{
+    components::{
+        guests::get_guests_by_gameday,
+        loading::Loading,
+        not_found::NotFound,
+        roster::{
+            AttendanceButtons, GuestAttendanceButtons, MarkRemainingAttended, SetAttendance,
+            SetGuestAttendance,
+        },
+    },
+    models::{get_players_by_gameday, Permission, Player},
+    pages::day_page::get_gameday_by_id,
+    timezone::to_club_time,
+};
+
+/// Check-in for organizers at the rink, one row per registered player and guest.
+#[component]
+pub fn CheckInPage() -> impl IntoView {
+    let params = use_params::<CheckInParam>();
+    let id = move || {
+        params
+            .read()
+            .as_ref()
+            .ok()
+            .and_then(|params| params.id)
+            .unwrap_or(0)
+    };
+
+    let player =
+        use_context::<Resource<Result<Player, ServerFnError>>>().expect("player context not found");
+
+    let set_attendance = ServerAction::<SetAttendance>::new();
+    let set_guest_attendance = ServerAction::<SetGuestAttendance>::new();
+    let mark_remaining = ServerAction::<MarkRemainingAttended>::new();
+    let gameday = Resource::new(id, |id| async move { get_gameday_by_id(id).await });
+    let players = Resource::new(
+        move || {
+            (
+                id(),
+                set_attendance.version().get(),
+                mark_remaining.version().get(),
+            )
+        },
+        |(id, _, _)| async move { get_players_by_gameday(id).await },
+    );
+    let guests = Resource::new(
+        move || {
+            (
+                id(),
+                set_guest_attendance.version().get(),
+                mark_remaining.version().get(),
+            )
+        },
+        |(id, _, _)| async move { get_guests_by_gameday(id).await },
+    );
+
+    view! {
+        <div class="flex flex-col w-full items-center">
+            <Suspense fallback=move || view! { <Loading /> }>
+                {move || Suspend::new(async move {
+                    let can_manage_roster = player
+                        .await
+                        .is_ok_and(|player| player.role.has_permission(Permission::ManageRoster));
+                    view! {
+                        <Show
+                            when=move || can_manage_roster
+                            fallback=|| view! { <Redirect path="/" /> }
+                        >
+                            <Transition fallback=move || view! { <Loading /> }>
+                                {move || Suspend::new(async move {
+                                    match gameday.await {
+                                        Ok(gameday) => {
+                                            view! {
+                                                <h2 class="text-center text-2xl mt-4">
+                                                    {format!(
+                                                        "Incheckning {}",
+                                                        to_club_time(gameday.start_date).format("%Y-%m-%d %H:%M"),
+                                                    )}
+                                                </h2>
+                                            }
+                                                .into_any()
+                                        }
+                                        Err(_) => view! { <NotFound /> }.into_any(),
+                                    }
+                                })}
+                            </Transition>
+                            <Transition fallback=move || view! { <Loading /> }>
+                                {move || Suspend::new(async move {
+                                    let players = players.await.unwrap_or_default();
+                                    let guests = guests.await.unwrap_or_default();
+                                    let checked = players
+                                        .iter()
+                                        .map(|player| player.attendance)
+                                        .chain(guests.iter().map(|guest| guest.attendance))
+                                        .filter(Option::is_some)
+                                        .count();
+                                    let total = players.len() + guests.len();
+                                    view! {
+                                        <p class="text-center m-2">
+                                            {format!("{} av {} incheckade", checked, total)}
+                                        </p>
+                                        <ul class="flex flex-col w-11/12 max-w-md">
+                                            {players
+                                                .into_iter()
+                                                .map(|player| {
+                                                    view! {
+                                                        <li class="flex items-center justify-between border-b py-2">
+                                                            <p>
+                                                                {player.name}
+                                                                {player.is_goalkeeper.then_some(" (MV)")}
+                                                            </p>
+                                                            <div class="flex gap-2">
+                                                                <AttendanceButtons
+                                                                    gameday_id=id()
+                                                                    player_id=player.player_id
+                                                                    attendance=player.attendance
+                                                                    action=set_attendance
+                                                                    large=true
+                                                                />
+                                                            </div>
+                                                        </li>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                            {guests
+                                                .into_iter()
+                                                .map(|guest| {
+                                                    view! {
+                                                        <li class="flex items-center justify-between border-b py-2">
+                                                            <p>
+                                                                {guest.name}
+                                                                {guest.is_goalkeeper.then_some(" (MV)")}
+                                                                <span class="text-sm">
+                                                                    {format!(" gäst till {}", guest.host_name)}
+                                                                </span>
+                                                            </p>
+                                                            <div class="flex gap-2">
+                                                                <GuestAttendanceButtons
+                                                                    guest_id=guest.guest_id
+                                                                    attendance=guest.attendance
+                                                                    action=set_guest_attendance
+                                                                    large=true
+                                                                />
+                                                            </div>
+                                                        </li>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </ul>
+                                        <Show when=move || { checked < total }>
+                                            <ActionForm action=mark_remaining>
+                                                <input type="hidden" name="gameday_id" value=id() />
+                                                <button type="submit" class="btn btn-success m-4">
+                                                    "Markera övriga som kom"
+                                                </button>
+                                            </ActionForm>
+                                        </Show>
+                                    }
+                                })}
+                            </Transition>
+                        </Show>
+                    }
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[derive(Params, PartialEq)]
+struct CheckInParam {
+    id: Option<i32>,
+}
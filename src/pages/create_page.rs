@@ -2,19 +2,24 @@ use leptos::prelude::*;
 use leptos_router::components::Redirect;
 
 use crate::{
-    auth::validate_admin,
+    auth::user_from_session,
     components::{
         cup_card::CupCard, cup_form::CupForm, date_picker::DatePicker, event_tab::EventTab,
-        gameday_create::GamedayCreate, loading::Loading,
+        gameday_create::GamedayCreate, loading::Loading, series_card::SeriesCard,
     },
-    models::{Cup, Gameday},
+    models::{Cup, Gameday, GamedaySeries, Permission},
 };
 
 #[component]
 pub fn CreatePage() -> impl IntoView {
-    let admin_check = Resource::new(
+    let role = Resource::new(
         || (),
-        |_| async move { validate_admin().await.unwrap_or(false) },
+        |_| async move {
+            user_from_session()
+                .await
+                .map(|player| player.role)
+                .unwrap_or_default()
+        },
     );
 
     let (invalidate_gamedays, set_invalidate_gamedays) = signal(false);
@@ -23,10 +28,12 @@ pub fn CreatePage() -> impl IntoView {
     let (show_create_cup, set_show_create_cup) = signal(false);
     let gamedays = Resource::new(|| (), |_| async move { get_all_gamedays().await });
     let cups = Resource::new(|| (), |_| async move { get_all_cups().await });
+    let series = Resource::new(|| (), |_| async move { get_all_series().await });
 
     Effect::new(move || {
         if invalidate_gamedays.get() {
             gamedays.refetch();
+            series.refetch();
             set_invalidate_gamedays.set(false);
         }
     });
@@ -41,9 +48,14 @@ pub fn CreatePage() -> impl IntoView {
             }
         }>
             {move || Suspend::new(async move {
-                let is_admin = admin_check.await;
+                let role = role.await;
+                let can_create_gameday = role.has_permission(Permission::CreateGameday);
+                let can_edit_cup = role.has_permission(Permission::EditCup);
                 view! {
-                    <Show when=move || { is_admin } fallback=|| view! { <Redirect path="/" /> }>
+                    <Show
+                        when=move || { can_create_gameday || can_edit_cup }
+                        fallback=|| view! { <Redirect path="/" /> }
+                    >
                         <div class="flex flex-col w-full items-center relative">
                             <div class="dropdown">
                                 <div tabindex="0" role="button" class="btn m-1">
@@ -53,14 +65,14 @@ pub fn CreatePage() -> impl IntoView {
                                     tabindex="0"
                                     class="dropdown-content menu bg-base-100 rounded-box z-[1] w-52 p-2 shadow"
                                 >
-                                    <li>
+                                    <li class:hidden=!can_create_gameday>
                                         <a on:click=move |_| {
                                             set_show_create_day.set(!show_create_day.get());
                                             set_show_create_cup.set(false);
                                             set_tab_change.set(true);
                                         }>Speldag</a>
                                     </li>
-                                    <li>
+                                    <li class:hidden=!can_edit_cup>
                                         <a on:click=move |_| {
                                             set_show_create_cup.set(!show_create_cup.get());
                                             set_show_create_day.set(false);
@@ -84,12 +96,12 @@ pub fn CreatePage() -> impl IntoView {
                                             <h3 class="text-center text-xl mt-2">Kommande cupper</h3>
                                             <ul class="flex flex-col items-center w-11/12">
                                                 {move || Suspend::new(async move {
-                                                    let cups = cups.await.expect("No cups found");
+                                                    let cups = cups.await.unwrap_or_default();
                                                     cups.into_iter()
                                                         .map(|cup| {
                                                             view! {
                                                                 <li class="flex justify-center my-2">
-                                                                    <CupCard cup edit_button=true />
+                                                                    <CupCard cup edit_button=can_edit_cup />
                                                                 </li>
                                                             }
                                                         })
@@ -100,11 +112,31 @@ pub fn CreatePage() -> impl IntoView {
                                     }
                                 }
                             >
+                                <Transition fallback=move || view! { <Loading /> }>
+                                    <h3 class="text-center text-xl mt-2">
+                                        "Återkommande speldagar"
+                                    </h3>
+                                    <ul class="flex flex-col items-center w-11/12">
+                                        {move || Suspend::new(async move {
+                                            let series = series.await.unwrap_or_default();
+                                            series
+                                                .into_iter()
+                                                .map(|series| {
+                                                    view! {
+                                                        <li class="flex justify-center my-2">
+                                                            <SeriesCard series set_invalidate_gamedays />
+                                                        </li>
+                                                    }
+                                                })
+                                                .collect_view()
+                                        })}
+                                    </ul>
+                                </Transition>
                                 <Transition fallback=move || view! { <Loading /> }>
                                     <h3 class="text-center text-xl mt-2">Kommande Speldagar</h3>
                                     <ul class="flex flex-col items-center w-11/12">
                                         {move || Suspend::new(async move {
-                                            let days = gamedays.await.expect("No gamedays found");
+                                            let days = gamedays.await.unwrap_or_default();
                                             days.into_iter()
                                                 .map(|day| {
                                                     view! {
@@ -132,12 +164,11 @@ pub fn CreatePage() -> impl IntoView {
 
 #[server]
 async fn get_all_gamedays() -> Result<Vec<Gameday>, ServerFnError> {
+    use crate::auth::require_permission;
     use crate::database::get_db;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
-    }
+    require_permission(Permission::EditGameday).await?;
 
     let pool = get_db();
     match sqlx::query_as!(
@@ -147,7 +178,15 @@ async fn get_all_gamedays() -> Result<Vec<Gameday>, ServerFnError> {
             g.gameday_id, 
             g.start_date, 
             g.end_date,
-            COUNT(pg.player_id) as player_count 
+            COUNT(pg.player_id) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id
+            ) as player_count,
+            g.max_skaters,
+            g.max_goalkeepers,
+            NULL::bigint as queue_position,
+            FALSE as "time_changed!",
+            FALSE as "late_cancellation!"
         FROM 
             gameday g
         LEFT JOIN 
@@ -155,7 +194,7 @@ async fn get_all_gamedays() -> Result<Vec<Gameday>, ServerFnError> {
         WHERE 
             g.start_date >= NOW() 
         GROUP BY 
-            g.gameday_id, g.start_date, g.end_date
+            g.gameday_id, g.start_date, g.end_date, g.max_skaters, g.max_goalkeepers
         ORDER BY 
             g.start_date ASC
         "#
@@ -219,3 +258,54 @@ pub async fn get_all_cups() -> Result<Vec<Cup>, ServerFnError> {
         }
     }
 }
+
+#[server]
+async fn get_all_series() -> Result<Vec<GamedaySeries>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    require_permission(Permission::EditGameday).await?;
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        GamedaySeries,
+        r#"
+        SELECT 
+            s.series_id,
+            s.weekday,
+            s.start_time,
+            s.end_time,
+            s.season_start,
+            s.season_end,
+            s.exceptions,
+            s.max_skaters,
+            s.max_goalkeepers,
+            COUNT(g.gameday_id) as upcoming_count
+        FROM 
+            gameday_series s
+        LEFT JOIN 
+            gameday g ON s.series_id = g.series_id AND g.start_date >= NOW()
+        WHERE 
+            s.season_end >= CURRENT_DATE
+        GROUP BY 
+            s.series_id
+        ORDER BY 
+            s.weekday, s.start_time
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(results) => {
+            info!("Successfully retrieved all gameday series.");
+            Ok(results)
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get all gameday series.".to_string(),
+            ))
+        }
+    }
+}
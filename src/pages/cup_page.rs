@@ -5,10 +5,10 @@ use leptos_router::params::Params;
 use crate::{
     auth::get_auth_url,
     components::{
-        join_cup_form::JoinCupForm, leave_cup_button::LeaveCupButton, loading::Loading,
-        not_found::NotFound,
+        cup_lineup::CupLineupView, join_cup_form::JoinCupForm, leave_cup_button::LeaveCupButton,
+        loading::Loading, not_found::NotFound,
     },
-    models::{Cup, CupPlayer, Player},
+    models::{Cup, CupPlayer, Player, Position},
 };
 
 #[component]
@@ -26,11 +26,8 @@ pub fn CupPage() -> impl IntoView {
     let player =
         use_context::<Resource<Result<Player, ServerFnError>>>().expect("player context not found");
 
-    let players = Resource::new(
-        move || id(),
-        |id| async move { get_players_by_cup_id(id).await },
-    );
-    let cup = Resource::new(move || id(), |id| async move { get_cup_by_id(id).await });
+    let players = Resource::new(id, |id| async move { get_players_by_cup_id(id).await });
+    let cup = Resource::new(id, |id| async move { get_cup_by_id(id).await });
 
     let (cups_joined, set_cups_joined) = signal(Vec::new());
     let (refetch_players, set_refetch_players) = signal(false);
@@ -101,7 +98,7 @@ pub fn CupPage() -> impl IntoView {
                                                         class="underline"
                                                         on:click=move |_| {
                                                             spawn_local(async {
-                                                                let _ = get_auth_url().await;
+                                                                let _ = get_auth_url(false).await;
                                                             });
                                                         }
                                                     >
@@ -137,6 +134,7 @@ pub fn CupPage() -> impl IntoView {
                     }
                 })}
             </Transition>
+            <CupLineupView cup_id=id() />
             <h2 class="text-center text-bold text-2xl mt-6">"Anmälda spelare"</h2>
             <div class="flex md:flex-col justify-around w-full">
                 <Transition fallback=move || view! { <Loading /> }>
@@ -149,7 +147,7 @@ pub fn CupPage() -> impl IntoView {
                                 players_vec
                                     .clone()
                                     .into_iter()
-                                    .filter(|player| player.position == *"defender")
+                                    .filter(|player| player.position == Position::Defender)
                                     .map(|player| {
                                         view! {
                                             <li class="my-1">
@@ -173,7 +171,7 @@ pub fn CupPage() -> impl IntoView {
                                 players_vec
                                     .clone()
                                     .into_iter()
-                                    .filter(|player| player.position == *"forward")
+                                    .filter(|player| player.position == Position::Forward)
                                     .map(|player| {
                                         view! {
                                             <li class="my-1">
@@ -196,7 +194,7 @@ pub fn CupPage() -> impl IntoView {
                             players_vec
                                 .clone()
                                 .into_iter()
-                                .filter(|player| player.position == *"goalkeeper")
+                                .filter(|player| player.position == Position::Goalkeeper)
                                 .map(|player| {
                                     view! {
                                         <li class="my-1">
@@ -278,7 +276,7 @@ async fn get_players_by_cup_id(id: i32) -> Result<Vec<CupPlayer>, ServerFnError>
         r#"
     SELECT 
         p.name, 
-        pc.position
+        pc.position as "position: Position"
     FROM 
         player p
     JOIN 
@@ -308,17 +306,36 @@ async fn get_players_by_cup_id(id: i32) -> Result<Vec<CupPlayer>, ServerFnError>
 
 #[server]
 pub async fn get_cups_by_player() -> Result<Vec<Cup>, ServerFnError> {
-    use crate::auth::user_from_session;
-    use crate::database::get_db;
+    use crate::auth::require_player;
     use tracing::{error, info};
 
-    match user_from_session().await {
-        Ok(user) => {
-            let pool = get_db();
+    match require_player().await {
+        Ok(user) => match cups_by_player(user.player_id).await {
+            Ok(cups) => {
+                info!(
+                    "Successfully retrieved {} cups for player {}",
+                    cups.len(),
+                    user.player_id
+                );
+                Ok(cups)
+            }
+            Err(e) => {
+                error!("Database error while fetching cups for player: {:?}", e);
+                Err(ServerFnError::from(e))
+            }
+        },
+        Err(err) => Err(err),
+    }
+}
+
+/// All cups a player is registered for, newest first.
+#[cfg(feature = "ssr")]
+pub async fn cups_by_player(player_id: i32) -> Result<Vec<Cup>, sqlx::Error> {
+    use crate::database::get_db;
 
-            match sqlx::query_as!(
-                Cup,
-                r#"
+    sqlx::query_as!(
+        Cup,
+        r#"
         SELECT
             c.cup_id,
             c.start_date,
@@ -337,25 +354,8 @@ pub async fn get_cups_by_player() -> Result<Vec<Cup>, ServerFnError> {
         ORDER BY
             c.start_date DESC        
         "#,
-                user.player_id
-            )
-            .fetch_all(pool)
-            .await
-            {
-                Ok(cups) => {
-                    info!(
-                        "Successfully retrieved {} cups for player {}",
-                        cups.len(),
-                        user.player_id
-                    );
-                    Ok(cups)
-                }
-                Err(e) => {
-                    error!("Database error while fetching cups for player: {:?}", e);
-                    Err(ServerFnError::from(e))
-                }
-            }
-        }
-        Err(err) => Err(err),
-    }
+        player_id
+    )
+    .fetch_all(get_db())
+    .await
 }
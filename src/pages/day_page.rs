@@ -1,12 +1,22 @@
 use leptos::{prelude::*, task::spawn_local};
-use leptos_router::{components::Redirect, hooks::use_params, params::Params};
+use leptos_router::{
+    components::{Redirect, A},
+    hooks::use_params,
+    params::Params,
+};
 
 use crate::{
     components::{
-        gameday_card::GamedayCard, join_button::get_gamedays_by_player, loading::Loading,
+        drop_in::{get_drop_in_payment, SwishPayment},
+        gameday_card::GamedayCard,
+        guests::{get_guests_by_gameday, AddGuestForm, GuestActions, GuestEntry},
+        join_button::get_gamedays_by_player,
+        loading::Loading,
         not_found::NotFound,
+        roster::{AddToRosterSearch, RosterActions, RosterControls},
+        teams::TeamLineups,
     },
-    models::{get_players_by_gameday, Gameday, Player},
+    models::{get_players_by_gameday, get_waitlist_by_gameday, Gameday, Guest, Permission, Player},
 };
 
 #[component]
@@ -24,15 +34,29 @@ pub fn DayPage() -> impl IntoView {
     let player =
         use_context::<Resource<Result<Player, ServerFnError>>>().expect("player context not found");
 
+    let roster_actions = RosterActions::new();
+    let guest_actions = GuestActions::new();
     let players = Resource::new(
-        move || id(),
-        |id| async move { get_players_by_gameday(id).await },
+        move || (id(), roster_actions.version()),
+        |(id, _)| async move { get_players_by_gameday(id).await },
+    );
+    let waitlist = Resource::new(
+        move || (id(), roster_actions.version()),
+        |(id, _)| async move { get_waitlist_by_gameday(id).await },
+    );
+    let guests = Resource::new(
+        move || (id(), roster_actions.version(), guest_actions.version()),
+        |(id, _, _)| async move { get_guests_by_gameday(id).await },
     );
     let gameday = Resource::new(
-        move || id(),
-        |id| async move { get_gameday_by_id(id).await },
+        move || (id(), guest_actions.version()),
+        |(id, _)| async move { get_gameday_by_id(id).await },
     );
     let (gamedays_joined, set_gamedays_joined) = signal(Vec::new());
+    let payment = Resource::new(
+        move || (id(), gamedays_joined.get()),
+        |(id, _)| async move { get_drop_in_payment(id).await },
+    );
 
     Effect::new(move |_| {
         if let Some(Ok(_player_data)) = player.get() {
@@ -42,6 +66,8 @@ pub fn DayPage() -> impl IntoView {
                 if let Ok(gamedays) = get_gamedays_by_player().await {
                     set_gamedays_joined.set(gamedays);
                 }
+                // The player has now seen the current time of the gameday
+                let _ = acknowledge_time_change(id()).await;
             });
         }
     });
@@ -49,6 +75,8 @@ pub fn DayPage() -> impl IntoView {
     Effect::new(move |_| {
         if !gamedays_joined.get().is_empty() {
             players.refetch();
+            waitlist.refetch();
+            guests.refetch();
         }
     });
 
@@ -59,6 +87,14 @@ pub fn DayPage() -> impl IntoView {
             }>
                 {move || Suspend::new(async move {
                     let player_loggedin = player.await;
+                    let can_manage_roster = player_loggedin
+                        .as_ref()
+                        .is_ok_and(|player| player.role.has_permission(Permission::ManageRoster));
+                    let player_id = player_loggedin.as_ref().map(|player| player.player_id).ok();
+                    // Hosts remove their own guests, organizers anyone's
+                    let can_remove_guest = move |guest: &Guest| {
+                        can_manage_roster || Some(guest.host_id) == player_id
+                    };
                     view! {
                         <Show
                             when=move || { player_loggedin.is_ok() }
@@ -87,7 +123,57 @@ pub fn DayPage() -> impl IntoView {
                                 })}
 
                             </Transition>
+                            <TeamLineups
+                                gameday_id=id()
+                                can_manage=can_manage_roster
+                                roster=roster_actions
+                            />
                             <h2 class="text-center text-bold text-2xl mt-6">"Anmälda spelare"</h2>
+                            {can_manage_roster
+                                .then(|| {
+                                    view! {
+                                        <A
+                                            href=move || format!("/day/checkin/{}", id())
+                                            attr:class="btn btn-sm btn-primary mt-2"
+                                        >
+                                            "Incheckning"
+                                        </A>
+                                        <AddToRosterSearch
+                                            gameday_id=id()
+                                            actions=roster_actions
+                                        />
+                                    }
+                                })}
+                            <Transition>
+                                {move || Suspend::new(async move {
+                                    let registered = players
+                                        .await
+                                        .unwrap_or_default()
+                                        .iter()
+                                        .any(|player| Some(player.player_id) == player_id);
+                                    registered
+                                        .then(|| {
+                                            view! {
+                                                <AddGuestForm gameday_id=id() actions=guest_actions />
+                                            }
+                                        })
+                                })}
+                            </Transition>
+                            <Transition>
+                                {move || Suspend::new(async move {
+                                    payment
+                                        .await
+                                        .ok()
+                                        .flatten()
+                                        .map(|payment| {
+                                            view! {
+                                                <div class="card bg-base-100 shadow-xl border p-4 mt-4">
+                                                    <SwishPayment payment />
+                                                </div>
+                                            }
+                                        })
+                                })}
+                            </Transition>
                             <Transition fallback=move || view! { <Loading /> }>
                                 <h3 class="text-center text-bold underline text-xl mt-6 mb-2">
                                     "Målvakter"
@@ -101,13 +187,48 @@ pub fn DayPage() -> impl IntoView {
                                             .filter(|player| player.is_goalkeeper)
                                             .map(|player| {
                                                 view! {
-                                                    <li class="my-1">
-                                                        <p>{player.name}</p>
+                                                    <li class="flex flex-col items-center my-1">
+                                                        <p>
+                                                            {player.name.clone()}
+                                                            {player
+                                                                .fee_unpaid
+                                                                .then(|| {
+                                                                    view! {
+                                                                        <span class="badge badge-warning ml-2">
+                                                                            "Obetald avgift"
+                                                                        </span>
+                                                                    }
+                                                                })}
+                                                        </p>
+                                                        {can_manage_roster
+                                                            .then(|| {
+                                                                view! {
+                                                                    <RosterControls
+                                                                        gameday_id=id()
+                                                                        player
+                                                                        actions=roster_actions
+                                                                    />
+                                                                }
+                                                            })}
                                                     </li>
                                                 }
                                             })
                                             .collect_view()
                                     })}
+                                    {move || Suspend::new(async move {
+                                        guests
+                                            .await
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .filter(|guest| guest.is_goalkeeper)
+                                            .map(|guest| {
+                                                let can_remove = can_remove_guest(&guest);
+                                                view! {
+                                                    <GuestEntry guest can_remove actions=guest_actions />
+                                                }
+                                            })
+                                            .collect_view()
+                                    })}
                                 </ul>
                             </Transition>
                             <Transition fallback=move || view! { <Loading /> }>
@@ -122,9 +243,76 @@ pub fn DayPage() -> impl IntoView {
                                             .into_iter()
                                             .filter(|player| !player.is_goalkeeper)
                                             .map(|player| {
+                                                view! {
+                                                    <li class="flex flex-col items-center my-1">
+                                                        <p>
+                                                            {player.name.clone()}
+                                                            {player
+                                                                .fee_unpaid
+                                                                .then(|| {
+                                                                    view! {
+                                                                        <span class="badge badge-warning ml-2">
+                                                                            "Obetald avgift"
+                                                                        </span>
+                                                                    }
+                                                                })}
+                                                        </p>
+                                                        {can_manage_roster
+                                                            .then(|| {
+                                                                view! {
+                                                                    <RosterControls
+                                                                        gameday_id=id()
+                                                                        player
+                                                                        actions=roster_actions
+                                                                    />
+                                                                }
+                                                            })}
+                                                    </li>
+                                                }
+                                            })
+                                            .collect_view()
+                                    })}
+                                    {move || Suspend::new(async move {
+                                        guests
+                                            .await
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .filter(|guest| !guest.is_goalkeeper)
+                                            .map(|guest| {
+                                                let can_remove = can_remove_guest(&guest);
+                                                view! {
+                                                    <GuestEntry guest can_remove actions=guest_actions />
+                                                }
+                                            })
+                                            .collect_view()
+                                    })}
+                                </ul>
+                            </Transition>
+                            <Transition fallback=move || view! { <Loading /> }>
+                                <h3 class="text-center text-bold underline text-xl mt-6 mb-2">
+                                    "Reservlista"
+                                </h3>
+                                <ul class="flex flex-col items-center w-11/12">
+                                    {move || Suspend::new(async move {
+                                        let waitlist_vec = waitlist.await.unwrap_or_default();
+                                        waitlist_vec
+                                            .into_iter()
+                                            .map(|player| {
+                                                let position = if player.is_goalkeeper {
+                                                    "Målvakt"
+                                                } else {
+                                                    "Utespelare"
+                                                };
                                                 view! {
                                                     <li class="my-1">
-                                                        <p>{player.name}</p>
+                                                        <p>
+                                                            {format!(
+                                                                "{}. {} ({})",
+                                                                player.queue_position.unwrap_or(0),
+                                                                player.name,
+                                                                position,
+                                                            )}
+                                                        </p>
                                                     </li>
                                                 }
                                             })
@@ -147,7 +335,7 @@ struct Day {
 }
 
 #[server]
-async fn get_gameday_by_id(id: i32) -> Result<Gameday, ServerFnError> {
+pub async fn get_gameday_by_id(id: i32) -> Result<Gameday, ServerFnError> {
     use crate::database::get_db;
     use http::StatusCode;
     use tracing::{error, info};
@@ -160,7 +348,15 @@ async fn get_gameday_by_id(id: i32) -> Result<Gameday, ServerFnError> {
             g.gameday_id, 
             g.start_date, 
             g.end_date,
-            COUNT(pg.player_id) as player_count 
+            COUNT(pg.player_id) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id
+            ) as player_count,
+            g.max_skaters,
+            g.max_goalkeepers,
+            NULL::bigint as queue_position,
+            FALSE as "time_changed!",
+            FALSE as "late_cancellation!"
         FROM 
             gameday g
         LEFT JOIN 
@@ -168,7 +364,7 @@ async fn get_gameday_by_id(id: i32) -> Result<Gameday, ServerFnError> {
         WHERE 
             g.gameday_id = $1 
         GROUP BY 
-            g.gameday_id, g.start_date, g.end_date
+            g.gameday_id, g.start_date, g.end_date, g.max_skaters, g.max_goalkeepers
         "#,
         id
     )
@@ -187,3 +383,44 @@ async fn get_gameday_by_id(id: i32) -> Result<Gameday, ServerFnError> {
         }
     }
 }
+
+#[server]
+async fn acknowledge_time_change(gameday_id: i32) -> Result<(), ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    match require_player().await {
+        Ok(user) => {
+            let pool = get_db();
+            match sqlx::query!(
+                r#"
+        DELETE FROM time_change_notice
+        WHERE player_id = $1 AND gameday_id = $2
+        "#,
+                user.player_id,
+                gameday_id
+            )
+            .execute(pool)
+            .await
+            {
+                Ok(result) => {
+                    if result.rows_affected() > 0 {
+                        info!(
+                            "Player: {:?} acknowledged time change on: {:?}",
+                            user.player_id, gameday_id
+                        );
+                    }
+                    Ok(())
+                }
+                Err(e) => {
+                    error!("Database error: {:?}", e);
+                    Err(ServerFnError::ServerError(
+                        "Failed to acknowledge time change.".to_string(),
+                    ))
+                }
+            }
+        }
+        Err(err) => Err(err),
+    }
+}
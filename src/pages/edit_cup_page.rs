@@ -3,9 +3,11 @@ use leptos_router::hooks::use_params;
 use leptos_router::params::Params;
 
 use crate::{
-    components::{loading::Loading, not_found::NotFound},
+    components::{cup_lineup::CupRosterBuilder, loading::Loading, not_found::NotFound},
     models::Cup,
+    timezone::to_club_time,
 };
+#[cfg(feature = "ssr")]
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -21,10 +23,7 @@ pub fn EditCupPage() -> impl IntoView {
             .unwrap_or(0)
     };
 
-    let cup = Resource::new(
-        move || id(),
-        |id| async move { get_cup_by_id_admin(id).await },
-    );
+    let cup = Resource::new(id, |id| async move { get_cup_by_id_admin(id).await });
     let title = RwSignal::new("".to_string());
     let info = RwSignal::new("".to_string());
     let date = RwSignal::new("".to_string());
@@ -35,9 +34,15 @@ pub fn EditCupPage() -> impl IntoView {
         if let Some(Ok(cup)) = cup.get() {
             title.set(cup.title.unwrap_or("".to_string()));
             info.set(cup.info.unwrap_or("".to_string()));
-            date.set(cup.start_date.date_naive().to_string());
-            start.set(cup.start_date.time().format("%H:%M").to_string());
-            end.set(cup.end_date.time().format("%H:%M").to_string());
+            let start_date = to_club_time(cup.start_date);
+            date.set(start_date.date_naive().to_string());
+            start.set(start_date.time().format("%H:%M").to_string());
+            end.set(
+                to_club_time(cup.end_date)
+                    .time()
+                    .format("%H:%M")
+                    .to_string(),
+            );
         }
     });
 
@@ -128,6 +133,7 @@ pub fn EditCupPage() -> impl IntoView {
                                     </button>
                                 </div>
                             </ActionForm>
+                            <CupRosterBuilder cup_id=id() />
                         </Show>
                     }
                 })}
@@ -153,14 +159,13 @@ struct InputCup {
 
 #[server]
 async fn get_cup_by_id_admin(id: i32) -> Result<Cup, ServerFnError> {
-    use crate::auth::validate_admin;
+    use crate::auth::require_permission;
     use crate::database::get_db;
+    use crate::models::Permission;
     use http::StatusCode;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
-    }
+    require_permission(Permission::EditCup).await?;
 
     let pool = get_db();
     match sqlx::query_as!(
@@ -202,9 +207,14 @@ async fn get_cup_by_id_admin(id: i32) -> Result<Cup, ServerFnError> {
 
 #[server]
 async fn update_cup(input: InputCup) -> Result<(), ServerFnError> {
-    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+    use crate::auth::require_permission;
+    use crate::models::Permission;
+    use crate::timezone::from_club_time;
+    use chrono::{NaiveDate, NaiveTime};
     use tracing::info;
 
+    let manager = require_permission(Permission::EditCup).await?;
+
     info!("Updating cup with values: {:?}", input);
 
     // Parse date and time
@@ -212,17 +222,24 @@ async fn update_cup(input: InputCup) -> Result<(), ServerFnError> {
     let start_time = NaiveTime::parse_from_str(&input.start, "%H:%M")?;
     let end_time = NaiveTime::parse_from_str(&input.end, "%H:%M")?;
 
-    // Combine date and time into NaiveDateTime
-    let start_datetime = NaiveDateTime::new(date, start_time);
-    let end_datetime = NaiveDateTime::new(date, end_time);
+    // The input is wall-clock time in the club's timezone
+    let to_utc = |time: NaiveTime| -> Result<DateTime<Utc>, ServerFnError> {
+        from_club_time(date, time).ok_or_else(|| {
+            ServerFnError::ServerError(format!("{} {} is not a valid local time.", date, time))
+        })
+    };
+
+    let start_datetime = to_utc(start_time)?;
+    let end_datetime = to_utc(end_time)?;
     info!(
-        "Parsed NaiveStart: {:?}, NaiveEnd: {:?}",
+        "Parsed Start: {:?}, End: {:?}",
         start_datetime, end_datetime
     );
 
     update_cup_db(
-        start_datetime.and_utc(),
-        end_datetime.and_utc(),
+        manager.player_id,
+        start_datetime,
+        end_datetime,
         input.title,
         input.info,
         input.cup_id,
@@ -232,37 +249,74 @@ async fn update_cup(input: InputCup) -> Result<(), ServerFnError> {
     Ok(())
 }
 
-#[server]
+#[cfg(feature = "ssr")]
 async fn update_cup_db(
+    actor_id: i32,
     start_date: DateTime<Utc>,
     end_date: DateTime<Utc>,
     title: String,
     info: String,
     cup_id: i32,
 ) -> Result<(), ServerFnError> {
-    use crate::auth::validate_admin;
+    use crate::audit::record_event;
     use crate::database::get_db;
     use tracing::{error, info};
 
-    if let Err(err) = validate_admin().await {
-        return Err(err);
+    let updated = async {
+        let mut tx = get_db().begin().await?;
+        let before = sqlx::query!(
+            r#"
+            SELECT start_date, end_date, title, info
+            FROM cup
+            WHERE cup_id = $1
+            FOR UPDATE
+            "#,
+            cup_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE cup
+            SET start_date = $1, end_date = $2, title = $3, info = $4
+            WHERE cup_id = $5
+            "#,
+            start_date,
+            end_date,
+            title,
+            info,
+            cup_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(before) = before {
+            record_event(
+                &mut *tx,
+                actor_id,
+                "cup.update",
+                &format!("cup:{}", cup_id),
+                Some(serde_json::json!({
+                    "start_date": before.start_date,
+                    "end_date": before.end_date,
+                    "title": before.title,
+                    "info": before.info,
+                })),
+                Some(serde_json::json!({
+                    "start_date": start_date,
+                    "end_date": end_date,
+                    "title": title,
+                    "info": info,
+                })),
+            )
+            .await?;
+        }
+        tx.commit().await
     }
-    let pool = get_db();
-    match sqlx::query!(
-        r#"
-        UPDATE cup
-        SET start_date = $1, end_date = $2, title = $3, info = $4
-        WHERE cup_id = $5
-        "#,
-        start_date,
-        end_date,
-        title,
-        info,
-        cup_id
-    )
-    .execute(pool)
-    .await
-    {
+    .await;
+
+    match updated {
         Ok(_) => {
             info!("Cup updated: {cup_id}",);
             leptos_axum::redirect(format!("/cup/{}", cup_id).as_str());
@@ -271,7 +325,7 @@ async fn update_cup_db(
         Err(e) => {
             error!("Database error: {:?}", e);
             Err(ServerFnError::ServerError(
-                "Failed to create cup.".to_string(),
+                "Failed to update cup.".to_string(),
             ))
         }
     }
@@ -0,0 +1,406 @@
+use leptos::prelude::*;
+use leptos_router::hooks::use_params;
+use leptos_router::params::Params;
+
+use crate::{
+    components::{loading::Loading, not_found::NotFound},
+    models::Gameday,
+    timezone::to_club_time,
+};
+#[cfg(feature = "ssr")]
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[component]
+pub fn EditDayPage() -> impl IntoView {
+    let params = use_params::<DayParam>();
+    let id = move || {
+        params
+            .read()
+            .as_ref()
+            .ok()
+            .and_then(|params| params.id)
+            .unwrap_or(0)
+    };
+
+    let gameday = Resource::new(id, |id| async move { get_gameday_by_id_admin(id).await });
+    let date = RwSignal::new("".to_string());
+    let start = RwSignal::new("".to_string());
+    let end = RwSignal::new("".to_string());
+    let max_skaters = RwSignal::new("".to_string());
+    let max_goalkeepers = RwSignal::new("".to_string());
+
+    Effect::new(move |_| {
+        if let Some(Ok(gameday)) = gameday.get() {
+            let start_date = to_club_time(gameday.start_date);
+            date.set(start_date.date_naive().to_string());
+            start.set(start_date.time().format("%H:%M").to_string());
+            end.set(
+                to_club_time(gameday.end_date)
+                    .time()
+                    .format("%H:%M")
+                    .to_string(),
+            );
+            max_skaters.set(gameday.max_skaters.to_string());
+            max_goalkeepers.set(gameday.max_goalkeepers.to_string());
+        }
+    });
+
+    let submit = ServerAction::<UpdateGameday>::new();
+    view! {
+        <div class="flex flex-col items-center justify-center m-2 w-full">
+            <Transition fallback=move || {
+                view! { <Loading /> }
+            }>
+                {move || Suspend::new(async move {
+                    let gameday = gameday.await;
+                    let gameday_exist = gameday.is_ok();
+
+                    view! {
+                        <Show
+                            when=move || { gameday_exist }
+                            fallback=|| {
+                                view! { <NotFound /> }
+                            }
+                        >
+                            <ActionForm action=submit>
+                                <div class="flex flex-col justify-center">
+                                    <div class="flex flex-col m-2 max-w-44">
+                                        <label for="input[date]">Datum</label>
+                                        <input
+                                            type="date"
+                                            bind:value=date
+                                            required
+                                            name="input[date]"
+                                            class="input input-bordered"
+                                        />
+                                    </div>
+                                    <div class="flex gap-2 m-2">
+                                        <div class="flex flex-col">
+                                            <label for="input[start]">Start</label>
+                                            <input
+                                                type="time"
+                                                bind:value=start
+                                                required
+                                                name="input[start]"
+                                                class="input input-bordered"
+                                            />
+                                        </div>
+                                        <div class="flex flex-col">
+                                            <label for="input[end]">Slut</label>
+                                            <input
+                                                type="time"
+                                                bind:value=end
+                                                required
+                                                name="input[end]"
+                                                class="input input-bordered"
+                                            />
+                                        </div>
+                                    </div>
+                                    <div class="flex gap-2 m-2">
+                                        <div class="flex flex-col">
+                                            <label for="input[max_skaters]">Max utespelare</label>
+                                            <input
+                                                type="number"
+                                                bind:value=max_skaters
+                                                required
+                                                min="0"
+                                                name="input[max_skaters]"
+                                                class="input input-bordered max-w-32"
+                                            />
+                                        </div>
+                                        <div class="flex flex-col">
+                                            <label for="input[max_goalkeepers]">
+                                                "Max målvakter"
+                                            </label>
+                                            <input
+                                                type="number"
+                                                bind:value=max_goalkeepers
+                                                required
+                                                min="0"
+                                                name="input[max_goalkeepers]"
+                                                class="input input-bordered max-w-32"
+                                            />
+                                        </div>
+                                    </div>
+                                    <label class="label cursor-pointer m-2">
+                                        <span class="label-text">
+                                            "Meddela anmälda spelare om ny tid"
+                                        </span>
+                                        <input
+                                            type="checkbox"
+                                            class="toggle"
+                                            value="true"
+                                            checked
+                                            name="input[notify_players]"
+                                        />
+                                    </label>
+                                </div>
+                                <input
+                                    class="hidden"
+                                    type="number"
+                                    value=move || { id() }
+                                    name="input[gameday_id]"
+                                />
+                                <div class="flex justify-center m-4">
+                                    <button class="btn btn-success w-40" type="submit">
+                                        Uppdatera speldag
+                                    </button>
+                                </div>
+                            </ActionForm>
+                        </Show>
+                    }
+                })}
+            </Transition>
+        </div>
+    }
+}
+
+#[derive(Params, PartialEq)]
+struct DayParam {
+    id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct InputGameday {
+    gameday_id: i32,
+    date: String,
+    start: String,
+    end: String,
+    max_skaters: i32,
+    max_goalkeepers: i32,
+    notify_players: Option<bool>,
+}
+
+#[server]
+async fn get_gameday_by_id_admin(id: i32) -> Result<Gameday, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use crate::models::Permission;
+    use http::StatusCode;
+    use tracing::{error, info};
+
+    require_permission(Permission::EditGameday).await?;
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        Gameday,
+        r#"
+        SELECT
+            g.gameday_id,
+            g.start_date,
+            g.end_date,
+            COUNT(pg.player_id) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id
+            ) as player_count,
+            g.max_skaters,
+            g.max_goalkeepers,
+            NULL::bigint as queue_position,
+            FALSE as "time_changed!",
+            FALSE as "late_cancellation!"
+        FROM
+            gameday g
+        LEFT JOIN
+            player_gameday pg ON g.gameday_id = pg.gameday_id
+        WHERE
+            g.gameday_id = $1
+        GROUP BY
+            g.gameday_id, g.start_date, g.end_date, g.max_skaters, g.max_goalkeepers
+        "#,
+        id
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(results) => {
+            info!("Successfully retrieved gameday with player counts.");
+            Ok(results)
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            let opts = expect_context::<leptos_axum::ResponseOptions>();
+            opts.set_status(StatusCode::NOT_FOUND);
+            Err(ServerFnError::ServerError("No gameday found.".to_string()))
+        }
+    }
+}
+
+#[server]
+async fn update_gameday(input: InputGameday) -> Result<(), ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::models::Permission;
+    use crate::timezone::from_club_time;
+    use chrono::{NaiveDate, NaiveTime};
+    use tracing::info;
+
+    let organizer = require_permission(Permission::EditGameday).await?;
+
+    info!("Updating gameday with values: {:?}", input);
+
+    // Parse date and time
+    let date = NaiveDate::parse_from_str(&input.date, "%Y-%m-%d")?;
+    let start_time = NaiveTime::parse_from_str(&input.start, "%H:%M")?;
+    let end_time = NaiveTime::parse_from_str(&input.end, "%H:%M")?;
+
+    // The input is wall-clock time in the club's timezone
+    let to_utc = |time: NaiveTime| -> Result<DateTime<Utc>, ServerFnError> {
+        from_club_time(date, time).ok_or_else(|| {
+            ServerFnError::ServerError(format!("{} {} is not a valid local time.", date, time))
+        })
+    };
+
+    let start_datetime = to_utc(start_time)?;
+    let end_datetime = to_utc(end_time)?;
+    info!(
+        "Parsed Start: {:?}, End: {:?}",
+        start_datetime, end_datetime
+    );
+
+    update_gameday_db(
+        organizer.player_id,
+        start_datetime,
+        end_datetime,
+        input.max_skaters,
+        input.max_goalkeepers,
+        input.notify_players.unwrap_or(false),
+        input.gameday_id,
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn update_gameday_db(
+    actor_id: i32,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    max_skaters: i32,
+    max_goalkeepers: i32,
+    notify_players: bool,
+    gameday_id: i32,
+) -> Result<(), ServerFnError> {
+    use crate::components::leave_button::notify_promoted;
+    use tracing::{error, info};
+
+    match update_gameday_registrations(
+        actor_id,
+        start_date,
+        end_date,
+        max_skaters,
+        max_goalkeepers,
+        notify_players,
+        gameday_id,
+    )
+    .await
+    {
+        Ok(promoted) => {
+            info!("Gameday updated: {gameday_id}");
+            notify_promoted(gameday_id, promoted);
+            leptos_axum::redirect(format!("/day/{}", gameday_id).as_str());
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to update gameday.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Updates a gameday while keeping its registrations. Registered and waitlisted players
+/// are flagged when the time changed and `notify_players` is set.
+/// Returns the ids of players promoted from the waitlist if the capacity grew.
+#[cfg(feature = "ssr")]
+async fn update_gameday_registrations(
+    actor_id: i32,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    max_skaters: i32,
+    max_goalkeepers: i32,
+    notify_players: bool,
+    gameday_id: i32,
+) -> Result<Vec<i32>, sqlx::Error> {
+    use crate::audit::record_event;
+    use crate::components::leave_button::promote_from_waitlist;
+    use crate::database::get_db;
+    use crate::pages::season_page::assign_seasons;
+
+    let mut tx = get_db().begin().await?;
+
+    let previous = sqlx::query!(
+        r#"
+        SELECT start_date, end_date, max_skaters, max_goalkeepers
+        FROM gameday
+        WHERE gameday_id = $1
+        FOR UPDATE
+        "#,
+        gameday_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE gameday
+        SET start_date = $1,
+            end_date = $2,
+            max_skaters = $3,
+            max_goalkeepers = $4,
+            reminder_sent = reminder_sent AND start_date = $1
+        WHERE gameday_id = $5
+        "#,
+        start_date,
+        end_date,
+        max_skaters,
+        max_goalkeepers,
+        gameday_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let time_changed = previous.start_date != start_date || previous.end_date != end_date;
+    if notify_players && time_changed {
+        sqlx::query!(
+            r#"
+            INSERT INTO time_change_notice (player_id, gameday_id)
+            SELECT player_id, gameday_id FROM player_gameday WHERE gameday_id = $1
+            UNION
+            SELECT player_id, gameday_id FROM waitlist_gameday WHERE gameday_id = $1
+            ON CONFLICT (player_id, gameday_id) DO UPDATE SET created_at = NOW()
+            "#,
+            gameday_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let promoted = promote_from_waitlist(&mut tx, gameday_id).await?;
+    assign_seasons(&mut tx, &[gameday_id]).await?;
+
+    record_event(
+        &mut *tx,
+        actor_id,
+        "gameday.update",
+        &format!("gameday:{}", gameday_id),
+        Some(serde_json::json!({
+            "start_date": previous.start_date,
+            "end_date": previous.end_date,
+            "max_skaters": previous.max_skaters,
+            "max_goalkeepers": previous.max_goalkeepers,
+        })),
+        Some(serde_json::json!({
+            "start_date": start_date,
+            "end_date": end_date,
+            "max_skaters": max_skaters,
+            "max_goalkeepers": max_goalkeepers,
+            "promoted": promoted,
+        })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(promoted)
+}
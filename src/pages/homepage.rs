@@ -100,7 +100,15 @@ async fn get_next_5_gamedays() -> Result<Vec<Gameday>, ServerFnError> {
             g.gameday_id, 
             g.start_date, 
             g.end_date,
-            COUNT(pg.player_id) as player_count 
+            COUNT(pg.player_id) + (
+                SELECT COUNT(*) FROM guest_gameday gg
+                WHERE gg.gameday_id = g.gameday_id
+            ) as player_count,
+            g.max_skaters,
+            g.max_goalkeepers,
+            NULL::bigint as queue_position,
+            FALSE as "time_changed!",
+            FALSE as "late_cancellation!"
         FROM 
             gameday g
         LEFT JOIN 
@@ -108,7 +116,7 @@ async fn get_next_5_gamedays() -> Result<Vec<Gameday>, ServerFnError> {
         WHERE 
             g.start_date >= NOW() 
         GROUP BY 
-            g.gameday_id, g.start_date, g.end_date
+            g.gameday_id, g.start_date, g.end_date, g.max_skaters, g.max_goalkeepers
         ORDER BY 
             g.start_date ASC
         LIMIT 5         
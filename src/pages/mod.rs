@@ -1,9 +1,13 @@
 pub mod admin_page;
+pub mod audit_page;
 pub mod auth_page;
+pub mod check_in_page;
 pub mod create_page;
 pub mod cup_page;
 pub mod day_page;
 pub mod edit_cup_page;
+pub mod edit_day_page;
 pub mod homepage;
 pub mod profile_page;
+pub mod season_page;
 pub mod terms_page;
@@ -1,20 +1,97 @@
 use leptos::{logging::log, prelude::*, task::spawn_local};
 
-use crate::{components::logout_button::LogoutButton, models::Player};
+use crate::{
+    auth::logout_everywhere,
+    components::logout_button::LogoutButton,
+    models::{ActiveSession, ApiToken, AttendanceStats, NotificationSettings, Player, Position},
+    timezone::to_club_time,
+};
 
 #[component]
 pub fn ProfilePage() -> impl IntoView {
     let player =
         use_context::<Resource<Result<Player, ServerFnError>>>().expect("player context not found");
 
-    let goalkeeper = RwSignal::new(false);
+    let position = RwSignal::new(Position::default());
+    let notify_registration = RwSignal::new(true);
+    let notify_waitlist = RwSignal::new(true);
+    let notify_cancellation = RwSignal::new(true);
+    let notify_reminder = RwSignal::new(true);
+    let notify_goalkeeper_shortage = RwSignal::new(true);
+    let notification_settings = Resource::new(|| (), |_| get_notification_settings());
+    let reset_calendar = ServerAction::<ResetCalendarToken>::new();
+    let calendar_url = Resource::new(
+        move || reset_calendar.version().get(),
+        |_| async move { get_calendar_url().await },
+    );
+
+    let create_api_token = ServerAction::<CreateApiToken>::new();
+    let delete_api_token = ServerAction::<DeleteApiToken>::new();
+    let api_tokens = Resource::new(
+        move || {
+            (
+                create_api_token.version().get(),
+                delete_api_token.version().get(),
+            )
+        },
+        |_| async move { get_api_tokens().await },
+    );
+    // The new token can't be read back later
+    let new_api_token = move || {
+        create_api_token
+            .value()
+            .get()
+            .and_then(|result| result.ok())
+            .map(|token| {
+                view! {
+                    <input
+                        type="text"
+                        readonly
+                        value=token
+                        class="input input-bordered input-sm w-full m-2 font-mono"
+                    />
+                }
+            })
+    };
+
+    let attendance = Resource::new(|| (), |_| get_my_attendance_stats());
+    let revoke_session = ServerAction::<RevokeSession>::new();
+    let sessions = Resource::new(
+        move || revoke_session.version().get(),
+        |_| async move { get_sessions().await },
+    );
 
     Effect::new(move |_| {
         if let Some(Ok(player_data)) = player.get() {
-            goalkeeper.set(player_data.is_goalkeeper);
+            position.set(player_data.position);
+        }
+    });
+
+    Effect::new(move |_| {
+        if let Some(Ok(settings)) = notification_settings.get() {
+            notify_registration.set(settings.notify_registration);
+            notify_waitlist.set(settings.notify_waitlist);
+            notify_cancellation.set(settings.notify_cancellation);
+            notify_reminder.set(settings.notify_reminder);
+            notify_goalkeeper_shortage.set(settings.notify_goalkeeper_shortage);
         }
     });
 
+    let save_notification_settings = move |_| {
+        let settings = NotificationSettings {
+            notify_registration: notify_registration.get(),
+            notify_waitlist: notify_waitlist.get(),
+            notify_cancellation: notify_cancellation.get(),
+            notify_reminder: notify_reminder.get(),
+            notify_goalkeeper_shortage: notify_goalkeeper_shortage.get(),
+        };
+        spawn_local(async move {
+            if let Err(err) = update_notification_settings(settings).await {
+                log!("Failed to update notification settings: {:?}", err);
+            }
+        });
+    };
+
     view! {
         <Suspense fallback=move || {
             view! {
@@ -43,14 +120,18 @@ pub fn ProfilePage() -> impl IntoView {
                         <p class="m-2 text-xl text-bold">{player.name}</p>
                         <p class="m-2">{player.email}</p>
 
-                        <label class="label cursor-pointer mt-2">
-                            <span class="label-text mx-2">Utespelare</span>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                bind:checked=goalkeeper
-                                on:change=move |_| {
-                                    let change_made = goalkeeper.get();
+                        <label class="label mt-2">
+                            <span class="label-text mx-2">"Position"</span>
+                            <select
+                                class="select select-bordered select-sm"
+                                on:change=move |ev| {
+                                    let Some(change_made) = Position::ALL
+                                        .into_iter()
+                                        .find(|position| position.as_str() == event_target_value(&ev))
+                                    else {
+                                        return;
+                                    };
+                                    position.set(change_made);
                                     spawn_local(async move {
                                         if let Err(err) = update_player_position(change_made).await
                                         {
@@ -58,9 +139,192 @@ pub fn ProfilePage() -> impl IntoView {
                                         }
                                     });
                                 }
-                            />
-                            <span class="label-text mx-2">"Målvakt"</span>
+                            >
+                                {Position::ALL
+                                    .into_iter()
+                                    .map(|option| {
+                                        view! {
+                                            <option
+                                                value=option.as_str()
+                                                selected=move || position.get() == option
+                                            >
+                                                {option.label()}
+                                            </option>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </select>
                         </label>
+                        <Transition>
+                            {move || Suspend::new(async move {
+                                attendance
+                                    .await
+                                    .ok()
+                                    .map(|stats| {
+                                        view! {
+                                            <div class="stats shadow mt-6">
+                                                <div class="stat place-items-center">
+                                                    <div class="stat-title">"Närvaro"</div>
+                                                    <div class="stat-value text-2xl">
+                                                        {stats.attendance_percent()}
+                                                    </div>
+                                                    <div class="stat-desc">
+                                                        {format!("{} speldagar", stats.registered)}
+                                                    </div>
+                                                </div>
+                                                <div class="stat place-items-center">
+                                                    <div class="stat-title">"Uteblev"</div>
+                                                    <div class="stat-value text-2xl">{stats.no_shows}</div>
+                                                </div>
+                                                <div class="stat place-items-center">
+                                                    <div class="stat-title">"Sena avanmälningar"</div>
+                                                    <div class="stat-value text-2xl">
+                                                        {stats.late_cancellations}
+                                                    </div>
+                                                </div>
+                                            </div>
+                                        }
+                                    })
+                            })}
+                        </Transition>
+                        <div class="flex flex-col items-center mt-6">
+                            <h3 class="text-center text-xl m-2">Mejl</h3>
+                            <label class="label cursor-pointer">
+                                <span class="label-text mx-2">"Anmälan och avanmälan"</span>
+                                <input
+                                    type="checkbox"
+                                    class="toggle"
+                                    bind:checked=notify_registration
+                                    on:change=save_notification_settings
+                                />
+                            </label>
+                            <label class="label cursor-pointer">
+                                <span class="label-text mx-2">"Plats från reservlistan"</span>
+                                <input
+                                    type="checkbox"
+                                    class="toggle"
+                                    bind:checked=notify_waitlist
+                                    on:change=save_notification_settings
+                                />
+                            </label>
+                            <label class="label cursor-pointer">
+                                <span class="label-text mx-2">"Inställda speldagar"</span>
+                                <input
+                                    type="checkbox"
+                                    class="toggle"
+                                    bind:checked=notify_cancellation
+                                    on:change=save_notification_settings
+                                />
+                            </label>
+                            <label class="label cursor-pointer">
+                                <span class="label-text mx-2">"Påminnelser"</span>
+                                <input
+                                    type="checkbox"
+                                    class="toggle"
+                                    bind:checked=notify_reminder
+                                    on:change=save_notification_settings
+                                />
+                            </label>
+                            <Show when=move || position.get() == Position::Goalkeeper>
+                                <label class="label cursor-pointer">
+                                    <span class="label-text mx-2">"Brist på målvakter"</span>
+                                    <input
+                                        type="checkbox"
+                                        class="toggle"
+                                        bind:checked=notify_goalkeeper_shortage
+                                        on:change=save_notification_settings
+                                    />
+                                </label>
+                            </Show>
+                        </div>
+                        <div class="flex flex-col items-center mt-6 w-11/12 max-w-md">
+                            <h3 class="text-center text-xl m-2">Kalender</h3>
+                            <p class="text-center text-sm">
+                                "Prenumerera på dina speldagar och cupper i din kalenderapp med länken nedan. Dela den inte med andra."
+                            </p>
+                            <Transition>
+                                {move || Suspend::new(async move {
+                                    let url = calendar_url.await.unwrap_or_default();
+                                    view! {
+                                        <input
+                                            type="text"
+                                            readonly
+                                            value=url
+                                            class="input input-bordered input-sm w-full m-2"
+                                        />
+                                    }
+                                })}
+                            </Transition>
+                            <ActionForm action=reset_calendar>
+                                <button type="submit" class="btn btn-xs btn-warning">
+                                    "Skapa ny länk"
+                                </button>
+                            </ActionForm>
+                        </div>
+                        <div class="flex flex-col items-center mt-6 w-11/12 max-w-md">
+                            <h3 class="text-center text-xl m-2">"API-nycklar"</h3>
+                            <p class="text-center text-sm">
+                                "Nycklar för skript och bottar som använder API:et på /api/v1. Nyckeln visas bara när den skapas, dela den inte med andra."
+                            </p>
+                            {new_api_token}
+                            <ActionForm action=create_api_token>
+                                <div class="flex items-center gap-2 m-2">
+                                    <input
+                                        type="text"
+                                        name="name"
+                                        placeholder="Namn, t.ex. chattbot"
+                                        class="input input-bordered input-sm"
+                                        maxlength=ApiToken::MAX_NAME_LENGTH
+                                        required
+                                    />
+                                    <button type="submit" class="btn btn-xs btn-primary">
+                                        "Skapa nyckel"
+                                    </button>
+                                </div>
+                            </ActionForm>
+                            <Transition>
+                                <ul class="flex flex-col w-full">
+                                    {move || Suspend::new(async move {
+                                        api_tokens
+                                            .await
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .map(|token| {
+                                                view! { <ApiTokenItem token delete_api_token /> }
+                                            })
+                                            .collect_view()
+                                    })}
+                                </ul>
+                            </Transition>
+                        </div>
+                        <div class="flex flex-col items-center mt-6 w-11/12 max-w-md">
+                            <h3 class="text-center text-xl m-2">Inloggade enheter</h3>
+                            <Transition>
+                                <ul class="flex flex-col w-full">
+                                    {move || Suspend::new(async move {
+                                        sessions
+                                            .await
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .map(|session| {
+                                                view! { <SessionItem session revoke_session /> }
+                                            })
+                                            .collect_view()
+                                    })}
+                                </ul>
+                            </Transition>
+                            <button
+                                class="btn btn-xs btn-warning m-2"
+                                on:click=move |_| {
+                                    spawn_local(async {
+                                        let _ = logout_everywhere().await;
+                                    });
+                                }
+                            >
+                                "Logga ut överallt"
+                            </button>
+                            <p class="text-center text-xs">"API-nycklarna tas också bort."</p>
+                        </div>
                         <div class="mt-20">
                             <LogoutButton />
                         </div>
@@ -72,38 +336,336 @@ pub fn ProfilePage() -> impl IntoView {
     }
 }
 
+#[component]
+fn SessionItem(
+    session: ActiveSession,
+    revoke_session: ServerAction<RevokeSession>,
+) -> impl IntoView {
+    let user_agent = session.user_agent.unwrap_or_default();
+    view! {
+        <li class="flex items-center justify-between border-b py-2">
+            <div class="flex flex-col">
+                <p class="font-bold" title=user_agent.clone()>
+                    {device_name(&user_agent)}
+                </p>
+                <p class="text-sm">
+                    {session.ip.unwrap_or_default()} " inloggad "
+                    {to_club_time(session.created_at).format("%Y-%m-%d %H:%M").to_string()}
+                </p>
+            </div>
+            <Show
+                when=move || !session.current
+                fallback=|| view! { <span class="badge badge-success">"Den här enheten"</span> }
+            >
+                <ActionForm action=revoke_session>
+                    <input type="hidden" name="session_id" value=session.session_id.to_string() />
+                    <button type="submit" class="btn btn-xs btn-error">
+                        "Logga ut"
+                    </button>
+                </ActionForm>
+            </Show>
+        </li>
+    }
+}
+
+#[component]
+fn ApiTokenItem(token: ApiToken, delete_api_token: ServerAction<DeleteApiToken>) -> impl IntoView {
+    let last_used = token
+        .last_used_at
+        .map(|last_used| to_club_time(last_used).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or("aldrig".to_string());
+    view! {
+        <li class="flex items-center justify-between border-b py-2">
+            <div class="flex flex-col">
+                <p class="font-bold">{token.name}</p>
+                <p class="text-sm">
+                    "Skapad " {to_club_time(token.created_at).format("%Y-%m-%d").to_string()}
+                    ", senast använd " {last_used}
+                </p>
+            </div>
+            <ActionForm action=delete_api_token>
+                <input type="hidden" name="api_token_id" value=token.api_token_id />
+                <button type="submit" class="btn btn-xs btn-error">
+                    "Ta bort"
+                </button>
+            </ActionForm>
+        </li>
+    }
+}
+
+/// Short description of a browser from its user agent, e.g. `Firefox på Windows`.
+fn device_name(user_agent: &str) -> String {
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ]
+    .into_iter()
+    .find(|(token, _)| user_agent.contains(token))
+    .map(|(_, name)| name);
+    let system = [
+        ("Android", "Android"),
+        ("iPhone", "iPhone"),
+        ("iPad", "iPad"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "Mac"),
+        ("Linux", "Linux"),
+    ]
+    .into_iter()
+    .find(|(token, _)| user_agent.contains(token))
+    .map(|(_, name)| name);
+
+    match (browser, system) {
+        (Some(browser), Some(system)) => format!("{} på {}", browser, system),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => "Okänd enhet".to_string(),
+    }
+}
+
+#[server]
+async fn get_my_attendance_stats() -> Result<AttendanceStats, ServerFnError> {
+    use crate::auth::require_player;
+    use crate::models::attendance_stats;
+    use tracing::error;
+
+    let user = require_player().await?;
+    match attendance_stats(Some(user.player_id)).await {
+        Ok(stats) => stats
+            .into_iter()
+            .next()
+            .ok_or_else(|| ServerFnError::ServerError("No player found.".to_string())),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get attendance.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn get_sessions() -> Result<Vec<ActiveSession>, ServerFnError> {
+    use crate::auth::{read_session_cookie, require_player};
+    use crate::database::get_db;
+    use leptos_axum::extract;
+    use tower_cookies::Cookies;
+    use tracing::error;
+
+    let user = require_player().await?;
+    let current = extract::<Cookies>()
+        .await
+        .ok()
+        .and_then(|cookies| read_session_cookie(&cookies))
+        .map(|cookie| cookie.session_id);
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        ActiveSession,
+        r#"
+        SELECT
+            session_id,
+            user_agent,
+            ip,
+            created_at as "created_at!",
+            expires_at,
+            session_id = $2 IS TRUE as "current!"
+        FROM session
+        WHERE player_id = $1
+          AND expires_at > NOW()
+        ORDER BY created_at DESC
+        "#,
+        user.player_id,
+        current
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(sessions) => Ok(sessions),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get sessions.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Logs out one of the player's own sessions, e.g. a lost phone.
+#[server]
+async fn revoke_session(session_id: uuid::Uuid) -> Result<(), ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    let user = require_player().await?;
+    let pool = get_db();
+    match sqlx::query!(
+        r#"
+        DELETE FROM session
+        WHERE session_id = $1 AND player_id = $2
+        "#,
+        session_id,
+        user.player_id
+    )
+    .execute(pool)
+    .await
+    {
+        Ok(_) => {
+            info!("Player: {:?} revoked a session", user.player_id);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to revoke session.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn get_api_tokens() -> Result<Vec<ApiToken>, ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use tracing::error;
+
+    let user = require_player().await?;
+    let pool = get_db();
+    match sqlx::query_as!(
+        ApiToken,
+        r#"
+        SELECT api_token_id, name, created_at, last_used_at
+        FROM api_token
+        WHERE player_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user.player_id
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(tokens) => Ok(tokens),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get API tokens.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Creates a personal API token and returns it, this is the only time it is shown.
+#[server]
+async fn create_api_token(name: String) -> Result<String, ServerFnError> {
+    use crate::auth::{generate_api_token, hash_api_token, require_player};
+    use crate::database::get_db;
+    use http::StatusCode;
+    use tracing::{error, info};
+
+    let user = require_player().await?;
+
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > ApiToken::MAX_NAME_LENGTH {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::ServerError(
+            "The API token needs a name.".to_string(),
+        ));
+    }
+
+    let token = generate_api_token();
+    let pool = get_db();
+    match sqlx::query!(
+        r#"
+        INSERT INTO api_token (player_id, name, token_hash)
+        VALUES ($1, $2, $3)
+        "#,
+        user.player_id,
+        name,
+        hash_api_token(&token)
+    )
+    .execute(pool)
+    .await
+    {
+        Ok(_) => {
+            info!("Player: {:?} created API token {:?}", user.player_id, name);
+            Ok(token)
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to create API token.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn delete_api_token(api_token_id: i32) -> Result<(), ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    let user = require_player().await?;
+    let pool = get_db();
+    match sqlx::query!(
+        r#"
+        DELETE FROM api_token
+        WHERE api_token_id = $1 AND player_id = $2
+        "#,
+        api_token_id,
+        user.player_id
+    )
+    .execute(pool)
+    .await
+    {
+        Ok(_) => {
+            info!(
+                "Player: {:?} deleted API token {:?}",
+                user.player_id, api_token_id
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to delete API token.".to_string(),
+            ))
+        }
+    }
+}
+
 #[server]
-async fn update_player_position(is_goalkeeper: bool) -> Result<(), ServerFnError> {
-    use crate::auth::user_from_session;
+async fn update_player_position(position: Position) -> Result<(), ServerFnError> {
+    use crate::auth::require_player;
     use crate::database::get_db;
     use tracing::{error, info};
 
-    match user_from_session().await {
+    match require_player().await {
         Ok(user) => {
             let pool = get_db();
             match sqlx::query!(
                 r#"
         UPDATE player
-        SET is_goalkeeper = $1
+        SET position = $1
         WHERE player_id = $2
         "#,
-                is_goalkeeper,
+                position as Position,
                 user.player_id
             )
             .execute(pool)
             .await
             {
                 Ok(_) => {
-                    info!(
-                        "Player: {:?} set goalkeeper: {:?}",
-                        user.player_id, is_goalkeeper
-                    );
+                    info!("Player: {:?} set position: {:?}", user.player_id, position);
                     Ok(())
                 }
                 Err(e) => {
                     error!("Database error: {:?}", e);
                     Err(ServerFnError::ServerError(
-                        "Failed to add player to gameday.".to_string(),
+                        "Failed to update position.".to_string(),
                     ))
                 }
             }
@@ -111,3 +673,149 @@ async fn update_player_position(is_goalkeeper: bool) -> Result<(), ServerFnError
         Err(err) => Err(err),
     }
 }
+
+#[server]
+async fn get_calendar_url() -> Result<String, ServerFnError> {
+    use crate::auth::require_player;
+    use crate::config::get_config;
+    use crate::database::get_db;
+    use tracing::error;
+
+    let user = require_player().await?;
+    let pool = get_db();
+    match sqlx::query_scalar!(
+        r#"
+        SELECT calendar_token
+        FROM player
+        WHERE player_id = $1
+        "#,
+        user.player_id
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(token) => {
+            let site_url = reqwest::Url::parse(&get_config().site.url)?;
+            Ok(site_url
+                .join(&format!("/calendar/{}.ics", token))?
+                .to_string())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get calendar token.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn reset_calendar_token() -> Result<(), ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    let user = require_player().await?;
+    let pool = get_db();
+    match sqlx::query!(
+        r#"
+        UPDATE player
+        SET calendar_token = gen_random_uuid()
+        WHERE player_id = $1
+        "#,
+        user.player_id
+    )
+    .execute(pool)
+    .await
+    {
+        Ok(_) => {
+            info!("Player: {:?} reset calendar token", user.player_id);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to reset calendar token.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn get_notification_settings() -> Result<NotificationSettings, ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use tracing::error;
+
+    let user = require_player().await?;
+    let pool = get_db();
+    match sqlx::query_as!(
+        NotificationSettings,
+        r#"
+        SELECT
+            notify_registration,
+            notify_waitlist,
+            notify_cancellation,
+            notify_reminder,
+            notify_goalkeeper_shortage
+        FROM player
+        WHERE player_id = $1
+        "#,
+        user.player_id
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(settings) => Ok(settings),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get notification settings.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn update_notification_settings(settings: NotificationSettings) -> Result<(), ServerFnError> {
+    use crate::auth::require_player;
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    let user = require_player().await?;
+    let pool = get_db();
+    match sqlx::query!(
+        r#"
+        UPDATE player
+        SET notify_registration = $1,
+            notify_waitlist = $2,
+            notify_cancellation = $3,
+            notify_reminder = $4,
+            notify_goalkeeper_shortage = $5
+        WHERE player_id = $6
+        "#,
+        settings.notify_registration,
+        settings.notify_waitlist,
+        settings.notify_cancellation,
+        settings.notify_reminder,
+        settings.notify_goalkeeper_shortage,
+        user.player_id
+    )
+    .execute(pool)
+    .await
+    {
+        Ok(_) => {
+            info!(
+                "Player: {:?} set notification settings: {:?}",
+                user.player_id, settings
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to update notification settings.".to_string(),
+            ))
+        }
+    }
+}
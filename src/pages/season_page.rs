@@ -0,0 +1,995 @@
+use leptos::prelude::*;
+use leptos_router::components::{Redirect, A};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::user_from_session,
+    components::{drop_in::DropInFees, loading::Loading},
+    models::{Permission, PlayerMembership, Season},
+};
+
+/// Seasons, the membership fees of the players and unpaid drop-in fees.
+#[component]
+pub fn SeasonPage() -> impl IntoView {
+    let role = Resource::new(
+        || (),
+        |_| async move {
+            user_from_session()
+                .await
+                .map(|player| player.role)
+                .unwrap_or_default()
+        },
+    );
+    let add_season = ServerAction::<AddSeason>::new();
+    let update_season = ServerAction::<UpdateSeason>::new();
+    let delete_season = ServerAction::<DeleteSeason>::new();
+    let set_membership = ServerAction::<SetMembership>::new();
+    let remove_membership = ServerAction::<RemoveMembership>::new();
+    let seasons = Resource::new(
+        move || {
+            (
+                add_season.version().get(),
+                update_season.version().get(),
+                delete_season.version().get(),
+            )
+        },
+        |_| get_seasons(),
+    );
+    // None shows the latest season
+    let (selected, set_selected) = signal(None::<i32>);
+    Effect::new(move || {
+        if delete_season
+            .value()
+            .get()
+            .is_some_and(|result| result.is_ok())
+        {
+            set_selected.set(None);
+        }
+    });
+    let add_error = move || {
+        add_season
+            .value()
+            .get()
+            .and_then(|result| result.err())
+            .map(|_| "Kunde inte lägga till säsongen, den får inte överlappa en annan.")
+    };
+
+    view! {
+        <Suspense fallback=move || view! { <Loading /> }>
+            {move || Suspend::new(async move {
+                let can_manage_players = role.await.has_permission(Permission::ManagePlayers);
+                view! {
+                    <Show
+                        when=move || can_manage_players
+                        fallback=|| view! { <Redirect path="/" /> }
+                    >
+                        <div class="flex flex-col w-full items-center">
+                            <h3 class="text-center text-xl mt-2">"Säsonger och avgifter"</h3>
+                            <A href="/admin" attr:class="link text-sm">
+                                "Tillbaka till admin"
+                            </A>
+                            <ActionForm action=add_season>
+                                <div class="flex flex-wrap items-end justify-center gap-2 m-2">
+                                    <SeasonFields season=None />
+                                    <button type="submit" class="btn btn-sm btn-success">
+                                        "Lägg till säsong"
+                                    </button>
+                                </div>
+                            </ActionForm>
+                            <p class="text-error">{add_error}</p>
+                            <Transition fallback=move || view! { <Loading /> }>
+                                {move || {
+                                    let selected = selected.get();
+                                    Suspend::new(async move {
+                                    let seasons = seasons.await.unwrap_or_default();
+                                    let season = selected
+                                        .and_then(|id| {
+                                            seasons.iter().find(|season| season.season_id == id)
+                                        })
+                                        .or(seasons.first())
+                                        .cloned();
+                                    let options = seasons
+                                        .iter()
+                                        .map(|option| {
+                                            let is_selected = season
+                                                .as_ref()
+                                                .is_some_and(|season| {
+                                                    season.season_id == option.season_id
+                                                });
+                                            view! {
+                                                <option value=option.season_id selected=is_selected>
+                                                    {option.name.clone()}
+                                                </option>
+                                            }
+                                        })
+                                        .collect_view();
+                                    match season {
+                                        Some(season) => {
+                                            view! {
+                                                <select
+                                                    class="select select-bordered select-sm mt-4"
+                                                    on:change=move |ev| {
+                                                        set_selected.set(event_target_value(&ev).parse().ok())
+                                                    }
+                                                >
+                                                    {options}
+                                                </select>
+                                                <p class="text-sm m-2">
+                                                    {format!(
+                                                        "{} – {}, {} speldagar, säsong {} kr, drop-in {} kr",
+                                                        season.start_date,
+                                                        season.end_date,
+                                                        season.gameday_count.unwrap_or(0),
+                                                        season.fee,
+                                                        season.drop_in_fee,
+                                                    )}
+                                                </p>
+                                                <EditSeason
+                                                    season=season.clone()
+                                                    update_season
+                                                    delete_season
+                                                />
+                                                <MembershipTable
+                                                    season
+                                                    set_membership
+                                                    remove_membership
+                                                />
+                                            }
+                                                .into_any()
+                                        }
+                                        None => {
+                                            view! { <p class="m-4">"Inga säsonger ännu."</p> }
+                                                .into_any()
+                                        }
+                                    }
+                                })
+                                }}
+                            </Transition>
+                            <DropInFees />
+                        </div>
+                    </Show>
+                }
+            })}
+        </Suspense>
+    }
+}
+
+/// The inputs of a season, filled in with the season that is edited.
+#[component]
+fn SeasonFields(season: Option<Season>) -> impl IntoView {
+    let value = |field: fn(&Season) -> String| season.as_ref().map(field).unwrap_or_default();
+
+    view! {
+        <div class="flex flex-col">
+            <label for="input_season[name]">"Namn"</label>
+            <input
+                type="text"
+                required
+                name="input_season[name]"
+                placeholder="2026/2027"
+                value=value(|season| season.name.clone())
+                class="input input-bordered input-sm"
+            />
+        </div>
+        <div class="flex flex-col">
+            <label for="input_season[start]">"Från"</label>
+            <input
+                type="date"
+                required
+                name="input_season[start]"
+                value=value(|season| season.start_date.to_string())
+                class="input input-bordered input-sm"
+            />
+        </div>
+        <div class="flex flex-col">
+            <label for="input_season[end]">"Till och med"</label>
+            <input
+                type="date"
+                required
+                name="input_season[end]"
+                value=value(|season| season.end_date.to_string())
+                class="input input-bordered input-sm"
+            />
+        </div>
+        <div class="flex flex-col">
+            <label for="input_season[fee]">"Säsongsavgift"</label>
+            <input
+                type="number"
+                required
+                min="0"
+                name="input_season[fee]"
+                value=value(|season| season.fee.to_string())
+                class="input input-bordered input-sm max-w-28"
+            />
+        </div>
+        <div class="flex flex-col">
+            <label for="input_season[drop_in_fee]">"Drop-in"</label>
+            <input
+                type="number"
+                required
+                min="0"
+                name="input_season[drop_in_fee]"
+                value=value(|season| season.drop_in_fee.to_string())
+                class="input input-bordered input-sm max-w-28"
+            />
+        </div>
+    }
+}
+
+/// Changes or deletes the selected season.
+#[component]
+fn EditSeason(
+    season: Season,
+    update_season: ServerAction<UpdateSeason>,
+    delete_season: ServerAction<DeleteSeason>,
+) -> impl IntoView {
+    let season_id = season.season_id;
+    let season = StoredValue::new(season);
+    let show_edit = RwSignal::new(false);
+    Effect::new(move || {
+        if update_season
+            .value()
+            .get()
+            .is_some_and(|result| result.is_ok())
+        {
+            show_edit.set(false);
+        }
+    });
+    let error = move || {
+        update_season
+            .value()
+            .get()
+            .and_then(|result| result.err())
+            .map(|_| "Kunde inte ändra säsongen, den får inte överlappa en annan.")
+            .or_else(|| {
+                delete_season
+                    .value()
+                    .get()
+                    .and_then(|result| result.err())
+                    .map(|_| "Kunde inte ta bort säsongen, den har betalda medlemskap.")
+            })
+    };
+
+    view! {
+        <Show when=move || show_edit.get()>
+            <ActionForm action=update_season>
+                <div class="flex flex-wrap items-end justify-center gap-2 m-2">
+                    <input type="hidden" name="season_id" value=season_id />
+                    <SeasonFields season=Some(season.get_value()) />
+                    <button type="submit" class="btn btn-sm btn-success">
+                        "Spara"
+                    </button>
+                </div>
+            </ActionForm>
+        </Show>
+        <div class="flex gap-2">
+            <button
+                class="btn btn-sm btn-warning"
+                on:click=move |_| show_edit.update(|show| *show = !*show)
+            >
+                "Ändra säsong"
+            </button>
+            <ActionForm action=delete_season>
+                <input type="hidden" name="season_id" value=season_id />
+                <button type="submit" class="btn btn-sm btn-error">
+                    "Ta bort säsong"
+                </button>
+            </ActionForm>
+        </div>
+        <p class="text-error">{error}</p>
+    }
+}
+
+/// Every player with the membership of the season, the amount defaults to the season fee.
+#[component]
+fn MembershipTable(
+    season: Season,
+    set_membership: ServerAction<SetMembership>,
+    remove_membership: ServerAction<RemoveMembership>,
+) -> impl IntoView {
+    let season_id = season.season_id;
+    let memberships = Resource::new(
+        move || {
+            (
+                set_membership.version().get(),
+                remove_membership.version().get(),
+            )
+        },
+        move |_| get_memberships(season_id),
+    );
+
+    view! {
+        <Transition fallback=move || view! { <Loading /> }>
+            {move || Suspend::new(async move {
+                let memberships = memberships.await.unwrap_or_default();
+                let (paid, unpaid): (Vec<_>, Vec<_>) = memberships
+                    .iter()
+                    .filter(|membership| membership.is_member())
+                    .partition(|membership| membership.is_paid());
+                let outstanding: i32 = unpaid
+                    .iter()
+                    .filter_map(|membership| membership.amount)
+                    .sum();
+                view! {
+                    <p class="text-center m-2">
+                        {format!(
+                            "{} av {} medlemmar har betalt, {} kr obetalt",
+                            paid.len(),
+                            paid.len() + unpaid.len(),
+                            outstanding,
+                        )}
+                    </p>
+                    <div class="overflow-x-auto w-11/12">
+                        <table class="table table-sm">
+                            <thead>
+                                <tr>
+                                    <th>"Namn"</th>
+                                    <th>"Avgift"</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {memberships
+                                    .into_iter()
+                                    .map(|membership| {
+                                        let is_member = membership.is_member();
+                                        let is_paid = membership.is_paid();
+                                        view! {
+                                            <tr>
+                                                <td>
+                                                    {membership.name}
+                                                    {(is_member && !is_paid)
+                                                        .then(|| {
+                                                            view! {
+                                                                <span class="badge badge-warning ml-2">
+                                                                    "Obetald"
+                                                                </span>
+                                                            }
+                                                        })}
+                                                </td>
+                                                <td>
+                                                    <ActionForm action=set_membership>
+                                                        <div class="flex items-center gap-2">
+                                                            <input
+                                                                type="hidden"
+                                                                name="season_id"
+                                                                value=season_id
+                                                            />
+                                                            <input
+                                                                type="hidden"
+                                                                name="player_id"
+                                                                value=membership.player_id
+                                                            />
+                                                            <input
+                                                                type="number"
+                                                                required
+                                                                min="0"
+                                                                name="amount"
+                                                                class="input input-bordered input-sm max-w-24"
+                                                                value=membership.amount.unwrap_or(season.fee)
+                                                            />
+                                                            <select
+                                                                class="select select-bordered select-sm"
+                                                                name="paid"
+                                                            >
+                                                                <option value="false" selected=!is_paid>
+                                                                    "Obetald"
+                                                                </option>
+                                                                <option value="true" selected=is_paid>
+                                                                    "Betald"
+                                                                </option>
+                                                            </select>
+                                                            <button type="submit" class="btn btn-sm btn-success">
+                                                                {if is_member { "Spara" } else { "Lägg till" }}
+                                                            </button>
+                                                        </div>
+                                                    </ActionForm>
+                                                </td>
+                                                <td>
+                                                    {is_member
+                                                        .then(|| {
+                                                            view! {
+                                                                <ActionForm action=remove_membership>
+                                                                    <input
+                                                                        type="hidden"
+                                                                        name="season_id"
+                                                                        value=season_id
+                                                                    />
+                                                                    <input
+                                                                        type="hidden"
+                                                                        name="player_id"
+                                                                        value=membership.player_id
+                                                                    />
+                                                                    <button type="submit" class="btn btn-sm btn-error">
+                                                                        "Ta bort"
+                                                                    </button>
+                                                                </ActionForm>
+                                                            }
+                                                        })}
+                                                </td>
+                                            </tr>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </tbody>
+                        </table>
+                    </div>
+                }
+            })}
+        </Transition>
+    }
+}
+
+/// Points the gamedays at the season their local date falls in. Called with the gamedays
+/// whose dates changed, or those of a season that changed.
+#[cfg(feature = "ssr")]
+pub async fn assign_seasons(
+    conn: &mut sqlx::PgConnection,
+    gameday_ids: &[i32],
+) -> Result<(), sqlx::Error> {
+    use crate::timezone::club_timezone;
+
+    sqlx::query!(
+        r#"
+        WITH assigned AS (
+            SELECT
+                g.gameday_id,
+                (
+                    SELECT s.season_id FROM season s
+                    WHERE (g.start_date AT TIME ZONE $1)::date
+                        BETWEEN s.start_date AND s.end_date
+                ) as season_id
+            FROM gameday g
+            WHERE g.gameday_id = ANY($2)
+        )
+        UPDATE gameday g
+        SET season_id = a.season_id
+        FROM assigned a
+        WHERE g.gameday_id = a.gameday_id AND g.season_id IS DISTINCT FROM a.season_id
+        "#,
+        club_timezone().name(),
+        gameday_ids
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The gamedays in a season and those within the dates it is given, the ones that may
+/// change season when it is created or its dates are changed.
+#[cfg(feature = "ssr")]
+async fn season_gamedays(
+    conn: &mut sqlx::PgConnection,
+    season_id: Option<i32>,
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+) -> Result<Vec<i32>, sqlx::Error> {
+    use crate::timezone::club_timezone;
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT gameday_id
+        FROM gameday
+        WHERE season_id = $1
+           OR (start_date AT TIME ZONE $4)::date BETWEEN $2 AND $3
+        "#,
+        season_id,
+        start_date,
+        end_date,
+        club_timezone().name()
+    )
+    .fetch_all(conn)
+    .await
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct InputSeason {
+    name: String,
+    start: String,
+    end: String,
+    fee: i32,
+    drop_in_fee: i32,
+}
+
+#[server]
+async fn get_seasons() -> Result<Vec<Season>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::error;
+
+    require_permission(Permission::ManagePlayers).await?;
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        Season,
+        r#"
+        SELECT
+            s.season_id,
+            s.name,
+            s.start_date,
+            s.end_date,
+            s.fee,
+            s.drop_in_fee,
+            (SELECT COUNT(*) FROM gameday g WHERE g.season_id = s.season_id) as gameday_count
+        FROM season s
+        ORDER BY s.start_date DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(seasons) => Ok(seasons),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get seasons.".to_string(),
+            ))
+        }
+    }
+}
+
+/// A season as entered, checked before it is saved.
+#[cfg(feature = "ssr")]
+struct ValidSeason {
+    name: String,
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+    fee: i32,
+    drop_in_fee: i32,
+}
+
+#[cfg(feature = "ssr")]
+fn validate_season(input_season: InputSeason) -> Result<ValidSeason, ServerFnError> {
+    use chrono::NaiveDate;
+    use http::StatusCode;
+
+    let name = input_season.name.trim().to_string();
+    let start_date = NaiveDate::parse_from_str(&input_season.start, "%Y-%m-%d")?;
+    let end_date = NaiveDate::parse_from_str(&input_season.end, "%Y-%m-%d")?;
+    if name.is_empty()
+        || end_date < start_date
+        || input_season.fee < 0
+        || input_season.drop_in_fee < 0
+    {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::ServerError("Invalid season.".to_string()));
+    }
+    Ok(ValidSeason {
+        name,
+        start_date,
+        end_date,
+        fee: input_season.fee,
+        drop_in_fee: input_season.drop_in_fee,
+    })
+}
+
+/// Locks the seasons and fails if the dates overlap another season than `season_id`,
+/// a gameday belongs to one season.
+#[cfg(feature = "ssr")]
+async fn check_overlap(
+    conn: &mut sqlx::PgConnection,
+    season_id: Option<i32>,
+    season: &ValidSeason,
+) -> Result<(), ServerFnError> {
+    use http::StatusCode;
+
+    sqlx::query!("LOCK TABLE season IN EXCLUSIVE MODE")
+        .execute(&mut *conn)
+        .await?;
+    let overlapping = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM season
+            WHERE start_date <= $2 AND end_date >= $1
+              AND season_id IS DISTINCT FROM $3
+        ) as "overlapping!"
+        "#,
+        season.start_date,
+        season.end_date,
+        season_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if overlapping {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "The season overlaps another season.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Creates a season, the gamedays within its dates join it.
+#[server]
+async fn add_season(input_season: InputSeason) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::info;
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+    let season = validate_season(input_season)?;
+
+    let mut tx = get_db().begin().await?;
+    check_overlap(&mut tx, None, &season).await?;
+
+    let season_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO season (name, start_date, end_date, fee, drop_in_fee)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING season_id
+        "#,
+        season.name,
+        season.start_date,
+        season.end_date,
+        season.fee,
+        season.drop_in_fee
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let gameday_ids = season_gamedays(&mut tx, None, season.start_date, season.end_date).await?;
+    assign_seasons(&mut tx, &gameday_ids).await?;
+
+    record_event(
+        &mut *tx,
+        admin.player_id,
+        "season.create",
+        &format!("season:{}", season_id),
+        None,
+        Some(serde_json::json!({
+            "name": season.name,
+            "start_date": season.start_date,
+            "end_date": season.end_date,
+            "fee": season.fee,
+            "drop_in_fee": season.drop_in_fee,
+        })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} created season {:?}",
+        admin.player_id, season_id
+    );
+    Ok(())
+}
+
+/// Changes the name, dates or fees of a season. The gamedays that are no longer within
+/// its dates leave it and those that now are join it.
+#[server]
+async fn update_season(season_id: i32, input_season: InputSeason) -> Result<(), ServerFnError> {
+    use crate::audit::{record_event, snapshot};
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::info;
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+    let season = validate_season(input_season)?;
+
+    let mut tx = get_db().begin().await?;
+    check_overlap(&mut tx, Some(season_id), &season).await?;
+
+    let Some(before) = lock_season(&mut tx, season_id).await? else {
+        return Err(ServerFnError::ServerError("No season found.".to_string()));
+    };
+    sqlx::query!(
+        r#"
+        UPDATE season
+        SET name = $2, start_date = $3, end_date = $4, fee = $5, drop_in_fee = $6
+        WHERE season_id = $1
+        "#,
+        season_id,
+        season.name,
+        season.start_date,
+        season.end_date,
+        season.fee,
+        season.drop_in_fee
+    )
+    .execute(&mut *tx)
+    .await?;
+    let gameday_ids =
+        season_gamedays(&mut tx, Some(season_id), season.start_date, season.end_date).await?;
+    assign_seasons(&mut tx, &gameday_ids).await?;
+
+    record_event(
+        &mut *tx,
+        admin.player_id,
+        "season.update",
+        &format!("season:{}", season_id),
+        snapshot(&before),
+        Some(serde_json::json!({
+            "name": season.name,
+            "start_date": season.start_date,
+            "end_date": season.end_date,
+            "fee": season.fee,
+            "drop_in_fee": season.drop_in_fee,
+        })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} updated season {:?}",
+        admin.player_id, season_id
+    );
+    Ok(())
+}
+
+/// Deletes a season and its unpaid memberships, its gamedays are left without a season.
+/// Seasons with paid memberships are kept so the payments aren't lost.
+#[server]
+async fn delete_season(season_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use http::StatusCode;
+    use tracing::info;
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+
+    let mut tx = get_db().begin().await?;
+    let Some(season) = lock_season(&mut tx, season_id).await? else {
+        return Err(ServerFnError::ServerError("No season found.".to_string()));
+    };
+    let memberships = sqlx::query!(
+        r#"
+        SELECT player_id, amount, paid_at IS NOT NULL as "paid!"
+        FROM membership
+        WHERE season_id = $1
+        FOR UPDATE
+        "#,
+        season_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    if memberships.iter().any(|membership| membership.paid) {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::ServerError(
+            "The season has paid memberships.".to_string(),
+        ));
+    }
+
+    // The gamedays lose their season through the foreign key
+    sqlx::query!(
+        r#"
+        DELETE FROM season
+        WHERE season_id = $1
+        "#,
+        season_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_event(
+        &mut *tx,
+        admin.player_id,
+        "season.delete",
+        &format!("season:{}", season_id),
+        Some(serde_json::json!({
+            "season": season,
+            "memberships": memberships
+                .iter()
+                .map(|membership| serde_json::json!({
+                    "player_id": membership.player_id,
+                    "amount": membership.amount,
+                }))
+                .collect::<Vec<_>>(),
+        })),
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Player {:?} deleted season {:?}",
+        admin.player_id, season_id
+    );
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn lock_season(
+    conn: &mut sqlx::PgConnection,
+    season_id: i32,
+) -> Result<Option<Season>, sqlx::Error> {
+    sqlx::query_as!(
+        Season,
+        r#"
+        SELECT
+            season_id,
+            name,
+            start_date,
+            end_date,
+            fee,
+            drop_in_fee,
+            NULL::bigint as gameday_count
+        FROM season
+        WHERE season_id = $1
+        FOR UPDATE
+        "#,
+        season_id
+    )
+    .fetch_optional(conn)
+    .await
+}
+
+#[server]
+async fn get_memberships(season_id: i32) -> Result<Vec<PlayerMembership>, ServerFnError> {
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::error;
+
+    require_permission(Permission::ManagePlayers).await?;
+
+    let pool = get_db();
+    match sqlx::query_as!(
+        PlayerMembership,
+        r#"
+        SELECT
+            p.player_id,
+            p.name,
+            m.amount as "amount?",
+            m.paid_at
+        FROM player p
+        LEFT JOIN membership m ON m.player_id = p.player_id AND m.season_id = $1
+        ORDER BY m.amount IS NULL, p.name
+        "#,
+        season_id
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(memberships) => Ok(memberships),
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to get memberships.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Adds a membership or updates its amount and payment. Keeps the original payment time
+/// when a paid membership is saved again.
+#[server]
+async fn set_membership(
+    season_id: i32,
+    player_id: i32,
+    amount: i32,
+    paid: bool,
+) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use http::StatusCode;
+    use tracing::{error, info};
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+
+    if amount < 0 {
+        let opts = expect_context::<leptos_axum::ResponseOptions>();
+        opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::ServerError(
+            "The amount can't be negative.".to_string(),
+        ));
+    }
+
+    let result = async {
+        let mut tx = get_db().begin().await?;
+        let before = sqlx::query!(
+            r#"
+            SELECT amount, paid_at IS NOT NULL as "paid!"
+            FROM membership
+            WHERE season_id = $1 AND player_id = $2
+            FOR UPDATE
+            "#,
+            season_id,
+            player_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO membership (season_id, player_id, amount, paid_at)
+            VALUES ($1, $2, $3, CASE WHEN $4 THEN NOW() END)
+            ON CONFLICT (season_id, player_id) DO UPDATE
+            SET amount = $3,
+                paid_at = CASE WHEN $4 THEN COALESCE(membership.paid_at, NOW()) END
+            "#,
+            season_id,
+            player_id,
+            amount,
+            paid
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        record_event(
+            &mut *tx,
+            admin.player_id,
+            "membership.set",
+            &format!("season:{}/player:{}", season_id, player_id),
+            before
+                .map(|before| serde_json::json!({ "amount": before.amount, "paid": before.paid })),
+            Some(serde_json::json!({ "amount": amount, "paid": paid })),
+        )
+        .await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            info!(
+                "Player {:?} set membership of player {:?} in season {:?}, paid: {:?}",
+                admin.player_id, player_id, season_id, paid
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to update membership.".to_string(),
+            ))
+        }
+    }
+}
+
+#[server]
+async fn remove_membership(season_id: i32, player_id: i32) -> Result<(), ServerFnError> {
+    use crate::audit::record_event;
+    use crate::auth::require_permission;
+    use crate::database::get_db;
+    use tracing::{error, info};
+
+    let admin = require_permission(Permission::ManagePlayers).await?;
+
+    let result = async {
+        let mut tx = get_db().begin().await?;
+        let removed = sqlx::query!(
+            r#"
+            DELETE FROM membership
+            WHERE season_id = $1 AND player_id = $2
+            RETURNING amount, paid_at IS NOT NULL as "paid!"
+            "#,
+            season_id,
+            player_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(removed) = removed {
+            record_event(
+                &mut *tx,
+                admin.player_id,
+                "membership.remove",
+                &format!("season:{}/player:{}", season_id, player_id),
+                Some(serde_json::json!({ "amount": removed.amount, "paid": removed.paid })),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            info!(
+                "Player {:?} removed membership of player {:?} in season {:?}",
+                admin.player_id, player_id, season_id
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!("Database error: {:?}", e);
+            Err(ServerFnError::ServerError(
+                "Failed to remove membership.".to_string(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+
+static CLUB_TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+/// The timezone the club schedules its sessions in, `Europe/Stockholm` unless configured.
+///
+/// The server sets it from the config at startup and renders it into the page head,
+/// the hydrated client reads it from there so both show the same local time.
+pub fn club_timezone() -> Tz {
+    *CLUB_TIMEZONE.get_or_init(|| {
+        #[cfg(feature = "hydrate")]
+        if let Some(tz) = timezone_from_page() {
+            return tz;
+        }
+        chrono_tz::Europe::Stockholm
+    })
+}
+
+#[cfg(feature = "ssr")]
+pub fn set_club_timezone(tz: Tz) {
+    if CLUB_TIMEZONE.set(tz).is_err() {
+        tracing::error!("Club timezone already set");
+    }
+}
+
+#[cfg(feature = "hydrate")]
+fn timezone_from_page() -> Option<Tz> {
+    leptos::prelude::document()
+        .query_selector("meta[name='club-timezone']")
+        .ok()
+        .flatten()?
+        .get_attribute("content")?
+        .parse()
+        .ok()
+}
+
+/// Converts a stored UTC timestamp to the club's local time.
+pub fn to_club_time(date: DateTime<Utc>) -> DateTime<Tz> {
+    date.with_timezone(&club_timezone())
+}
+
+/// Interprets a wall-clock date and time in the club's timezone and converts it to UTC.
+///
+/// Ambiguous times, when the clocks go back, resolve to the first occurrence.
+/// Times skipped when the clocks go forward don't exist and return `None`.
+pub fn from_club_time(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    from_local_time(club_timezone(), date, time)
+}
+
+fn from_local_time(tz: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    match tz.from_local_datetime(&NaiveDateTime::new(date, time)) {
+        LocalResult::Single(local) => Some(local.with_timezone(&Utc)),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest.with_timezone(&Utc)),
+        LocalResult::None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Stockholm;
+
+    fn stockholm(date: &str, time: &str) -> Option<String> {
+        from_local_time(
+            Stockholm,
+            date.parse().unwrap(),
+            NaiveTime::parse_from_str(time, "%H:%M").unwrap(),
+        )
+        .map(|utc| utc.to_rfc3339())
+    }
+
+    #[test]
+    fn from_local_time_uses_the_offset_of_the_date() {
+        assert_eq!(
+            stockholm("2025-01-15", "19:00").as_deref(),
+            Some("2025-01-15T18:00:00+00:00")
+        );
+        assert_eq!(
+            stockholm("2025-07-15", "19:00").as_deref(),
+            Some("2025-07-15T17:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn from_local_time_handles_the_dst_changes() {
+        // The clocks go from 02:00 to 03:00
+        assert_eq!(stockholm("2025-03-30", "02:30"), None);
+        // The clocks go from 03:00 back to 02:00, the first 02:30 is still summer time
+        assert_eq!(
+            stockholm("2025-10-26", "02:30").as_deref(),
+            Some("2025-10-26T00:30:00+00:00")
+        );
+    }
+}
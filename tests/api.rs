@@ -0,0 +1,443 @@
+//! Calls the JSON API anonymously, with a bad token, a session and an API token and checks
+//! that only authenticated callers get through, then joins and leaves gamedays and cups
+//! through it. Needs the database from `DATABASE_URL`:
+//!
+//! ```bash
+//! cargo test --features ssr --test api
+//! ```
+#![cfg(feature = "ssr")]
+
+use axum::{body::Body, Router};
+use chrono::{DateTime, Duration, Utc};
+use cookie::{Cookie, CookieJar, Key};
+use gubbhockey::{
+    api,
+    auth::{delete_player_sessions, generate_api_token, hash_api_token},
+    config::{init_config, Config},
+    database::{get_db, init_db},
+};
+use http::{header, Method, Request, StatusCode};
+use serde_json::{json, Value};
+use std::{future::Future, sync::OnceLock};
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+use tower_cookies::CookieManagerLayer;
+use uuid::Uuid;
+
+const SESSION_KEY: &str = "api-test-key-0123456789abcdef0123456789";
+
+// Ids that don't exist, nothing is changed if the authentication lets the call through
+const ROUTES: &[(Method, &str)] = &[
+    (Method::GET, "/api/v1/me"),
+    (Method::GET, "/api/v1/me/registrations"),
+    (Method::GET, "/api/v1/gamedays"),
+    (Method::GET, "/api/v1/gamedays/-1"),
+    (Method::PUT, "/api/v1/gamedays/-1/registration"),
+    (Method::DELETE, "/api/v1/gamedays/-1/registration"),
+    (Method::GET, "/api/v1/cups"),
+    (Method::GET, "/api/v1/cups/-1"),
+    (Method::PUT, "/api/v1/cups/-1/registration"),
+    (Method::DELETE, "/api/v1/cups/-1/registration"),
+];
+
+/// The cookie value the server sets for a session.
+fn signed(session_id: Uuid) -> String {
+    let mut jar = CookieJar::new();
+    jar.signed_mut(&Key::derive_from(SESSION_KEY.as_bytes()))
+        .add(Cookie::new("session_id", session_id.to_string()));
+    jar.get("session_id").unwrap().value().to_string()
+}
+
+async fn call(
+    app: &Router,
+    method: Method,
+    path: &str,
+    (name, value): (header::HeaderName, String),
+) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header(name, value)
+        .body(Body::empty())
+        .unwrap();
+
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+/// Calls the API with an API token and a JSON body, returns the status and the JSON answer.
+async fn call_json(
+    app: &Router,
+    method: Method,
+    path: &str,
+    token: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+/// Runs a test on the runtime shared by all tests, the database pool is bound to the
+/// runtime it was created on.
+fn run<F: Future>(test: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| Runtime::new().expect("failed to start runtime"))
+        .block_on(test)
+}
+
+async fn setup() -> Router {
+    std::env::set_var("SESSION_KEYS", SESSION_KEY);
+    std::env::set_var("LATE_CANCELLATION_POLICY", "block");
+    std::env::set_var("SWISH_NUMBER", "1231234567");
+    init_config(Config::load().expect("invalid configuration"));
+    let _ = init_db().await;
+
+    Router::new()
+        .nest("/api/v1", api::router())
+        .layer(CookieManagerLayer::new())
+}
+
+/// Creates a player without a role with a session and an API token.
+async fn create_player() -> (i32, Uuid, String) {
+    let email = format!("api-test-{}@example.com", Uuid::new_v4());
+    let player_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO player (name, given_name, family_name, email)
+        VALUES ('Test Player', 'Test', 'Player', $1)
+        RETURNING player_id
+        "#,
+        email
+    )
+    .fetch_one(get_db())
+    .await
+    .unwrap();
+
+    let session_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO session (session_id, player_id, expires_at)
+        VALUES ($1, $2, NOW() + INTERVAL '1 hour')
+        "#,
+        session_id,
+        player_id
+    )
+    .execute(get_db())
+    .await
+    .unwrap();
+
+    let token = generate_api_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_token (player_id, name, token_hash)
+        VALUES ($1, 'Test', $2)
+        "#,
+        player_id,
+        hash_api_token(&token)
+    )
+    .execute(get_db())
+    .await
+    .unwrap();
+
+    (player_id, session_id, token)
+}
+
+/// Creates a gameday with room for one skater and one goalkeeper.
+async fn create_gameday(start_date: DateTime<Utc>) -> i32 {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO gameday (start_date, end_date, max_skaters, max_goalkeepers)
+        VALUES ($1, $1::timestamptz + INTERVAL '1 hour', 1, 1)
+        RETURNING gameday_id
+        "#,
+        start_date
+    )
+    .fetch_one(get_db())
+    .await
+    .unwrap()
+}
+
+async fn delete_gameday(gameday_id: i32) {
+    sqlx::query!("DELETE FROM gameday WHERE gameday_id = $1", gameday_id)
+        .execute(get_db())
+        .await
+        .unwrap();
+}
+
+async fn delete_player(player_id: i32) {
+    sqlx::query!("DELETE FROM player WHERE player_id = $1", player_id)
+        .execute(get_db())
+        .await
+        .unwrap();
+}
+
+#[test]
+fn api_requires_session_or_token() {
+    run(api_requires_session_or_token_test());
+}
+
+async fn api_requires_session_or_token_test() {
+    let app = setup().await;
+    let (player_id, session_id, token) = create_player().await;
+
+    let mut failures = Vec::new();
+
+    let unknown_token = generate_api_token();
+    for (method, path) in ROUTES {
+        for (caller, auth) in [
+            (
+                "anonymously",
+                (header::ACCEPT, "application/json".to_string()),
+            ),
+            (
+                "with unknown token",
+                (header::AUTHORIZATION, format!("Bearer {}", unknown_token)),
+            ),
+            (
+                "with unsigned session",
+                (header::COOKIE, format!("session_id={}", session_id)),
+            ),
+        ] {
+            let status = call(&app, method.clone(), path, auth).await;
+            if status != StatusCode::UNAUTHORIZED {
+                failures.push(format!("{} {} {}: {}", method, path, caller, status));
+            }
+        }
+    }
+
+    let session = (header::COOKIE, format!("session_id={}", signed(session_id)));
+    let status = call(&app, Method::GET, "/api/v1/me", session).await;
+    if status != StatusCode::OK {
+        failures.push(format!("/api/v1/me with session: {}", status));
+    }
+    let bearer = (header::AUTHORIZATION, format!("Bearer {}", token));
+    let status = call(&app, Method::GET, "/api/v1/me", bearer.clone()).await;
+    if status != StatusCode::OK {
+        failures.push(format!("/api/v1/me with token: {}", status));
+    }
+    let status = call(&app, Method::GET, "/api/v1/gamedays/-1", bearer).await;
+    if status != StatusCode::NOT_FOUND {
+        failures.push(format!("/api/v1/gamedays/-1 with token: {}", status));
+    }
+
+    // The schema is public so clients can be generated without an account
+    let accept = (header::ACCEPT, "application/json".to_string());
+    let status = call(&app, Method::GET, "/api/v1/openapi.json", accept).await;
+    if status != StatusCode::OK {
+        failures.push(format!("/api/v1/openapi.json anonymously: {}", status));
+    }
+
+    delete_player(player_id).await;
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn logging_out_everywhere_revokes_api_tokens() {
+    run(logging_out_everywhere_revokes_api_tokens_test());
+}
+
+async fn logging_out_everywhere_revokes_api_tokens_test() {
+    let app = setup().await;
+    let (player_id, session_id, token) = create_player().await;
+
+    let session = (header::COOKIE, format!("session_id={}", signed(session_id)));
+    let bearer = (header::AUTHORIZATION, format!("Bearer {}", token));
+    let before = (
+        call(&app, Method::GET, "/api/v1/me", session.clone()).await,
+        call(&app, Method::GET, "/api/v1/me", bearer.clone()).await,
+    );
+
+    let deleted = delete_player_sessions(get_db(), player_id).await.unwrap();
+
+    let after = (
+        call(&app, Method::GET, "/api/v1/me", session).await,
+        call(&app, Method::GET, "/api/v1/me", bearer).await,
+    );
+    delete_player(player_id).await;
+
+    assert_eq!(before, (StatusCode::OK, StatusCode::OK));
+    assert_eq!(deleted, (1, 1));
+    assert_eq!(after, (StatusCode::UNAUTHORIZED, StatusCode::UNAUTHORIZED));
+}
+
+#[test]
+fn joining_and_leaving_a_gameday() {
+    run(joining_and_leaving_a_gameday_test());
+}
+
+async fn joining_and_leaving_a_gameday_test() {
+    let app = setup().await;
+    let (first_id, _, first) = create_player().await;
+    let (second_id, _, second) = create_player().await;
+    let gameday_id = create_gameday(Utc::now() + Duration::days(30)).await;
+
+    // A season of its own so the drop-in fee doesn't depend on the seasons in the database
+    let season_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO season (name, start_date, end_date, drop_in_fee)
+        VALUES ('API test', '2999-01-01', '2999-01-01', 100)
+        RETURNING season_id
+        "#
+    )
+    .fetch_one(get_db())
+    .await
+    .unwrap();
+    sqlx::query!(
+        "UPDATE gameday SET season_id = $1 WHERE gameday_id = $2",
+        season_id,
+        gameday_id
+    )
+    .execute(get_db())
+    .await
+    .unwrap();
+
+    let registration = format!("/api/v1/gamedays/{}/registration", gameday_id);
+    let joined = call_json(&app, Method::PUT, &registration, &first, None).await;
+    let joined_again = call_json(&app, Method::PUT, &registration, &first, None).await;
+    let waitlisted = call_json(&app, Method::PUT, &registration, &second, None).await;
+    let left = call_json(&app, Method::DELETE, &registration, &first, None).await;
+    let left_again = call_json(&app, Method::DELETE, &registration, &first, None).await;
+    let (_, details) = call_json(
+        &app,
+        Method::GET,
+        &format!("/api/v1/gamedays/{}", gameday_id),
+        &second,
+        None,
+    )
+    .await;
+
+    delete_gameday(gameday_id).await;
+    sqlx::query!("DELETE FROM season WHERE season_id = $1", season_id)
+        .execute(get_db())
+        .await
+        .unwrap();
+    delete_player(first_id).await;
+    delete_player(second_id).await;
+
+    assert_eq!(joined.0, StatusCode::OK);
+    assert_eq!(joined.1["confirmed"], json!(true));
+    assert_eq!(joined.1["drop_in_payment"]["amount"], json!(100));
+    assert_eq!(joined.1["drop_in_payment"]["payee"], json!("1231234567"));
+    assert_eq!(joined_again.0, StatusCode::CONFLICT);
+    assert_eq!(waitlisted.0, StatusCode::OK);
+    assert_eq!(
+        waitlisted.1,
+        json!({ "confirmed": false, "drop_in_payment": null })
+    );
+    assert_eq!(left.0, StatusCode::NO_CONTENT);
+    assert_eq!(left_again.0, StatusCode::NOT_FOUND);
+    // The freed spot went to the waitlist
+    assert_eq!(details["players"][0]["player_id"], json!(second_id));
+    assert_eq!(details["waitlist"], json!([]));
+}
+
+#[test]
+fn late_and_started_gamedays_are_closed() {
+    run(late_and_started_gamedays_are_closed_test());
+}
+
+async fn late_and_started_gamedays_are_closed_test() {
+    let app = setup().await;
+    let (player_id, _, token) = create_player().await;
+    let soon = create_gameday(Utc::now() + Duration::hours(1)).await;
+    let started = create_gameday(Utc::now() - Duration::minutes(30)).await;
+
+    let soon_registration = format!("/api/v1/gamedays/{}/registration", soon);
+    let joined = call_json(&app, Method::PUT, &soon_registration, &token, None).await;
+    let left = call_json(&app, Method::DELETE, &soon_registration, &token, None).await;
+    let (_, registrations) =
+        call_json(&app, Method::GET, "/api/v1/me/registrations", &token, None).await;
+    let started_registration = format!("/api/v1/gamedays/{}/registration", started);
+    let joined_started = call_json(&app, Method::PUT, &started_registration, &token, None).await;
+    sqlx::query!(
+        "INSERT INTO player_gameday (player_id, gameday_id, is_goalkeeper) VALUES ($1, $2, FALSE)",
+        player_id,
+        started
+    )
+    .execute(get_db())
+    .await
+    .unwrap();
+    let left_started = call_json(&app, Method::DELETE, &started_registration, &token, None).await;
+
+    delete_gameday(soon).await;
+    delete_gameday(started).await;
+    delete_player(player_id).await;
+
+    assert_eq!(joined.0, StatusCode::OK);
+    assert_eq!(joined.1["confirmed"], json!(true));
+    // LATE_CANCELLATION_POLICY is block in these tests
+    assert_eq!(left.0, StatusCode::CONFLICT);
+    assert_eq!(registrations["gamedays"][0]["gameday_id"], json!(soon));
+    assert_eq!(
+        registrations["gamedays"][0]["late_cancellation"],
+        json!(true)
+    );
+    assert_eq!(joined_started.0, StatusCode::CONFLICT);
+    // Whatever the policy, the attendance of a started gameday is kept
+    assert_eq!(left_started.0, StatusCode::CONFLICT);
+}
+
+#[test]
+fn joining_and_leaving_a_cup() {
+    run(joining_and_leaving_a_cup_test());
+}
+
+async fn joining_and_leaving_a_cup_test() {
+    let app = setup().await;
+    let (player_id, _, token) = create_player().await;
+    let cup_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO cup (start_date, end_date, title, info)
+        VALUES (NOW() + INTERVAL '30 days', NOW() + INTERVAL '31 days', 'API test', '')
+        RETURNING cup_id
+        "#
+    )
+    .fetch_one(get_db())
+    .await
+    .unwrap();
+
+    let registration = format!("/api/v1/cups/{}/registration", cup_id);
+    let position = Some(json!({ "position": "goalkeeper" }));
+    let joined = call_json(&app, Method::PUT, &registration, &token, position.clone()).await;
+    let joined_again = call_json(&app, Method::PUT, &registration, &token, position).await;
+    let (_, details) = call_json(
+        &app,
+        Method::GET,
+        &format!("/api/v1/cups/{}", cup_id),
+        &token,
+        None,
+    )
+    .await;
+    let left = call_json(&app, Method::DELETE, &registration, &token, None).await;
+    let left_again = call_json(&app, Method::DELETE, &registration, &token, None).await;
+
+    sqlx::query!("DELETE FROM cup WHERE cup_id = $1", cup_id)
+        .execute(get_db())
+        .await
+        .unwrap();
+    delete_player(player_id).await;
+
+    assert_eq!(joined.0, StatusCode::NO_CONTENT);
+    assert_eq!(joined_again.0, StatusCode::CONFLICT);
+    assert_eq!(
+        details["players"],
+        json!([{ "name": "Test Player", "position": "goalkeeper" }])
+    );
+    assert_eq!(left.0, StatusCode::NO_CONTENT);
+    assert_eq!(left_again.0, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,291 @@
+//! Calls every server function anonymously and as a player without a role and checks that
+//! the guards deny them. Needs the database from `DATABASE_URL`:
+//!
+//! ```bash
+//! cargo test --features ssr --test authorization
+//! ```
+#![cfg(feature = "ssr")]
+
+use axum::{body::Body, routing::post, Router};
+use cookie::{Cookie, CookieJar, Key};
+use gubbhockey::{
+    config::{init_config, Config},
+    database::{get_db, init_db},
+};
+use http::{header, Request, StatusCode};
+use leptos::server_fn::axum::server_fn_paths;
+use tower::ServiceExt;
+use tower_cookies::CookieManagerLayer;
+use uuid::Uuid;
+
+const SESSION_KEY: &str = "authorization-test-key-0123456789abcdef";
+
+/// Anyone may call these, the login flow and the public pages use them.
+const PUBLIC: &[&str] = &[
+    "get_auth_url",
+    "set_loggin_session",
+    "logout",
+    "user_from_session",
+    "get_next_5_gamedays",
+    "get_gameday_by_id",
+    "get_cup_by_id",
+    "get_players_by_cup_id",
+    "get_all_cups",
+    "get_cancellation_policy",
+    "get_cup_lineup",
+];
+
+// Ids that don't exist, nothing is changed if a guard lets the call through
+const PLAYER: &[(&str, &str)] = &[
+    ("join_gameday", "gameday_id=-1"),
+    ("leave_gameday", "gameday_id=-1"),
+    ("join_cup", "position=forward&cup_id=-1"),
+    ("leave_cup", "cup_id=-1"),
+    ("get_gamedays_by_player", ""),
+    ("get_cups_by_player", ""),
+    ("get_players_by_gameday", "gameday_id=-1"),
+    ("get_waitlist_by_gameday", "gameday_id=-1"),
+    ("acknowledge_time_change", "gameday_id=-1"),
+    ("update_player_position", "position=goalkeeper"),
+    ("get_calendar_url", ""),
+    ("reset_calendar_token", ""),
+    ("get_notification_settings", ""),
+    ("get_sessions", ""),
+    (
+        "revoke_session",
+        "session_id=00000000-0000-0000-0000-000000000000",
+    ),
+    ("logout_everywhere", ""),
+    ("get_my_attendance_stats", ""),
+    ("get_teams", "gameday_id=-1"),
+    ("get_guests_by_gameday", "gameday_id=-1"),
+    ("add_guest", "gameday_id=-1&name=Guest&is_goalkeeper=false"),
+    ("remove_guest", "guest_id=-1"),
+    ("get_drop_in_payment", "gameday_id=-1"),
+    ("get_api_tokens", ""),
+    ("create_api_token", "name=Test"),
+    ("delete_api_token", "api_token_id=-1"),
+    (
+        "update_notification_settings",
+        "settings[notify_registration]=true&settings[notify_waitlist]=true\
+         &settings[notify_cancellation]=true&settings[notify_reminder]=true\
+         &settings[notify_goalkeeper_shortage]=true",
+    ),
+];
+
+const PRIVILEGED: &[(&str, &str)] = &[
+    (
+        "add_date",
+        "input_date[date]=2000-01-01&input_date[start]=19:00&input_date[end]=20:00\
+         &input_date[max_skaters]=20&input_date[max_goalkeepers]=2",
+    ),
+    (
+        "update_gameday",
+        "input[gameday_id]=-1&input[date]=2000-01-01&input[start]=19:00&input[end]=20:00\
+         &input[max_skaters]=20&input[max_goalkeepers]=2",
+    ),
+    ("delete_gameday", "gameday_id=-1&redirect_on_delete=false"),
+    ("get_gameday_by_id_admin", "id=-1"),
+    ("get_all_gamedays", ""),
+    ("get_all_series", ""),
+    (
+        "shift_series",
+        "input[series_id]=-1&input[weekday]=1&input[start]=19:00&input[end]=20:00",
+    ),
+    ("delete_series", "series_id=-1"),
+    (
+        "add_cup",
+        "input_cup[date]=2000-01-01&input_cup[start]=10:00&input_cup[end]=16:00\
+         &input_cup[title]=Cup&input_cup[info]=",
+    ),
+    (
+        "update_cup",
+        "input[cup_id]=-1&input[date]=2000-01-01&input[start]=10:00&input[end]=16:00\
+         &input[title]=Cup&input[info]=",
+    ),
+    ("get_cup_by_id_admin", "id=-1"),
+    ("get_all_players", ""),
+    ("set_role", "player_id=-1&role=admin"),
+    ("revoke_player_sessions", "player_id=-1"),
+    ("search_players", "gameday_id=-1&query=test"),
+    ("add_to_roster", "gameday_id=-1&player_id=-1"),
+    ("remove_from_roster", "gameday_id=-1&player_id=-1"),
+    (
+        "change_roster_position",
+        "gameday_id=-1&player_id=-1&is_goalkeeper=true",
+    ),
+    (
+        "set_attendance",
+        "gameday_id=-1&player_id=-1&attendance=no_show",
+    ),
+    ("mark_remaining_attended", "gameday_id=-1"),
+    ("set_guest_attendance", "guest_id=-1"),
+    ("get_attendance_stats", ""),
+    ("set_skill", "player_id=-1&skill=3"),
+    ("generate_teams", "gameday_id=-1"),
+    ("lock_teams", "gameday_id=-1&locked=true"),
+    ("add_cup_team", "cup_id=-1&name=Team"),
+    ("remove_cup_team", "cup_team_id=-1"),
+    (
+        "place_cup_player",
+        "cup_id=-1&player_id=-1&spot[cup_team_id]=-1&spot[position]=center&spot[line]=1",
+    ),
+    ("unplace_cup_player", "cup_id=-1&player_id=-1"),
+    ("publish_cup_lineup", "cup_id=-1&published=true"),
+    (
+        "get_audit_events",
+        "filter[action]=&filter[actor]=&filter[target]=&page=0",
+    ),
+    ("get_audit_actions", ""),
+    ("get_seasons", ""),
+    (
+        "add_season",
+        "input_season[name]=Season&input_season[start]=2000-01-01&input_season[end]=2000-06-30\
+         &input_season[fee]=0&input_season[drop_in_fee]=0",
+    ),
+    (
+        "update_season",
+        "season_id=-1&input_season[name]=Season&input_season[start]=2000-01-01\
+         &input_season[end]=2000-06-30&input_season[fee]=0&input_season[drop_in_fee]=0",
+    ),
+    ("delete_season", "season_id=-1"),
+    ("get_memberships", "season_id=-1"),
+    (
+        "set_membership",
+        "season_id=-1&player_id=-1&amount=0&paid=true",
+    ),
+    ("remove_membership", "season_id=-1&player_id=-1"),
+    ("get_unpaid_drop_in_fees", ""),
+    ("mark_drop_in_paid", "gameday_id=-1&player_id=-1"),
+    ("import_bank_statement", "statement=a,b"),
+];
+
+/// Server function name without the hash leptos appends to the path.
+fn name(path: &str) -> &str {
+    path.trim_start_matches("/api/")
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+}
+
+fn find_path(function: &str) -> &'static str {
+    server_fn_paths()
+        .map(|(path, _)| path)
+        .find(|path| name(path) == function)
+        .unwrap_or_else(|| panic!("{} is not a registered server function", function))
+}
+
+/// The cookie value the server sets for a session.
+fn signed(session_id: Uuid) -> String {
+    let mut jar = CookieJar::new();
+    jar.signed_mut(&Key::derive_from(SESSION_KEY.as_bytes()))
+        .add(Cookie::new("session_id", session_id.to_string()));
+    jar.get("session_id").unwrap().value().to_string()
+}
+
+async fn call(app: &Router, function: &str, body: &str, cookie: Option<String>) -> StatusCode {
+    let mut request = Request::post(find_path(function))
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header(header::ACCEPT, "application/json");
+    if let Some(cookie) = cookie {
+        request = request.header(header::COOKIE, format!("session_id={}", cookie));
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+async fn setup() -> Router {
+    std::env::set_var("SESSION_KEYS", SESSION_KEY);
+    init_config(Config::load().expect("invalid configuration"));
+    let _ = init_db().await;
+
+    Router::new()
+        .route("/api/*fn_name", post(leptos_axum::handle_server_fns))
+        .layer(CookieManagerLayer::new())
+}
+
+/// Creates a player without a role and a session for it.
+async fn create_player() -> (i32, Uuid) {
+    let email = format!("authorization-test-{}@example.com", Uuid::new_v4());
+    let player_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO player (name, given_name, family_name, email)
+        VALUES ('Test Player', 'Test', 'Player', $1)
+        RETURNING player_id
+        "#,
+        email
+    )
+    .fetch_one(get_db())
+    .await
+    .unwrap();
+
+    let session_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO session (session_id, player_id, expires_at)
+        VALUES ($1, $2, NOW() + INTERVAL '1 hour')
+        "#,
+        session_id,
+        player_id
+    )
+    .execute(get_db())
+    .await
+    .unwrap();
+
+    (player_id, session_id)
+}
+
+async fn delete_player(player_id: i32) {
+    sqlx::query!("DELETE FROM player WHERE player_id = $1", player_id)
+        .execute(get_db())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn server_functions_deny_unauthorized_callers() {
+    let app = setup().await;
+    let (player_id, session_id) = create_player().await;
+
+    let mut failures = Vec::new();
+
+    // A new server function has to be added to one of the lists
+    for (path, _) in server_fn_paths() {
+        let function = name(path);
+        let known = PUBLIC.contains(&function)
+            || PLAYER
+                .iter()
+                .chain(PRIVILEGED)
+                .any(|(name, _)| *name == function);
+        if !known {
+            failures.push(format!("{} is not covered by the test", function));
+        }
+    }
+
+    for (function, body) in PLAYER.iter().chain(PRIVILEGED) {
+        let status = call(&app, function, body, None).await;
+        if status != StatusCode::UNAUTHORIZED {
+            failures.push(format!("{} anonymously: {}", function, status));
+        }
+    }
+
+    for (function, body) in PRIVILEGED {
+        let status = call(&app, function, body, Some(signed(session_id))).await;
+        if status != StatusCode::FORBIDDEN {
+            failures.push(format!("{} as player: {}", function, status));
+        }
+    }
+
+    // Unknown and unsigned sessions are treated like no session
+    let status = call(&app, "get_all_players", "", Some(signed(Uuid::new_v4()))).await;
+    if status != StatusCode::UNAUTHORIZED {
+        failures.push(format!("get_all_players with unknown session: {}", status));
+    }
+    let status = call(&app, "get_all_players", "", Some(session_id.to_string())).await;
+    if status != StatusCode::UNAUTHORIZED {
+        failures.push(format!("get_all_players with unsigned session: {}", status));
+    }
+
+    delete_player(player_id).await;
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
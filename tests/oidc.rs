@@ -0,0 +1,254 @@
+//! Discovers a mock OIDC issuer that signs its ID tokens with a local key and checks that
+//! only ID tokens with a valid signature, nonce, issuer, audience and expiry are accepted,
+//! and that rotated signing keys are picked up. Doesn't need the database:
+//!
+//! ```bash
+//! cargo test --features ssr --test oidc
+//! ```
+#![cfg(feature = "ssr")]
+
+use std::sync::{Arc, RwLock};
+
+use axum::{extract::State, routing::get, Json, Router};
+use chrono::{Duration, Utc};
+use gubbhockey::{
+    auth::{AuthClient, AuthConfig, IdTokenError},
+    config::{AuthSettings, Secret},
+};
+use openidconnect::{
+    core::{
+        CoreIdToken, CoreIdTokenClaims, CoreIdTokenFields, CoreJsonWebKey, CoreJsonWebKeySet,
+        CoreJwsSigningAlgorithm, CoreProviderMetadata, CoreResponseType, CoreRsaPrivateSigningKey,
+        CoreSubjectIdentifierType, CoreTokenResponse, CoreTokenType,
+    },
+    AccessToken, Audience, AuthUrl, ClaimsVerificationError, EmptyAdditionalClaims,
+    EmptyAdditionalProviderMetadata, EmptyExtraTokenFields, EndUserEmail, IssuerUrl, JsonWebKeyId,
+    JsonWebKeySetUrl, Nonce, PrivateSigningKey, ResponseTypes, SignatureVerificationError,
+    StandardClaims, SubjectIdentifier, TokenUrl,
+};
+use tokio::net::TcpListener;
+
+const CLIENT_ID: &str = "gubbhockey";
+const NONCE: &str = "nonce-of-this-login";
+
+fn signing_key(kid: &str) -> CoreRsaPrivateSigningKey {
+    CoreRsaPrivateSigningKey::from_pem(
+        include_str!("fixtures/oidc_signing_key.pem"),
+        Some(JsonWebKeyId::new(kid.to_string())),
+    )
+    .unwrap()
+}
+
+fn other_key(kid: &str) -> CoreRsaPrivateSigningKey {
+    CoreRsaPrivateSigningKey::from_pem(
+        include_str!("fixtures/oidc_other_key.pem"),
+        Some(JsonWebKeyId::new(kid.to_string())),
+    )
+    .unwrap()
+}
+
+/// The keys the issuer publishes, they can be swapped while the issuer is running.
+type PublishedKeys = Arc<RwLock<Vec<CoreJsonWebKey>>>;
+
+/// Serves the discovery document and the JWKS on a free port, returns the issuer URL.
+async fn start_issuer(keys: PublishedKeys) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let issuer = format!("http://{}/", listener.local_addr().unwrap());
+
+    let metadata = CoreProviderMetadata::new(
+        IssuerUrl::new(issuer.clone()).unwrap(),
+        AuthUrl::new(format!("{}authorize", issuer)).unwrap(),
+        JsonWebKeySetUrl::new(format!("{}jwks", issuer)).unwrap(),
+        vec![ResponseTypes::new(vec![CoreResponseType::Code])],
+        vec![CoreSubjectIdentifierType::Public],
+        vec![CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256],
+        EmptyAdditionalProviderMetadata {},
+    )
+    .set_token_endpoint(Some(TokenUrl::new(format!("{}token", issuer)).unwrap()));
+
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(move || async move { Json(metadata) }),
+        )
+        .route(
+            "/jwks",
+            get(|State(keys): State<PublishedKeys>| async move {
+                Json(CoreJsonWebKeySet::new(keys.read().unwrap().clone()))
+            }),
+        )
+        .with_state(keys);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    issuer
+}
+
+async fn discover(issuer: &str) -> AuthClient {
+    let config = AuthConfig::new(&AuthSettings {
+        issuer_url: issuer.to_string(),
+        client_id: CLIENT_ID.to_string(),
+        client_secret: Secret::from("secret".to_string()),
+        redirect_url: "http://localhost:3000/auth".to_string(),
+        logout_url: format!("{}logout", issuer),
+        userinfo_url: None,
+    })
+    .unwrap();
+    AuthClient::discover(config).await.unwrap()
+}
+
+/// ID token claims as the issuer would send them for this login.
+fn claims(issuer: &str) -> CoreIdTokenClaims {
+    CoreIdTokenClaims::new(
+        IssuerUrl::new(issuer.to_string()).unwrap(),
+        vec![Audience::new(CLIENT_ID.to_string())],
+        Utc::now() + Duration::minutes(5),
+        Utc::now(),
+        StandardClaims::new(SubjectIdentifier::new("player-1".to_string()))
+            .set_email(Some(EndUserEmail::new("player@example.com".to_string()))),
+        EmptyAdditionalClaims {},
+    )
+    .set_nonce(Some(Nonce::new(NONCE.to_string())))
+}
+
+fn token_response(claims: CoreIdTokenClaims, key: &CoreRsaPrivateSigningKey) -> CoreTokenResponse {
+    let access_token = AccessToken::new("access-token".to_string());
+    let id_token = CoreIdToken::new(
+        claims,
+        key,
+        CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256,
+        Some(&access_token),
+        None,
+    )
+    .unwrap();
+
+    CoreTokenResponse::new(
+        access_token,
+        CoreTokenType::Bearer,
+        CoreIdTokenFields::new(Some(id_token), EmptyExtraTokenFields {}),
+    )
+}
+
+async fn verify(client: &AuthClient, response: &CoreTokenResponse) -> Result<(), IdTokenError> {
+    client
+        .verify_id_token(response, &Nonce::new(NONCE.to_string()))
+        .await
+        .map(|_| ())
+}
+
+#[tokio::test]
+async fn id_tokens_are_verified_against_the_issuer() {
+    let key = signing_key("key-1");
+    let keys = Arc::new(RwLock::new(vec![key.as_verification_key()]));
+    let issuer = start_issuer(keys).await;
+    let client = discover(&issuer).await;
+
+    let claims = client
+        .verify_id_token(
+            &token_response(self::claims(&issuer), &key),
+            &Nonce::new(NONCE.to_string()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(claims.subject().as_str(), "player-1");
+    assert_eq!(
+        claims.email().map(|email| email.as_str()),
+        Some("player@example.com")
+    );
+
+    // Signed by a key the issuer doesn't publish, with the kid of the published one
+    assert!(matches!(
+        verify(
+            &client,
+            &token_response(self::claims(&issuer), &other_key("key-1"))
+        )
+        .await,
+        Err(IdTokenError::Claims(
+            ClaimsVerificationError::SignatureVerification(
+                SignatureVerificationError::CryptoError(_)
+            )
+        ))
+    ));
+
+    // Issued for another login attempt
+    assert!(matches!(
+        verify(
+            &client,
+            &token_response(
+                self::claims(&issuer).set_nonce(Some(Nonce::new("another-login".to_string()))),
+                &key,
+            )
+        )
+        .await,
+        Err(IdTokenError::Claims(ClaimsVerificationError::InvalidNonce(
+            _
+        )))
+    ));
+
+    // Issued by someone else
+    assert!(matches!(
+        verify(
+            &client,
+            &token_response(self::claims("http://127.0.0.1:1/"), &key)
+        )
+        .await,
+        Err(IdTokenError::Claims(
+            ClaimsVerificationError::InvalidIssuer(_)
+        ))
+    ));
+
+    // Issued for another client
+    assert!(matches!(
+        verify(
+            &client,
+            &token_response(
+                self::claims(&issuer)
+                    .set_audiences(vec![Audience::new("another-client".to_string())]),
+                &key,
+            )
+        )
+        .await,
+        Err(IdTokenError::Claims(
+            ClaimsVerificationError::InvalidAudience(_)
+        ))
+    ));
+
+    // Expired
+    assert!(matches!(
+        verify(
+            &client,
+            &token_response(
+                self::claims(&issuer)
+                    .set_expiration(Utc::now() - Duration::minutes(5))
+                    .set_issue_time(Utc::now() - Duration::minutes(10)),
+                &key,
+            )
+        )
+        .await,
+        Err(IdTokenError::Claims(ClaimsVerificationError::Expired(_)))
+    ));
+}
+
+#[tokio::test]
+async fn rotated_signing_keys_are_fetched_again() {
+    let old_key = signing_key("key-1");
+    let new_key = other_key("key-2");
+    let keys = Arc::new(RwLock::new(vec![old_key.as_verification_key()]));
+    let issuer = start_issuer(keys.clone()).await;
+    let client = discover(&issuer).await;
+
+    // The provider starts signing with a new key after the server discovered it
+    *keys.write().unwrap() = vec![new_key.as_verification_key()];
+    verify(&client, &token_response(claims(&issuer), &new_key))
+        .await
+        .unwrap();
+
+    // The retired key isn't trusted any longer
+    assert!(matches!(
+        verify(&client, &token_response(claims(&issuer), &old_key)).await,
+        Err(IdTokenError::Claims(
+            ClaimsVerificationError::SignatureVerification(
+                SignatureVerificationError::NoMatchingKey
+            )
+        ))
+    ));
+}